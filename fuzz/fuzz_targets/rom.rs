@@ -0,0 +1,21 @@
+#![no_main]
+
+use chip8::Emulator;
+use libfuzzer_sys::fuzz_target;
+
+// Runs the input as a ROM for a second's worth of frames, pressing keys
+// taken from the frame number. Errors are fine, panics are not.
+fuzz_target!(|rom: &[u8]| {
+    let mut emulator = Emulator::with_seed(0);
+    if emulator.load_rom(rom).is_err() {
+        return;
+    }
+    for frame in 0..60u16 {
+        let keys = core::array::from_fn(|key| frame >> (key % 6) & 1 == 1);
+        if emulator.run_frame(&keys).is_err() {
+            break;
+        }
+    }
+    emulator.display_string();
+    emulator.registers_string();
+});
@@ -0,0 +1,24 @@
+#![no_main]
+
+use chip8::Emulator;
+use libfuzzer_sys::fuzz_target;
+
+// Steps an arbitrary machine, ticking the timers every few instructions,
+// and checks that saving and loading its state survives whatever it ends
+// up in.
+fuzz_target!(|emulator: Emulator| {
+    let mut emulator = emulator;
+    for i in 0..1000 {
+        if emulator.step().is_err() {
+            break;
+        }
+        if i % 10 == 0 {
+            emulator.tick_timers();
+        }
+    }
+    emulator.display_string();
+    let state = emulator.save_state();
+    let mut loaded = Emulator::with_seed(0);
+    loaded.load_state(&state).unwrap();
+    assert_eq!(loaded.save_state(), state);
+});
@@ -0,0 +1,81 @@
+/* Generated with cbindgen from src/capi.rs, do not edit. Regenerate with
+ * `cbindgen --config cbindgen.toml --output include/chip8.h`. */
+
+#ifndef CHIP8_H
+#define CHIP8_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * A CHIP-8 machine: memory, registers, timers, keypad and display.
+ */
+typedef struct Emulator Emulator;
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/**
+ * Creates an emulator whose CXNN results are determined by `seed`. Free it
+ * with [`chip8_free`].
+ */
+Emulator *chip8_new(uint64_t seed);
+
+/**
+ * Frees an emulator created by [`chip8_new`]. Does nothing when it's null.
+ */
+void chip8_free(Emulator *emulator);
+
+/**
+ * Loads the `len` bytes at `rom` at 0x200. Returns 0, or -1 when the ROM
+ * doesn't fit in memory.
+ */
+int chip8_load_rom(Emulator *emulator, const uint8_t *rom, size_t len);
+
+/**
+ * Restarts the loaded ROM, restoring memory to how it was loaded and
+ * clearing the registers, stack, timers, keys and display.
+ */
+void chip8_reset(Emulator *emulator);
+
+/**
+ * Resets the emulator to how [`chip8_new`] creates it, with no ROM loaded.
+ */
+void chip8_hard_reset(Emulator *emulator);
+
+/**
+ * Executes the instruction at PC. Returns 1 when the display changed, 0
+ * when it didn't, and -1 when the emulator stopped on an error.
+ */
+int chip8_step(Emulator *emulator);
+
+/**
+ * Decrements the delay and sound timers, to call at 60Hz. Returns 1 while
+ * the sound timer is still running, 0 otherwise.
+ */
+int chip8_tick_timers(Emulator *emulator);
+
+/**
+ * Returns the display as 64 × 64 bytes, row by row, 1 for set pixels and 0
+ * for unset ones, of which the rows past [`chip8_height`] are unused. The
+ * pointer stays valid until the emulator is freed.
+ */
+const uint8_t *chip8_framebuffer(const Emulator *emulator);
+
+/**
+ * Returns the number of display rows in use, 32, or 64 for hires ROMs.
+ */
+uintptr_t chip8_height(const Emulator *emulator);
+
+/**
+ * Sets whether keypad key `key`, 0 to 15, is held. Other keys are ignored.
+ */
+void chip8_set_key(Emulator *emulator, uint8_t key, bool pressed);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif
+
+#endif  /* CHIP8_H */
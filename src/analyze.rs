@@ -0,0 +1,383 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::emulator::{is_hires, HIRES_CLS, HIRES_ENTRY};
+use crate::{decode, Instruction, Platform, Quirks, MEMORY_SIZE};
+
+/// Something wrong or suspicious found in a ROM by [`analyze`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// The ROM doesn't fit in memory from its load address on.
+    TooLarge { size: usize, max: usize },
+    /// Instructions are two bytes, so an odd length hints at a truncated or
+    /// padded ROM.
+    OddLength { size: usize },
+    /// A reachable instruction the emulator doesn't execute, with the
+    /// extension it belongs to when it's from one.
+    Unsupported {
+        address: usize,
+        opcode: u16,
+        extension: Option<&'static str>,
+    },
+    /// A jump or call below the load address, into the interpreter's memory.
+    JumpBelowProgram { address: usize, target: usize },
+}
+
+impl Diagnostic {
+    /// Returns true when the ROM can't run at all.
+    pub fn is_error(&self) -> bool {
+        matches!(self, Diagnostic::TooLarge { .. })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Diagnostic::TooLarge { size, max } => write!(
+                f,
+                "ROM is {} bytes, but at most {} bytes fit in memory",
+                size, max
+            ),
+            Diagnostic::OddLength { size } => write!(f, "ROM has an odd length of {} bytes", size),
+            Diagnostic::Unsupported {
+                address,
+                opcode,
+                extension,
+            } => {
+                write!(f, "unsupported opcode {:04X} at {:#05X}", opcode, address)?;
+                match extension {
+                    Some(extension) => write!(f, ", a {} instruction", extension),
+                    None => Ok(()),
+                }
+            }
+            Diagnostic::JumpBelowProgram { address, target } => write!(
+                f,
+                "jump from {:#05X} to {:#05X}, below the program",
+                address, target
+            ),
+        }
+    }
+}
+
+/// Checks that `rom` fits in memory when loaded at `load_address`, and
+/// follows the code reachable from there for unsupported instructions and
+/// jumps out of the program. Jumps through BNNN can't be followed, so code
+/// only reached through them isn't checked.
+pub fn analyze(rom: &[u8], load_address: usize) -> Vec<Diagnostic> {
+    let max = MEMORY_SIZE.saturating_sub(load_address);
+    if rom.len() > max {
+        return vec![Diagnostic::TooLarge {
+            size: rom.len(),
+            max,
+        }];
+    }
+    let mut diagnostics = Vec::new();
+    if rom.len() % 2 == 1 {
+        diagnostics.push(Diagnostic::OddLength { size: rom.len() });
+    }
+
+    let (_, found) = trace(rom, load_address);
+    diagnostics.extend(found);
+    diagnostics.sort_by_key(|diagnostic| match *diagnostic {
+        Diagnostic::Unsupported { address, .. } | Diagnostic::JumpBelowProgram { address, .. } => {
+            address
+        }
+        _ => 0,
+    });
+    diagnostics
+}
+
+/// Guesses the quirks `rom` relies on from idioms in its code, for ROMs
+/// without known settings. Returns `quirks` with the changes made, and the
+/// reason for each.
+pub fn guess_quirks(rom: &[u8], load_address: usize, mut quirks: Quirks) -> (Quirks, Vec<String>) {
+    let (code, diagnostics) = trace(rom, load_address);
+    let mut reasons = Vec::new();
+    let previous = |address: usize| code.get(&address.wrapping_sub(2)).copied();
+    let superchip = diagnostics.iter().any(|diagnostic| {
+        matches!(
+            diagnostic,
+            Diagnostic::Unsupported {
+                extension: Some("SUPER-CHIP"),
+                ..
+            }
+        )
+    });
+
+    for (&address, &opcode) in &code {
+        match decode(opcode) {
+            Some(Instruction::ShiftRight { x, y } | Instruction::ShiftLeft { x, y })
+                if y != x && y != 0 && !quirks.shift_vy =>
+            {
+                quirks.shift_vy = true;
+                reasons.push(format!(
+                    "shift_vy: the shift at {:#05X} names V{:X} as its source",
+                    address, y
+                ));
+            }
+            Some(Instruction::ShiftRight { x, y } | Instruction::ShiftLeft { x, y })
+                if y == 0 && x != 0 && quirks.shift_vy =>
+            {
+                quirks.shift_vy = false;
+                reasons.push(format!(
+                    "no shift_vy: the shift at {:#05X} leaves out its source",
+                    address
+                ));
+            }
+            Some(instruction @ (Instruction::Store { .. } | Instruction::Restore { .. }))
+                if !quirks.memory_increment =>
+            {
+                // LD I, NNN right before storing into the program's code.
+                let modified = match (instruction, previous(address).and_then(decode)) {
+                    (Instruction::Store { .. }, Some(Instruction::LoadI(target))) => {
+                        Some(target as usize).filter(|target| code.contains_key(target))
+                    }
+                    _ => None,
+                };
+                let next = code.get(&(address + 2)).copied().and_then(decode);
+                if let Some(target) = modified {
+                    quirks.memory_increment = true;
+                    reasons.push(format!(
+                        "memory_increment: FX55 at {:#05X} modifies the code at {:#05X}, like \
+                         COSMAC VIP programs",
+                        address, target
+                    ));
+                } else if matches!(
+                    next,
+                    Some(Instruction::Store { .. } | Instruction::Restore { .. })
+                ) {
+                    quirks.memory_increment = true;
+                    reasons.push(format!(
+                        "memory_increment: {:#05X} and {:#05X} access memory through I without \
+                         setting it in between",
+                        address,
+                        address + 2
+                    ));
+                }
+            }
+            Some(Instruction::JumpOffset { x, .. }) if x != 0 && !quirks.jump_vx => {
+                // Setting VX right before BXNN means the jump is relative to it.
+                let sets_vx = previous(address).and_then(decode).is_some_and(|op| {
+                    matches!(
+                        op,
+                        Instruction::LoadByte { x: vx, .. }
+                            | Instruction::Load { x: vx, .. }
+                            | Instruction::Or { x: vx, .. }
+                            | Instruction::And { x: vx, .. }
+                            | Instruction::Xor { x: vx, .. }
+                            | Instruction::Add { x: vx, .. }
+                            | Instruction::Sub { x: vx, .. }
+                            | Instruction::ShiftRight { x: vx, .. }
+                            | Instruction::SubN { x: vx, .. }
+                            | Instruction::ShiftLeft { x: vx, .. }
+                            if vx == x
+                    )
+                });
+                if sets_vx || superchip {
+                    quirks.jump_vx = true;
+                    let why = if sets_vx {
+                        format!("follows setting V{:X}", x)
+                    } else {
+                        "is in a SUPER-CHIP ROM".to_string()
+                    };
+                    reasons.push(format!("jump_vx: BNNN at {:#05X} {}", address, why));
+                }
+            }
+            _ => {}
+        }
+    }
+    (quirks, reasons)
+}
+
+/// What [`scan`] found about the platform a ROM was written for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Scan {
+    /// Extensions the reachable code uses instructions of, with the address
+    /// of the first use of each.
+    pub extensions: BTreeMap<&'static str, usize>,
+    /// Reachable instructions the emulator doesn't execute, which stop the
+    /// ROM once reached.
+    pub unsupported: Vec<(usize, u16)>,
+    /// Quirks the reachable code has instructions affected by, with the
+    /// number of them.
+    pub ambiguous: BTreeMap<&'static str, usize>,
+    /// Platform whose preset suits the ROM best.
+    pub platform: Platform,
+    /// The platform's quirks, adjusted by [`guess_quirks`].
+    pub quirks: Quirks,
+    /// Why each quirk was adjusted.
+    pub reasons: Vec<String>,
+}
+
+/// Scans the code reachable in `rom` for the extensions it uses and the
+/// instructions whose behavior differs between interpreters, to tell which
+/// platform and quirks it needs before running it.
+pub fn scan(rom: &[u8], load_address: usize) -> Scan {
+    let (code, diagnostics) = trace(rom, load_address);
+    let mut extensions = BTreeMap::new();
+    let mut unsupported = Vec::new();
+    for diagnostic in diagnostics {
+        if let Diagnostic::Unsupported {
+            address,
+            opcode,
+            extension,
+        } = diagnostic
+        {
+            unsupported.push((address, opcode));
+            if let Some(extension) = extension {
+                extensions.entry(extension).or_insert(address);
+            }
+        }
+    }
+    unsupported.sort();
+    let mut ambiguous = BTreeMap::new();
+    for (&address, &opcode) in &code {
+        if let Some(
+            Instruction::BigFont { .. }
+            | Instruction::SaveFlags { .. }
+            | Instruction::LoadFlags { .. },
+        ) = decode(opcode)
+        {
+            let first = extensions.entry("SUPER-CHIP").or_insert(address);
+            *first = address.min(*first);
+        }
+        let quirks: &[&'static str] = match decode(opcode) {
+            Some(Instruction::Or { .. } | Instruction::And { .. } | Instruction::Xor { .. }) => {
+                &["vf_reset"]
+            }
+            Some(Instruction::ShiftRight { .. } | Instruction::ShiftLeft { .. }) => &["shift_vy"],
+            Some(Instruction::Store { .. } | Instruction::Restore { .. }) => &["memory_increment"],
+            Some(Instruction::JumpOffset { .. }) => &["jump_vx"],
+            Some(Instruction::Draw { .. }) => &["display_wait", "wrap"],
+            Some(Instruction::WaitKey { .. }) => &["key_press"],
+            _ => &[],
+        };
+        for &quirk in quirks {
+            *ambiguous.entry(quirk).or_insert(0) += 1;
+        }
+    }
+    let platform = if extensions.contains_key("XO-CHIP") {
+        Platform::XoChip
+    } else if extensions.contains_key("SUPER-CHIP") {
+        Platform::Schip
+    } else {
+        Platform::Chip8
+    };
+    let (quirks, reasons) = guess_quirks(rom, load_address, platform.quirks());
+    Scan {
+        extensions,
+        unsupported,
+        ambiguous,
+        platform,
+        quirks,
+        reasons,
+    }
+}
+
+impl fmt::Display for Scan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.extensions.is_empty() {
+            writeln!(f, "Uses only CHIP-8 instructions")?;
+        }
+        for (extension, address) in &self.extensions {
+            writeln!(f, "Uses {} instructions, from {:#05X}", extension, address)?;
+        }
+        let platform = match self.platform {
+            Platform::Chip8 => "chip8",
+            Platform::Chip48 => "chip48",
+            Platform::Schip => "schip",
+            Platform::XoChip => "xochip",
+        };
+        writeln!(f, "Platform: {}", platform)?;
+        let on: Vec<&str> = Quirks::NAMES
+            .into_iter()
+            .filter(|name| self.quirks.get(name) == Some(true))
+            .collect();
+        match on.as_slice() {
+            [] => writeln!(f, "Quirks: none")?,
+            on => writeln!(f, "Quirks: {}", on.join(", "))?,
+        }
+        for reason in &self.reasons {
+            writeln!(f, "  {}", reason)?;
+        }
+        for (quirk, count) in &self.ambiguous {
+            writeln!(f, "{} instructions depend on {}", count, quirk)?;
+        }
+        match self.unsupported.as_slice() {
+            [] => writeln!(f, "Supported: every reachable instruction runs"),
+            [(address, opcode), ..] => writeln!(
+                f,
+                "Not supported: {} reachable instructions don't run, the first is {:04X} at {:#05X}",
+                self.unsupported.len(),
+                opcode,
+                address
+            ),
+        }
+    }
+}
+
+/// Follows the code reachable from where the ROM starts executing, which
+/// is `load_address` unless it's a hires ROM, returning the instructions
+/// found by address along with the unsupported ones and jumps below the
+/// program.
+pub(crate) fn trace(rom: &[u8], load_address: usize) -> (BTreeMap<usize, u16>, Vec<Diagnostic>) {
+    let mut code = BTreeMap::new();
+    let mut diagnostics = Vec::new();
+    let end = load_address + rom.len();
+    let hires = is_hires(rom, load_address);
+    let mut pending = vec![if hires { HIRES_ENTRY } else { load_address }];
+    while let Some(address) = pending.pop() {
+        if address < load_address || address + 2 > end || code.contains_key(&address) {
+            continue;
+        }
+        let offset = address - load_address;
+        let opcode = u16::from_be_bytes([rom[offset], rom[offset + 1]]);
+        code.insert(address, opcode);
+        if !(is_supported(opcode) || hires && opcode == HIRES_CLS) {
+            diagnostics.push(Diagnostic::Unsupported {
+                address,
+                opcode,
+                extension: extension(opcode),
+            });
+            continue;
+        }
+        let target = (opcode & 0xFFF) as usize;
+        let next = address + 2;
+        match opcode >> 12 {
+            0x1 | 0x2 if target < load_address => {
+                diagnostics.push(Diagnostic::JumpBelowProgram { address, target });
+            }
+            0x1 => pending.push(target),
+            0x2 => pending.extend([next, target]),
+            0x0 if opcode == 0x00EE => {}
+            0xB => {}
+            0x3 | 0x4 | 0x5 | 0x9 | 0xE => pending.extend([next, next + 2]),
+            _ => pending.push(next),
+        }
+    }
+    (code, diagnostics)
+}
+
+/// Returns true for the instructions the emulator executes, which are all
+/// the decoded ones except SYS.
+fn is_supported(opcode: u16) -> bool {
+    !matches!(decode(opcode), None | Some(Instruction::Sys(_)))
+}
+
+/// Returns the extension an instruction the emulator doesn't execute is
+/// from, if any. MEGA-CHIP is only recognized so scans can name it: its
+/// 256x192 indexed-color display and 24-bit addresses don't fit the fixed
+/// display and 4 KiB memory of the core, so none of it runs.
+fn extension(opcode: u16) -> Option<&'static str> {
+    match opcode {
+        0x00C0..=0x00CF | 0x00FB..=0x00FF => Some("SUPER-CHIP"),
+        // Scrolling up, LDHI, the palette, sprite size, alpha, sound and
+        // blend modes.
+        0x00B0..=0x00BF | 0x0100..=0x08FF => Some("MEGA-CHIP"),
+        0x00D0..=0x00DF | 0xF000 | 0xF002 => Some("XO-CHIP"),
+        _ if matches!(opcode & 0xF00F, 0x5002 | 0x5003) => Some("XO-CHIP"),
+        _ if matches!(opcode & 0xF0FF, 0xF001 | 0xF03A) => Some("XO-CHIP"),
+        0x0010 | 0x0011 => Some("MEGA-CHIP"),
+        _ => None,
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use zip::ZipArchive;
+
+/// Whether `path` names a ZIP archive, by its extension.
+pub fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+fn is_rom(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ch8"))
+}
+
+fn open(path: &Path) -> Result<ZipArchive<File>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    ZipArchive::new(file).map_err(|e| e.to_string())
+}
+
+/// Returns the paths of the `.ch8` files in the archive at `path`, as the
+/// archive's path joined with their name in it, sorted by name.
+pub fn list_roms(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let archive = open(path)?;
+    let mut roms: Vec<PathBuf> = archive
+        .file_names()
+        .filter(|name| is_rom(name))
+        .map(|name| path.join(name))
+        .collect();
+    roms.sort();
+    Ok(roms)
+}
+
+/// Whether `path` is an archive holding more than one ROM to pick from.
+pub fn is_pack(path: &Path) -> bool {
+    is_zip(path) && list_roms(path).is_ok_and(|roms| roms.len() > 1)
+}
+
+/// Reads the ROM at `path`, which is either a file, a ZIP archive holding a
+/// single `.ch8` file, or a file in an archive as returned by [`list_roms`].
+pub fn read_rom(path: &Path) -> Result<Vec<u8>, String> {
+    if path.is_file() && !is_zip(path) {
+        return std::fs::read(path).map_err(|e| e.to_string());
+    }
+    let (archive_path, name) = if is_zip(path) && path.is_file() {
+        let roms = list_roms(path)?;
+        match roms.as_slice() {
+            [rom] => (path, rom.strip_prefix(path).unwrap().to_path_buf()),
+            [] => return Err("the archive contains no .ch8 ROM".to_string()),
+            _ => {
+                return Err(format!(
+                    "the archive contains {} ROMs, pick one with <archive>/<rom>",
+                    roms.len()
+                ))
+            }
+        }
+    } else {
+        let archive_path = path
+            .ancestors()
+            .skip(1)
+            .find(|ancestor| is_zip(ancestor) && ancestor.is_file())
+            .ok_or_else(|| "No such file or directory".to_string())?;
+        (
+            archive_path,
+            path.strip_prefix(archive_path).unwrap().to_path_buf(),
+        )
+    };
+    // Names in archives always use forward slashes.
+    let name = name
+        .iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut archive = open(archive_path)?;
+    let mut file = archive.by_name(&name).map_err(|e| e.to_string())?;
+    let mut rom = Vec::new();
+    file.read_to_end(&mut rom).map_err(|e| e.to_string())?;
+    Ok(rom)
+}
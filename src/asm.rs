@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// Address the assembled program is loaded at, used to resolve labels.
+pub const ORIGIN: usize = 0x200;
+
+/// An assembly error and the (1-based) source line it occurred on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operand {
+    V(u16),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    Hf,
+    B,
+    R,
+    Number(usize),
+}
+
+struct Statement<'a> {
+    line: usize,
+    mnemonic: String,
+    operands: Vec<&'a str>,
+}
+
+/// Assembles CHIP-8 source into a ROM image loaded at [`ORIGIN`].
+///
+/// The syntax follows the mnemonics produced by the disassembler. Lines may
+/// start with a `label:`, comments start with `;`, and numbers are decimal,
+/// `0x` hexadecimal or `0b` binary. `DB` and `DW` emit raw bytes and words.
+pub fn assemble(source: &str) -> Result<Vec<u8>, Error> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = ORIGIN;
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let error = |message: String| Error {
+            line: line_number,
+            message,
+        };
+        let mut code = line.split(';').next().unwrap().trim();
+        if let Some((label, rest)) = code.split_once(':') {
+            let label = label.trim();
+            if !is_identifier(label) {
+                return Err(error(format!("invalid label `{}`", label)));
+            }
+            if reserved(label).is_some() {
+                return Err(error(format!("label `{}` is a reserved name", label)));
+            }
+            if labels.insert(label.to_string(), address).is_some() {
+                return Err(error(format!("duplicate label `{}`", label)));
+            }
+            code = rest.trim();
+        }
+        if code.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = code.split_once(char::is_whitespace).unwrap_or((code, ""));
+        let operands: Vec<&str> = operands
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect();
+        let mnemonic = mnemonic.to_ascii_uppercase();
+        address += match mnemonic.as_str() {
+            "DB" => operands.len(),
+            "DW" => 2 * operands.len(),
+            _ => 2,
+        };
+        statements.push(Statement {
+            line: line_number,
+            mnemonic,
+            operands,
+        });
+    }
+
+    let mut rom = Vec::new();
+    for statement in statements {
+        let error = |message: String| Error {
+            line: statement.line,
+            message,
+        };
+        let operands = statement
+            .operands
+            .iter()
+            .map(|s| parse_operand(s, &labels))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(error)?;
+        match statement.mnemonic.as_str() {
+            "DB" => {
+                for operand in operands {
+                    rom.push(number(operand, 0xFF).map_err(error)? as u8);
+                }
+            }
+            "DW" => {
+                for operand in operands {
+                    let word = number(operand, 0xFFFF).map_err(error)? as u16;
+                    rom.extend_from_slice(&word.to_be_bytes());
+                }
+            }
+            mnemonic => {
+                let op = encode(mnemonic, &operands).map_err(error)?;
+                rom.extend_from_slice(&op.to_be_bytes());
+            }
+        }
+    }
+    Ok(rom)
+}
+
+fn encode(mnemonic: &str, operands: &[Operand]) -> Result<u16, String> {
+    use Operand::*;
+
+    let addr = |operand| number(operand, 0xFFF).map(|n| n as u16);
+    let byte = |operand| number(operand, 0xFF).map(|n| n as u16);
+    let nibble = |operand| number(operand, 0xF).map(|n| n as u16);
+    let op = match (mnemonic, operands) {
+        ("CLS", []) => 0x00E0,
+        ("RET", []) => 0x00EE,
+        ("SYS", &[a]) => addr(a)?,
+        ("JP", &[V(0), a]) => 0xB000 | addr(a)?,
+        ("JP", &[a]) => 0x1000 | addr(a)?,
+        ("CALL", &[a]) => 0x2000 | addr(a)?,
+        ("SE", &[V(x), V(y)]) => 0x5000 | x << 8 | y << 4,
+        ("SE", &[V(x), nn]) => 0x3000 | x << 8 | byte(nn)?,
+        ("SNE", &[V(x), V(y)]) => 0x9000 | x << 8 | y << 4,
+        ("SNE", &[V(x), nn]) => 0x4000 | x << 8 | byte(nn)?,
+        ("LD", &[V(x), V(y)]) => 0x8000 | x << 8 | y << 4,
+        ("LD", &[V(x), Dt]) => 0xF007 | x << 8,
+        ("LD", &[V(x), K]) => 0xF00A | x << 8,
+        ("LD", &[V(x), IndirectI]) => 0xF065 | x << 8,
+        ("LD", &[V(x), R]) => 0xF085 | x << 8,
+        ("LD", &[V(x), nn]) => 0x6000 | x << 8 | byte(nn)?,
+        ("LD", &[I, a]) => 0xA000 | addr(a)?,
+        ("LD", &[Dt, V(x)]) => 0xF015 | x << 8,
+        ("LD", &[St, V(x)]) => 0xF018 | x << 8,
+        ("LD", &[F, V(x)]) => 0xF029 | x << 8,
+        ("LD", &[Hf, V(x)]) => 0xF030 | x << 8,
+        ("LD", &[B, V(x)]) => 0xF033 | x << 8,
+        ("LD", &[IndirectI, V(x)]) => 0xF055 | x << 8,
+        ("LD", &[R, V(x)]) => 0xF075 | x << 8,
+        ("ADD", &[V(x), V(y)]) => 0x8004 | x << 8 | y << 4,
+        ("ADD", &[V(x), nn]) => 0x7000 | x << 8 | byte(nn)?,
+        ("ADD", &[I, V(x)]) => 0xF01E | x << 8,
+        ("OR", &[V(x), V(y)]) => 0x8001 | x << 8 | y << 4,
+        ("AND", &[V(x), V(y)]) => 0x8002 | x << 8 | y << 4,
+        ("XOR", &[V(x), V(y)]) => 0x8003 | x << 8 | y << 4,
+        ("SUB", &[V(x), V(y)]) => 0x8005 | x << 8 | y << 4,
+        ("SHR", &[V(x)]) => 0x8006 | x << 8 | x << 4,
+        ("SHR", &[V(x), V(y)]) => 0x8006 | x << 8 | y << 4,
+        ("SUBN", &[V(x), V(y)]) => 0x8007 | x << 8 | y << 4,
+        ("SHL", &[V(x)]) => 0x800E | x << 8 | x << 4,
+        ("SHL", &[V(x), V(y)]) => 0x800E | x << 8 | y << 4,
+        ("RND", &[V(x), nn]) => 0xC000 | x << 8 | byte(nn)?,
+        ("DRW", &[V(x), V(y), n]) => 0xD000 | x << 8 | y << 4 | nibble(n)?,
+        ("SKP", &[V(x)]) => 0xE09E | x << 8,
+        ("SKNP", &[V(x)]) => 0xE0A1 | x << 8,
+        _ => return Err(format!("invalid instruction `{}`", mnemonic)),
+    };
+    Ok(op)
+}
+
+fn number(operand: Operand, max: usize) -> Result<usize, String> {
+    match operand {
+        Operand::Number(n) if n <= max => Ok(n),
+        Operand::Number(n) => Err(format!("{:#X} is larger than {:#X}", n, max)),
+        _ => Err(format!("expected a number, found {:?}", operand)),
+    }
+}
+
+fn parse_operand(s: &str, labels: &HashMap<String, usize>) -> Result<Operand, String> {
+    if let Some(operand) = reserved(s) {
+        return Ok(operand);
+    }
+    match parse_number(s).or_else(|| labels.get(s).copied()) {
+        Some(n) => Ok(Operand::Number(n)),
+        None if is_identifier(s) => Err(format!("undefined label `{}`", s)),
+        None => Err(format!("invalid operand `{}`", s)),
+    }
+}
+
+/// Registers and other operands with a fixed name, which labels can't shadow.
+fn reserved(s: &str) -> Option<Operand> {
+    let upper = s.to_ascii_uppercase();
+    let operand = match upper.as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::Dt,
+        "ST" => Operand::St,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "HF" => Operand::Hf,
+        "B" => Operand::B,
+        "R" => Operand::R,
+        _ => match upper.strip_prefix('V') {
+            Some(x) if x.len() == 1 => Operand::V(u16::from_str_radix(x, 16).ok()?),
+            _ => return None,
+        },
+    };
+    Some(operand)
+}
+
+fn parse_number(s: &str) -> Option<usize> {
+    let lower = s.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        usize::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        usize::from_str_radix(bin, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,121 @@
+use std::f32::consts::TAU;
+
+use clap::ValueEnum;
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::Sdl;
+use serde::Deserialize;
+
+pub const DEFAULT_FREQUENCY: f32 = 440.0;
+pub const DEFAULT_VOLUME: f32 = 0.25;
+
+/// Shapes of the tone's wave.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Waveform {
+    #[default]
+    Square,
+    Triangle,
+    Sine,
+}
+
+/// Sound of the tone.
+#[derive(Clone, Copy, Debug)]
+pub struct Tone {
+    pub waveform: Waveform,
+    /// Frequency in Hz.
+    pub frequency: f32,
+    /// Part of each period a square wave is high for, from 0 to 1.
+    pub duty_cycle: f32,
+}
+
+impl Tone {
+    /// Value of the wave from -1 to 1 at `phase`, from 0 to 1 through a
+    /// period.
+    fn sample(&self, phase: f32) -> f32 {
+        match self.waveform {
+            Waveform::Square if phase < self.duty_cycle => 1.0,
+            Waveform::Square => -1.0,
+            Waveform::Triangle => 4.0 * (phase - 0.5).abs() - 1.0,
+            Waveform::Sine => (phase * TAU).sin(),
+        }
+    }
+}
+
+struct Synth {
+    tone: Tone,
+    sample_rate: f32,
+    phase: f32,
+    volume: f32,
+}
+
+impl AudioCallback for Synth {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        let phase_inc = self.tone.frequency / self.sample_rate;
+        for x in out.iter_mut() {
+            *x = self.volume * self.tone.sample(self.phase);
+            self.phase = (self.phase + phase_inc) % 1.0;
+        }
+    }
+}
+
+/// Plays a tone while the sound timer is active.
+pub struct Beeper {
+    device: AudioDevice<Synth>,
+    volume: f32,
+    muted: bool,
+}
+
+impl Beeper {
+    pub fn new(sdl_context: &Sdl, volume: f32, tone: Tone) -> Result<Beeper, String> {
+        let audio_subsystem = sdl_context.audio()?;
+        let spec = AudioSpecDesired {
+            freq: Some(44100),
+            channels: Some(1),
+            samples: None,
+        };
+        let device = audio_subsystem.open_playback(None, &spec, |spec| Synth {
+            tone,
+            sample_rate: spec.freq as f32,
+            phase: 0.0,
+            volume,
+        })?;
+        Ok(Beeper {
+            device,
+            volume,
+            muted: false,
+        })
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// Sets the volume, from 0 for silence to 1 for full scale.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        self.update_gain();
+    }
+
+    /// Mutes or unmutes the tone, keeping the volume. Returns true when it's
+    /// now muted.
+    pub fn toggle_mute(&mut self) -> bool {
+        self.muted = !self.muted;
+        self.update_gain();
+        self.muted
+    }
+
+    fn update_gain(&mut self) {
+        let gain = if self.muted { 0.0 } else { self.volume };
+        self.device.lock().volume = gain;
+    }
+
+    pub fn set_playing(&self, playing: bool) {
+        if playing {
+            self.device.resume();
+        } else {
+            self.device.pause();
+        }
+    }
+}
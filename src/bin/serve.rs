@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+use std::process::exit;
+
+use chip8::serve::{EmulatorOptions, ServeOptions};
+use chip8::theme::Theme;
+use chip8::{Emulator, DEFAULT_SPEED};
+use clap::Parser;
+
+/// Runs a ROM without a window, streaming the display to browsers, like
+/// `chip8 serve` on machines without the SDL2 libraries
+#[derive(Parser)]
+struct Cli {
+    /// Path to the ROM to run
+    rom: PathBuf,
+    #[command(flatten)]
+    emulator: EmulatorOptions,
+    #[command(flatten)]
+    serve: ServeOptions,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let rom = std::fs::read(&cli.rom).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", cli.rom.display(), e);
+        exit(1);
+    });
+    let mut emulator = match cli.emulator.seed {
+        Some(seed) => Emulator::with_seed(seed),
+        None => Emulator::new(),
+    };
+    let mut speed = DEFAULT_SPEED;
+    cli.emulator.apply(&mut speed, &mut emulator.quirks);
+    emulator.set_speed(speed);
+    if let Some(timing) = cli.emulator.timing {
+        emulator.set_timing(timing);
+    }
+    if let Some(stack_limit) = cli.emulator.stack_limit {
+        emulator.set_stack_limit(stack_limit.into());
+    }
+    if let Err(e) = emulator.load_rom(&rom) {
+        eprintln!("Failed to load {}: {}", cli.rom.display(), e);
+        exit(1);
+    }
+    let palette = cli.emulator.palette(Theme::default());
+    let addr = cli.serve.addr();
+    if let Err(e) = chip8::serve::serve(&mut emulator, &palette, addr) {
+        eprintln!("Failed to serve on {}: {}", addr, e);
+        exit(1);
+    }
+}
@@ -0,0 +1,127 @@
+use std::os::raw::c_int;
+
+use crate::{Emulator, HIRES_HEIGHT, WIDTH};
+
+/// Creates an emulator whose CXNN results are determined by `seed`. Free it
+/// with [`chip8_free`].
+#[no_mangle]
+pub extern "C" fn chip8_new(seed: u64) -> *mut Emulator {
+    Box::into_raw(Box::new(Emulator::with_seed(seed)))
+}
+
+/// Frees an emulator created by [`chip8_new`]. Does nothing when it's null.
+///
+/// # Safety
+///
+/// `emulator` must be null or come from [`chip8_new`], and not be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_free(emulator: *mut Emulator) {
+    if !emulator.is_null() {
+        drop(Box::from_raw(emulator));
+    }
+}
+
+/// Loads the `len` bytes at `rom` at 0x200. Returns 0, or -1 when the ROM
+/// doesn't fit in memory.
+///
+/// # Safety
+///
+/// `emulator` must come from [`chip8_new`] and `rom` must point to `len`
+/// readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(
+    emulator: *mut Emulator,
+    rom: *const u8,
+    len: usize,
+) -> c_int {
+    let rom = std::slice::from_raw_parts(rom, len);
+    match (*emulator).load_rom(rom) {
+        Ok(_) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// Restarts the loaded ROM, restoring memory to how it was loaded and
+/// clearing the registers, stack, timers, keys and display.
+///
+/// # Safety
+///
+/// `emulator` must come from [`chip8_new`].
+#[no_mangle]
+pub unsafe extern "C" fn chip8_reset(emulator: *mut Emulator) {
+    (*emulator).reset();
+}
+
+/// Resets the emulator to how [`chip8_new`] creates it, with no ROM loaded.
+///
+/// # Safety
+///
+/// `emulator` must come from [`chip8_new`].
+#[no_mangle]
+pub unsafe extern "C" fn chip8_hard_reset(emulator: *mut Emulator) {
+    (*emulator).hard_reset();
+}
+
+/// Executes the instruction at PC. Returns 1 when the display changed, 0
+/// when it didn't, and -1 when the emulator stopped on an error.
+///
+/// # Safety
+///
+/// `emulator` must come from [`chip8_new`].
+#[no_mangle]
+pub unsafe extern "C" fn chip8_step(emulator: *mut Emulator) -> c_int {
+    match (*emulator).step() {
+        Ok(changed) => changed as c_int,
+        Err(_) => -1,
+    }
+}
+
+/// Decrements the delay and sound timers, to call at 60Hz. Returns 1 while
+/// the sound timer is still running, 0 otherwise.
+///
+/// # Safety
+///
+/// `emulator` must come from [`chip8_new`].
+#[no_mangle]
+pub unsafe extern "C" fn chip8_tick_timers(emulator: *mut Emulator) -> c_int {
+    let emulator = &mut *emulator;
+    emulator.tick_timers();
+    (emulator.sound_timer > 0) as c_int
+}
+
+/// Returns the display as 64 × 64 bytes, row by row, 1 for set pixels and 0
+/// for unset ones, of which the rows past [`chip8_height`] are unused. The
+/// pointer stays valid until the emulator is freed.
+///
+/// # Safety
+///
+/// `emulator` must come from [`chip8_new`].
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(emulator: *const Emulator) -> *const u8 {
+    const _: () =
+        assert!(std::mem::size_of::<[[bool; WIDTH]; HIRES_HEIGHT]>() == WIDTH * HIRES_HEIGHT);
+    (*emulator).display.as_ptr().cast()
+}
+
+/// Returns the number of display rows in use, 32, or 64 for hires ROMs.
+///
+/// # Safety
+///
+/// `emulator` must come from [`chip8_new`].
+#[no_mangle]
+pub unsafe extern "C" fn chip8_height(emulator: *const Emulator) -> usize {
+    (*emulator).height()
+}
+
+/// Sets whether keypad key `key`, 0 to 15, is held. Other keys are ignored.
+///
+/// # Safety
+///
+/// `emulator` must come from [`chip8_new`].
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(emulator: *mut Emulator, key: u8, pressed: bool) {
+    if key < 16 {
+        (*emulator).set_key(key, pressed);
+    }
+}
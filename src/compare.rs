@@ -0,0 +1,244 @@
+use std::time::{Duration, Instant};
+
+use chip8::palette::{Palette, Rgb};
+use chip8::{decode, Emulator, Platform, FRAME_RATE, WIDTH};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use crate::keymap::Keymap;
+
+const TITLE: &str = "CHIP-8 comparison";
+/// Space between the two displays, in CHIP-8 pixels.
+const GAP: usize = 2;
+
+/// Two emulators running the same ROM with the quirks of different
+/// platforms in lockstep, given the same input, which notes the first
+/// instruction after which their states differ.
+pub struct Comparison {
+    emulators: [Emulator; 2],
+    platforms: [Platform; 2],
+    executed: u64,
+    divergence: Option<String>,
+}
+
+impl Comparison {
+    /// Starts comparing two copies of `emulator`, using the quirks of
+    /// `platforms` but its speed and seed.
+    pub fn new(emulator: Emulator, platforms: [Platform; 2]) -> Comparison {
+        let emulators = platforms.map(|platform| {
+            let mut emulator = emulator.clone();
+            emulator.quirks = platform.quirks();
+            emulator
+        });
+        Comparison {
+            emulators,
+            platforms,
+            executed: 0,
+            divergence: None,
+        }
+    }
+
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        for emulator in &mut self.emulators {
+            emulator.set_key(key, pressed);
+        }
+    }
+
+    /// Where the states first differed, if they have.
+    pub fn divergence(&self) -> Option<&str> {
+        self.divergence.as_deref()
+    }
+
+    /// Emulates a frame on both emulators, one instruction at a time,
+    /// comparing their states after each.
+    pub fn run_frame(&mut self) -> Result<(), String> {
+        for _ in 0..self.emulators[0].frame_instructions() {
+            let pc = self.emulators[0].pc;
+            let opcode = self.emulators[0].current_opcode();
+            for (emulator, platform) in self.emulators.iter_mut().zip(self.platforms) {
+                if !emulator.is_waiting() {
+                    emulator
+                        .step()
+                        .map_err(|e| format!("{:?} stopped: {}", platform, e))?;
+                }
+            }
+            self.executed += 1;
+            if self.divergence.is_none() {
+                let [a, b] = &self.emulators;
+                let differences = differences(a, b);
+                if !differences.is_empty() {
+                    let instruction = match opcode.ok().and_then(decode) {
+                        Some(instruction) => instruction.to_string(),
+                        None => "an unknown instruction".to_string(),
+                    };
+                    self.divergence = Some(format!(
+                        "{} differ after {} instructions, at {:#05X}: {}",
+                        differences.join(", "),
+                        self.executed,
+                        pc,
+                        instruction
+                    ));
+                }
+            }
+        }
+        for emulator in &mut self.emulators {
+            emulator.tick_timers();
+        }
+        Ok(())
+    }
+}
+
+/// Names the parts of the machine whose state differs between `a` and `b`.
+fn differences(a: &Emulator, b: &Emulator) -> Vec<String> {
+    let mut differences = Vec::new();
+    if a.pc != b.pc {
+        differences.push("PC".to_string());
+    }
+    if a.index_register != b.index_register {
+        differences.push("I".to_string());
+    }
+    for (i, (x, y)) in a.var_registers.iter().zip(b.var_registers).enumerate() {
+        if *x != y {
+            differences.push(format!("V{:X}", i));
+        }
+    }
+    if a.stack[..] != b.stack[..] {
+        differences.push("the stack".to_string());
+    }
+    if (a.delay_timer, a.sound_timer) != (b.delay_timer, b.sound_timer) {
+        differences.push("the timers".to_string());
+    }
+    if a.display != b.display {
+        differences.push("the displays".to_string());
+    }
+    if a.memory[..] != b.memory[..] {
+        differences.push("the memory".to_string());
+    }
+    differences
+}
+
+/// Shows the displays side by side in a window until it's closed or Escape
+/// is pressed, forwarding the keypad keys to both emulators.
+pub fn run(
+    mut comparison: Comparison,
+    keymap: &Keymap,
+    palette: &Palette,
+    scale: u32,
+) -> Result<(), String> {
+    let sdl_context = sdl2::init()?;
+    let rows = comparison.emulators[0].height();
+    let window = sdl_context
+        .video()?
+        .window(TITLE, (2 * WIDTH + GAP) as u32 * scale, rows as u32 * scale)
+        .position_centered()
+        .resizable()
+        .allow_highdpi()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+    let mut event_pump = sdl_context.event_pump()?;
+    let frame_duration = Duration::from_secs(1) / FRAME_RATE;
+    let mut next_frame = Instant::now();
+    let mut stopped = false;
+
+    'running: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => break 'running,
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => {
+                    if let Some(key) = keymap.keypad(keycode) {
+                        comparison.set_key(key, true);
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    if let Some(key) = keymap.keypad(keycode) {
+                        comparison.set_key(key, false);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !stopped {
+            let had_diverged = comparison.divergence().is_some();
+            if let Err(e) = comparison.run_frame() {
+                println!("{}", e);
+                stopped = true;
+            }
+            if let Some(divergence) = comparison.divergence().filter(|_| !had_diverged) {
+                println!("{}", divergence);
+                let title = format!("{} - {}", TITLE, divergence);
+                canvas.window_mut().set_title(&title).unwrap();
+            }
+        }
+        draw(&mut canvas, &comparison, palette);
+
+        next_frame += frame_duration;
+        let now = Instant::now();
+        if next_frame > now {
+            std::thread::sleep(next_frame - now);
+        } else {
+            next_frame = now;
+        }
+    }
+    if comparison.divergence().is_none() {
+        println!("No differences after {} instructions", comparison.executed);
+    }
+    Ok(())
+}
+
+fn draw(canvas: &mut Canvas<Window>, comparison: &Comparison, palette: &Palette) {
+    let rows = comparison.emulators[0].height();
+    let columns = 2 * WIDTH + GAP;
+    let (width, height) = canvas.output_size().unwrap();
+    let px = (width as usize / columns)
+        .min(height as usize / rows)
+        .max(1);
+    let x = (width as i32 - (columns * px) as i32) / 2;
+    let y = (height as i32 - (rows * px) as i32) / 2;
+    let color = |Rgb(r, g, b)| Color::RGB(r, g, b);
+
+    canvas.set_draw_color(Color::BLACK);
+    canvas.clear();
+    for (i, emulator) in comparison.emulators.iter().enumerate() {
+        let left = x + (i * (WIDTH + GAP) * px) as i32;
+        canvas.set_draw_color(color(palette.background()));
+        let area = Rect::new(left, y, (WIDTH * px) as u32, (rows * px) as u32);
+        canvas.fill_rect(area).unwrap();
+        let pixels: Vec<Rect> = emulator.display[..rows]
+            .iter()
+            .enumerate()
+            .flat_map(|(row, pixels)| {
+                pixels
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &set)| set)
+                    .map(move |(col, _)| {
+                        Rect::new(
+                            left + (col * px) as i32,
+                            y + (row * px) as i32,
+                            px as u32,
+                            px as u32,
+                        )
+                    })
+            })
+            .collect();
+        canvas.set_draw_color(color(palette.foreground()));
+        canvas.fill_rects(&pixels).unwrap();
+    }
+    canvas.present();
+}
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use chip8::palette::{Palette, Rgb};
+use chip8::theme::Theme;
+use chip8::{Emulator, Platform, Quirks, Timing, DEFAULT_SPEED, PROGRAM_ADDRESS, STACK_SIZE};
+use clap::ValueEnum;
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+
+use crate::audio::{self, Tone, Waveform};
+use crate::keymap::{self, Bindings, Key, PadButton};
+use crate::osd::VisualBeep;
+use crate::screen::Crt;
+use crate::BLOCK_SIZE;
+
+const FILE_NAME: &str = "chip8.toml";
+
+/// Settings loaded from `chip8.toml`. Every field is optional in the file
+/// and falls back to its default.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Path to the CHIP-8 Archive's `programs.json`.
+    pub database: Option<PathBuf>,
+    /// File to replace the built-in hexadecimal digit font with.
+    pub font: Option<PathBuf>,
+    pub rom_dir: PathBuf,
+    pub scale: u32,
+    pub speed: u32,
+    /// Count the instructions run in a frame by what they cost on the
+    /// COSMAC VIP with `vip`, instead of by `speed`.
+    pub timing: Timing,
+    /// Subroutine calls which can be nested before a ROM stops with a stack
+    /// overflow, up to 16.
+    pub stack_limit: usize,
+    /// Address ROMs are loaded at and start executing from.
+    pub load_address: usize,
+    /// Fade out erased pixels instead of turning them off at once.
+    pub phosphor: bool,
+    pub fullscreen: bool,
+    /// Pace frames by the display's refresh instead of a timer.
+    pub vsync: bool,
+    /// Show the registers and code in a window of their own.
+    pub debug_window: bool,
+    /// Show the keypad and the keys it's bound to over the display.
+    pub keypad_overlay: bool,
+    /// Show the frame, instruction and timer rates in the window title.
+    pub stats: bool,
+    /// Pause and go quiet while no window of the emulator has focus.
+    pub pause_in_background: bool,
+    pub crt: Crt,
+    pub rewind_buffer: usize,
+    pub theme: Theme,
+    pub colors: Colors,
+    pub audio: Audio,
+    pub gamepad: Gamepad,
+    pub keys: Bindings,
+    pub quirks: Quirks,
+    /// Guess the quirks of ROMs the database doesn't know from their code.
+    pub guess_quirks: bool,
+    /// Stop ROMs which write below their load address.
+    pub protect_interpreter: bool,
+    /// Hold tapped keys until the ROM checks them.
+    pub latch_keys: bool,
+    pub unknown_opcode: UnknownOpcode,
+    /// Settings for single ROMs, by the SHA-1 hash of the ROM.
+    pub roms: HashMap<String, RomConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            database: None,
+            font: None,
+            rom_dir: PathBuf::from("rom"),
+            scale: BLOCK_SIZE,
+            speed: DEFAULT_SPEED,
+            timing: Timing::Fixed,
+            stack_limit: STACK_SIZE,
+            load_address: PROGRAM_ADDRESS,
+            phosphor: false,
+            fullscreen: false,
+            vsync: false,
+            debug_window: false,
+            keypad_overlay: false,
+            stats: false,
+            pause_in_background: true,
+            crt: Crt::default(),
+            rewind_buffer: 4,
+            theme: Theme::default(),
+            colors: Colors::default(),
+            audio: Audio::default(),
+            gamepad: Gamepad::default(),
+            keys: Bindings::default(),
+            quirks: Quirks::default(),
+            guess_quirks: false,
+            protect_interpreter: false,
+            latch_keys: false,
+            unknown_opcode: UnknownOpcode::default(),
+            roms: HashMap::new(),
+        }
+    }
+}
+
+/// What happens when a ROM executes an opcode which isn't an instruction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownOpcode {
+    /// Stop with an error report.
+    #[default]
+    Halt,
+    /// Report it and continue after it.
+    Skip,
+    /// Pause at it in the debugger, attaching one if there is none.
+    Break,
+}
+
+/// Settings for a single ROM, from a `<rom>.toml` file next to it such as
+/// `game.ch8.toml`, or else from a `[roms."<sha1>"]` table. They take
+/// precedence over all other settings.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RomConfig {
+    /// Interpreter to use the quirks and speed of, before applying `speed`
+    /// and `quirks`.
+    pub platform: Option<Platform>,
+    pub speed: Option<u32>,
+    /// Quirks replacing all the configured ones.
+    pub quirks: Option<Quirks>,
+    pub load_address: Option<usize>,
+    pub theme: Option<Theme>,
+    pub colors: Colors,
+    pub keypad: Option<[Key; 16]>,
+    pub gamepad: Option<HashMap<PadButton, u8>>,
+}
+
+impl RomConfig {
+    /// Checks what deserializing doesn't.
+    fn check(&self) -> Result<(), String> {
+        self.gamepad.as_ref().map_or(Ok(()), keymap::check_gamepad)
+    }
+
+    /// Applies the settings to a ROM loaded with the rest of the
+    /// configuration applied.
+    pub fn apply(&self, emulator: &mut Emulator, palette: &mut Palette) {
+        if let Some(platform) = self.platform {
+            emulator.quirks = platform.quirks();
+            emulator.set_speed(platform.speed());
+        }
+        if let Some(speed) = self.speed {
+            emulator.set_speed(speed);
+        }
+        emulator.quirks = self.quirks.unwrap_or(emulator.quirks);
+        if let Some(theme) = self.theme {
+            *palette = theme.palette();
+        }
+        palette.colors[0] = self.colors.background.unwrap_or(palette.colors[0]);
+        palette.colors[1] = self.colors.foreground.unwrap_or(palette.colors[1]);
+    }
+}
+
+/// Colors overriding the ones from the theme.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub foreground: Option<Rgb>,
+    pub background: Option<Rgb>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Audio {
+    pub enabled: bool,
+    pub volume: f32,
+    pub waveform: Waveform,
+    /// Frequency of the tone in Hz.
+    pub frequency: f32,
+    /// Part of each period the square wave is high for, from 0 to 1.
+    pub duty_cycle: f32,
+    /// Shows the buzzer on screen too, or instead with `enabled = false`.
+    pub visual_beep: VisualBeep,
+}
+
+impl Audio {
+    pub fn tone(&self) -> Tone {
+        Tone {
+            waveform: self.waveform,
+            frequency: self.frequency,
+            duty_cycle: self.duty_cycle,
+        }
+    }
+}
+
+impl Default for Audio {
+    fn default() -> Audio {
+        Audio {
+            enabled: true,
+            volume: audio::DEFAULT_VOLUME,
+            waveform: Waveform::default(),
+            frequency: audio::DEFAULT_FREQUENCY,
+            duty_cycle: 0.5,
+            visual_beep: VisualBeep::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Gamepad {
+    pub enabled: bool,
+    /// SDL2 `gamecontrollerdb.txt` with mappings for controllers SDL doesn't
+    /// know.
+    pub mappings: Option<PathBuf>,
+    /// Strength controllers rumble with while the sound timer is active,
+    /// from 0 for off to 1.
+    pub rumble: f32,
+}
+
+impl Default for Gamepad {
+    fn default() -> Gamepad {
+        Gamepad {
+            enabled: true,
+            mappings: None,
+            rumble: 0.0,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration from `path`, or when it's `None` from the
+    /// first `chip8.toml` found in the working directory or the user's
+    /// config directory. Returns the defaults when there is no such file.
+    pub fn load(path: Option<&Path>) -> Result<Config, String> {
+        let Some(path) = path.map(Path::to_path_buf).or_else(find) else {
+            return Ok(Config::default());
+        };
+        let error = |e: &dyn fmt::Display| format!("{}: {}", path.display(), e);
+        let text = std::fs::read_to_string(&path).map_err(|e| error(&e))?;
+        let config: Config = toml::from_str(&text).map_err(|e| error(&e))?;
+        config.check().map_err(|e| error(&e))?;
+        Ok(config)
+    }
+
+    /// Checks what deserializing doesn't, for the values loaded and again
+    /// once the command line flags are applied over them.
+    pub fn check(&self) -> Result<(), String> {
+        if self.scale == 0 {
+            return Err("scale must be at least 1".to_string());
+        }
+        if !(1..=STACK_SIZE).contains(&self.stack_limit) {
+            return Err(format!("stack_limit must be from 1 to {}", STACK_SIZE));
+        }
+        if !self.audio.frequency.is_finite() || self.audio.frequency <= 0.0 {
+            return Err("audio.frequency must be positive".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.audio.duty_cycle) {
+            return Err("audio.duty_cycle must be from 0 to 1".to_string());
+        }
+        keymap::check_gamepad(&self.keys.gamepad)?;
+        for rom_config in self.roms.values() {
+            rom_config.check()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the settings for the ROM `rom` read from `path`, from its
+    /// sidecar file or its table in the configuration.
+    pub fn rom_config(&self, path: &Path, rom: &[u8]) -> Result<RomConfig, String> {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(".toml");
+        let sidecar = PathBuf::from(sidecar);
+        if sidecar.is_file() {
+            let error = |e: &dyn fmt::Display| format!("{}: {}", sidecar.display(), e);
+            let text = std::fs::read_to_string(&sidecar).map_err(|e| error(&e))?;
+            let rom_config: RomConfig = toml::from_str(&text).map_err(|e| error(&e))?;
+            rom_config.check().map_err(|e| error(&e))?;
+            return Ok(rom_config);
+        }
+        let hash = format!("{:x}", Sha1::digest(rom));
+        let rom_config = self
+            .roms
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(&hash))
+            .map(|(_, rom_config)| rom_config.clone());
+        Ok(rom_config.unwrap_or_default())
+    }
+
+    /// Returns the theme's palette with the configured colors applied.
+    pub fn palette(&self) -> Palette {
+        let mut palette = self.theme.palette();
+        if let Some(background) = self.colors.background {
+            palette.colors[0] = background;
+        }
+        if let Some(foreground) = self.colors.foreground {
+            palette.colors[1] = foreground;
+        }
+        palette
+    }
+}
+
+fn find() -> Option<PathBuf> {
+    let local = Some(PathBuf::from(FILE_NAME));
+    let user = dirs::config_dir().map(|dir| dir.join("chip8").join(FILE_NAME));
+    [local, user]
+        .into_iter()
+        .flatten()
+        .find(|path| path.is_file())
+}
@@ -0,0 +1,71 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::io;
+use std::path::PathBuf;
+
+use chip8::{disasm, hexdump, Chip8Error, Emulator};
+
+use crate::screenshot;
+
+/// Instructions listed in a crash dump.
+const HISTORY: usize = 64;
+
+/// The most recently executed instructions, written to a crash dump along
+/// with the rest of the machine state when the emulator stops on an error.
+pub struct CrashLog {
+    recent: VecDeque<(usize, u16)>,
+}
+
+impl CrashLog {
+    pub fn new() -> CrashLog {
+        CrashLog {
+            recent: VecDeque::with_capacity(HISTORY),
+        }
+    }
+
+    /// Records the instruction at PC, before executing it.
+    pub fn record(&mut self, emulator: &Emulator) {
+        let Ok(opcode) = emulator.current_opcode() else {
+            return;
+        };
+        if self.recent.len() == HISTORY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((emulator.pc, opcode));
+    }
+
+    /// Writes a crash dump for `error`, returning a message with the error
+    /// and where the dump went.
+    pub fn report(&self, emulator: &Emulator, error: &Chip8Error) -> String {
+        match self.write(emulator, error) {
+            Ok(path) => format!("{}\nCrash dump written to {}", error, path.display()),
+            Err(e) => format!("{}\nFailed to write a crash dump: {}", error, e),
+        }
+    }
+
+    /// Writes the error, registers, stack, last instructions, display and
+    /// memory to a timestamped file in the working directory.
+    fn write(&self, emulator: &Emulator, error: &Chip8Error) -> io::Result<PathBuf> {
+        let mut s = format!("Error: {}\n\n", error);
+        s += &emulator.registers_string();
+        s += "\nStack:\n";
+        for (i, addr) in emulator.stack.iter().enumerate().rev() {
+            writeln!(s, "[{:>2}]: {:#05X}", i, addr).unwrap();
+        }
+        writeln!(s, "\nLast {} instructions:", self.recent.len()).unwrap();
+        for &(pc, opcode) in &self.recent {
+            let line = disasm::disassemble(&opcode.to_be_bytes(), pc)
+                .next()
+                .unwrap();
+            writeln!(s, "{}", line).unwrap();
+        }
+        s += "\nDisplay:\n";
+        s += &emulator.display_string();
+        s += "\nMemory:\n";
+        s += &hexdump::hexdump(&emulator.memory, 0, &[]);
+
+        let path = screenshot::timestamped_path("crash.txt");
+        std::fs::write(&path, s)?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use chip8::palette::Rgb;
+use chip8::{Platform, Quirks, FRAME_RATE};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+
+/// Settings for a ROM known to the database.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub title: String,
+    /// Speed in instructions per second.
+    pub speed: Option<u32>,
+    pub quirks: Quirks,
+    pub background: Option<Rgb>,
+    pub foreground: Option<Rgb>,
+}
+
+#[derive(Deserialize)]
+struct Program {
+    title: String,
+    roms: HashMap<String, Rom>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rom {
+    #[serde(default)]
+    platforms: Vec<String>,
+    /// Instructions per frame.
+    tickrate: Option<u32>,
+    #[serde(default)]
+    colors: Colors,
+    /// Quirks which differ from those of a platform, by platform.
+    #[serde(default)]
+    quirky_platforms: HashMap<String, HashMap<String, bool>>,
+}
+
+#[derive(Default, Deserialize)]
+struct Colors {
+    /// Background color followed by the colors of set pixels.
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+/// ROM settings from the `programs.json` of the CHIP-8 Archive's community
+/// database, looked up by the SHA-1 hash of the ROM.
+pub struct Database {
+    roms: HashMap<String, Settings>,
+}
+
+impl Database {
+    pub fn load(path: &Path) -> Result<Database, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let programs: Vec<Program> = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        let mut roms = HashMap::new();
+        for program in programs {
+            for (hash, rom) in program.roms {
+                let settings = rom.settings(&program.title);
+                roms.insert(hash.to_ascii_lowercase(), settings);
+            }
+        }
+        Ok(Database { roms })
+    }
+
+    pub fn lookup(&self, rom: &[u8]) -> Option<&Settings> {
+        self.roms.get(&format!("{:x}", Sha1::digest(rom)))
+    }
+}
+
+impl Rom {
+    fn settings(&self, title: &str) -> Settings {
+        let platform = self.platforms.first().map_or("", String::as_str);
+        let mut quirks = match platform {
+            "originalChip8" | "hybridVIP" => Platform::Chip8.quirks(),
+            "chip48" => Platform::Chip48.quirks(),
+            "superchip1" | "superchip" => Platform::Schip.quirks(),
+            "xochip" => Platform::XoChip.quirks(),
+            _ => Quirks::default(),
+        };
+        for (name, &value) in self.quirky_platforms.get(platform).into_iter().flatten() {
+            match name.as_str() {
+                "vblank" => quirks.display_wait = value,
+                "logic" => quirks.vf_reset = value,
+                "memoryLeaveIUnchanged" => quirks.memory_increment = !value,
+                "shift" => quirks.shift_vy = !value,
+                "jump" => quirks.jump_vx = value,
+                "wrap" => quirks.wrap = value,
+                _ => {}
+            }
+        }
+        let color = |i: usize| self.colors.pixels.get(i).and_then(|s| s.parse().ok());
+        Settings {
+            title: title.to_string(),
+            speed: self.tickrate.map(|tickrate| tickrate * FRAME_RATE),
+            quirks,
+            background: color(0),
+            foreground: color(1),
+        }
+    }
+}
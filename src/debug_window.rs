@@ -0,0 +1,54 @@
+use chip8::palette::{Palette, Rgb};
+use chip8::Emulator;
+use sdl2::pixels::Color;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::VideoSubsystem;
+
+use crate::overlay;
+use crate::text::{GLYPH_WIDTH, LINE_HEIGHT};
+
+/// Size of the window's contents in characters and lines.
+const COLUMNS: i32 = 40;
+const LINES: i32 = 16;
+/// Initial size in window pixels per font pixel.
+const SCALE: i32 = 3;
+
+/// Window of its own showing what the debug overlay shows, keeping the game
+/// window free of it.
+pub struct DebugWindow {
+    canvas: Canvas<Window>,
+}
+
+impl DebugWindow {
+    pub fn new(video: &VideoSubsystem) -> Result<DebugWindow, String> {
+        let width = (COLUMNS * GLYPH_WIDTH * SCALE) as u32;
+        let height = (LINES * LINE_HEIGHT * SCALE) as u32;
+        let window = video
+            .window("CHIP-8 debugger", width, height)
+            .resizable()
+            .allow_highdpi()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+        Ok(DebugWindow { canvas })
+    }
+
+    /// ID of the window, which its events are tagged with.
+    pub fn id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    /// Redraws the window with the current state of `emulator`.
+    pub fn draw(&mut self, emulator: &Emulator, palette: &Palette) {
+        let (width, height) = self.canvas.output_size().unwrap();
+        let px = (width as i32 / (COLUMNS * GLYPH_WIDTH))
+            .min(height as i32 / (LINES * LINE_HEIGHT))
+            .max(1);
+        let Rgb(r, g, b) = palette.background();
+        self.canvas.set_draw_color(Color::RGB(r, g, b));
+        self.canvas.clear();
+        overlay::draw_state(&mut self.canvas, emulator, palette, px, COLUMNS as usize);
+        self.canvas.present();
+    }
+}
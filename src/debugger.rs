@@ -0,0 +1,914 @@
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+
+use chip8::disasm::{self, Labels, Pattern};
+use chip8::{hexdump, Access, Chip8Error, Emulator, Quirks, Snapshot};
+
+use crate::states;
+
+const DISASM_LINES: usize = 10;
+const MEM_BYTES: usize = 64;
+/// Instructions between the snapshots step-back re-executes from.
+const CHECKPOINT_INTERVAL: usize = 100;
+/// Snapshots kept for step-back, about 450 KiB.
+const MAX_CHECKPOINTS: usize = 100;
+
+const HELP: &str = "\
+Commands:
+  break [where] [if <reg> <op> <value>]
+                  set a breakpoint at an address, or with `op <pattern>` on
+                  instructions matching an opcode pattern like DXYN or 8XY6,
+                  pausing only while the condition holds if given, or list
+                  breakpoints
+  clear <where>   remove the breakpoint at an address or pattern
+  watch [range] [read|write]
+                  pause when an instruction reads or writes memory in range,
+                  given as addr or start..end (default both), or list
+                  watchpoints
+  unwatch <range> remove the watchpoint on range
+  step [n]        execute n instructions (default 1)
+  next            execute an instruction, running a subroutine it calls to
+                  completion
+  finish          run until the current subroutine returns
+  back [n]        undo the last n instructions (default 1)
+  continue        resume execution
+  pause           pause execution
+  regs            print V0-VF, I, PC, SP and timers
+  stack           print the call stack, with the subroutine each frame is
+                  in
+  disasm [addr] [n]
+                  disassemble n instructions from addr (default PC)
+  mem [addr] [n]  dump n bytes of memory from addr (default I), with the
+                  bytes at PC and I highlighted
+  poke <addr> <byte>...
+                  write bytes to memory from addr
+  dump <file>     write all of memory to file as a raw image
+  restore <file>  load a raw memory image from file into memory from 0
+  diff [file]     print what changed since execution last resumed, or from
+                  the save state in file to now
+  set <reg> <value>
+                  set V0-VF, I, PC, DT or ST to value
+  quirk [name] [on|off]
+                  flip a quirk, or turn it on or off, without resetting, or
+                  list the quirks
+  help            print this message";
+
+/// Controls execution from outside the emulator, like a debugger.
+pub trait Control {
+    /// Handles pending commands.
+    fn poll(&mut self, emulator: &mut Emulator);
+
+    fn is_paused(&self) -> bool;
+
+    /// Pauses execution at the instruction at PC, which failed with `error`.
+    fn stop(&mut self, emulator: &Emulator, error: &Chip8Error);
+
+    /// Returns true when the emulator may execute the next instruction.
+    fn can_run(&mut self, emulator: &Emulator) -> bool;
+}
+
+/// Stdin driven debugger. Commands are read on a separate thread so the
+/// SDL2 window keeps pumping events while waiting for input.
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    paused: bool,
+    /// Set when resuming from a breakpoint, so it doesn't trigger again
+    /// before the instruction at PC has executed.
+    skip_breakpoint: bool,
+    /// Depth to pause at once the stack is no deeper than it, for `next`
+    /// and `finish`, checked after the first instruction.
+    until_depth: Option<usize>,
+    watchpoints: Vec<Watchpoint>,
+    /// Watchpoint hit by the last instruction, reported before the next.
+    watch_hit: Option<WatchHit>,
+    history: History,
+    /// State when execution last resumed or stepped, for `diff`.
+    resumed: Option<Emulator>,
+    /// Names of subroutines, for the call stack.
+    labels: Labels,
+    commands: Receiver<String>,
+}
+
+impl Control for Debugger {
+    fn poll(&mut self, emulator: &mut Emulator) {
+        while let Ok(line) = self.commands.try_recv() {
+            self.handle(emulator, &line);
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn stop(&mut self, emulator: &Emulator, error: &Chip8Error) {
+        self.paused = true;
+        println!("Stopped: {}", error);
+        print_current(emulator);
+    }
+
+    fn can_run(&mut self, emulator: &Emulator) -> bool {
+        if self.paused {
+            return false;
+        }
+        if let Some(hit) = self.watch_hit.take() {
+            self.paused = true;
+            self.until_depth = None;
+            hit.report(emulator);
+            print_current(emulator);
+            return false;
+        }
+        let skip = std::mem::take(&mut self.skip_breakpoint);
+        if let Some(depth) = self.until_depth.filter(|_| !skip) {
+            if emulator.stack.len() <= depth {
+                self.paused = true;
+                self.until_depth = None;
+                print_current(emulator);
+                return false;
+            }
+        }
+        let hit = self.breakpoints.iter().find(|b| b.hits(emulator));
+        if let Some(breakpoint) = hit.filter(|_| !skip) {
+            self.paused = true;
+            self.until_depth = None;
+            if let Target::Opcode(_) = &breakpoint.target {
+                println!("Breakpoint {} hit at {:#05X}", breakpoint, emulator.pc);
+            } else {
+                println!("Breakpoint hit at {:#05X}", emulator.pc);
+            }
+            print_current(emulator);
+            return false;
+        }
+        self.watch_hit = self.check_watchpoints(emulator);
+        self.history.record(emulator);
+        true
+    }
+}
+
+impl Debugger {
+    pub fn new(labels: Labels) -> Debugger {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        println!("Debugger attached, execution paused. Type `help` for a list of commands.");
+        Debugger {
+            breakpoints: Vec::new(),
+            paused: true,
+            skip_breakpoint: false,
+            until_depth: None,
+            watchpoints: Vec::new(),
+            watch_hit: None,
+            history: History::default(),
+            resumed: None,
+            labels,
+            commands: rx,
+        }
+    }
+
+    /// Returns the watchpoint hit the instruction at PC will cause, if any.
+    fn check_watchpoints(&self, emulator: &Emulator) -> Option<WatchHit> {
+        let access = emulator.memory_access()?;
+        self.watchpoints
+            .iter()
+            .any(|watchpoint| watchpoint.matches(&access))
+            .then_some(WatchHit {
+                pc: emulator.pc,
+                access,
+            })
+    }
+
+    /// Resumes until the stack is at most `depth` deep after an instruction,
+    /// or a breakpoint or watchpoint is hit first.
+    fn run_until(&mut self, emulator: &Emulator, depth: usize) {
+        self.mark_resumed(emulator);
+        self.paused = false;
+        self.skip_breakpoint = true;
+        self.watch_hit = None;
+        self.until_depth = Some(depth);
+    }
+
+    /// Remembers the machine's state to `diff` against later.
+    fn mark_resumed(&mut self, emulator: &Emulator) {
+        match &mut self.resumed {
+            Some(resumed) => resumed.clone_from(emulator),
+            None => self.resumed = Some(emulator.clone()),
+        }
+    }
+
+    fn handle(&mut self, emulator: &mut Emulator, line: &str) {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return;
+        };
+        if let "watch" | "w" | "unwatch" = command {
+            let words: Vec<&str> = words.collect();
+            if let Err(e) = self.handle_watch(command, &words) {
+                println!("{}", e);
+            }
+            return;
+        }
+        if let "break" | "b" | "clear" = command {
+            let words: Vec<&str> = words.collect();
+            if let Err(e) = self.handle_break(command, &words) {
+                println!("{}", e);
+            }
+            return;
+        }
+        if let "dump" | "restore" = command {
+            // The rest of the line, so that paths may contain spaces.
+            let path = line.trim_start()[command.len()..].trim();
+            if path.is_empty() {
+                println!("Usage: {} <file>", command);
+                return;
+            }
+            let path = Path::new(path);
+            if command == "dump" {
+                match dump_memory(emulator, path) {
+                    Ok(()) => println!("Wrote memory to {}", path.display()),
+                    Err(e) => println!("Failed to write {}: {}", path.display(), e),
+                }
+            } else {
+                match restore_memory(emulator, path) {
+                    Ok(size) => {
+                        self.history = History::default();
+                        println!("Loaded {} bytes from {}", size, path.display());
+                    }
+                    Err(e) => println!("Failed to load {}: {}", path.display(), e),
+                }
+            }
+            return;
+        }
+        if command == "diff" {
+            let path = line.trim_start()[command.len()..].trim();
+            if !path.is_empty() {
+                match states::read(Path::new(path)) {
+                    Ok(old) => print!("{}", states::diff(&old, emulator)),
+                    Err(e) => println!("Failed to read {}: {}", path, e),
+                }
+            } else if let Some(old) = &self.resumed {
+                print!("{}", states::diff(old, emulator));
+            } else {
+                println!("Execution hasn't resumed yet");
+            }
+            return;
+        }
+        if command == "quirk" {
+            let words: Vec<&str> = words.collect();
+            match set_quirk(emulator, &words) {
+                Ok(()) => self.history = History::default(),
+                Err(e) => println!("{}", e),
+            }
+            return;
+        }
+        if command == "set" {
+            let words: Vec<&str> = words.collect();
+            match set_register(emulator, &words) {
+                Ok(()) => self.history = History::default(),
+                Err(e) => println!("{}", e),
+            }
+            return;
+        }
+        let args = match words.map(parse_number).collect::<Result<Vec<_>, _>>() {
+            Ok(args) => args,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        match (command, args.as_slice()) {
+            ("next" | "n", []) => self.run_until(emulator, emulator.stack.len()),
+            ("finish" | "f", []) => match emulator.stack.len().checked_sub(1) {
+                Some(depth) => self.run_until(emulator, depth),
+                None => println!("Not in a subroutine"),
+            },
+            ("step" | "s", [] | [_]) => {
+                let count = args.first().copied().unwrap_or(1);
+                self.paused = true;
+                self.mark_resumed(emulator);
+                for _ in 0..count {
+                    let hit = self.check_watchpoints(emulator);
+                    self.history.record(emulator);
+                    if let Err(e) = emulator.step() {
+                        println!("Stopped: {}", e);
+                        break;
+                    }
+                    if let Some(hit) = hit {
+                        hit.report(emulator);
+                        break;
+                    }
+                }
+                print_current(emulator);
+            }
+            ("back", [] | [_]) => {
+                let count = args.first().copied().unwrap_or(1);
+                self.paused = true;
+                self.watch_hit = None;
+                for i in 0..count {
+                    if !self.history.step_back(emulator) {
+                        println!("No earlier instructions recorded, went back {}", i);
+                        break;
+                    }
+                }
+                print_current(emulator);
+            }
+            ("continue" | "c", []) => {
+                self.mark_resumed(emulator);
+                self.paused = false;
+                self.skip_breakpoint = true;
+                self.watch_hit = None;
+                self.until_depth = None;
+            }
+            ("pause", []) => {
+                self.paused = true;
+                self.until_depth = None;
+                print_current(emulator);
+            }
+            ("regs" | "r", []) => print_registers(emulator),
+            ("stack", []) => print_stack(emulator, &self.labels),
+            ("disasm" | "d", [] | [_] | [_, _]) => {
+                let addr = args.first().copied().unwrap_or(emulator.pc);
+                let count = args.get(1).copied().unwrap_or(DISASM_LINES);
+                let end = addr
+                    .saturating_add(count.saturating_mul(2))
+                    .min(emulator.memory.len());
+                if let Some(bytes) = emulator.memory.get(addr..end) {
+                    for line in disasm::disassemble(bytes, addr) {
+                        println!("{}", line);
+                    }
+                }
+            }
+            ("mem" | "m", [] | [_] | [_, _]) => {
+                let addr = args.first().copied().unwrap_or(emulator.index_register);
+                let count = args.get(1).copied().unwrap_or(MEM_BYTES);
+                let end = addr.saturating_add(count).min(emulator.memory.len());
+                if let Some(bytes) = emulator.memory.get(addr..end) {
+                    let highlight = [emulator.pc, emulator.pc + 1, emulator.index_register];
+                    print!("{}", hexdump::hexdump(bytes, addr, &highlight));
+                }
+            }
+            ("poke", [addr, bytes @ ..]) if !bytes.is_empty() => {
+                let Some(bytes) = bytes
+                    .iter()
+                    .map(|&byte| u8::try_from(byte).ok())
+                    .collect::<Option<Vec<u8>>>()
+                else {
+                    println!("Values must be bytes, at most 0xFF");
+                    return;
+                };
+                let end = addr.saturating_add(bytes.len());
+                let Some(memory) = emulator.memory.get_mut(*addr..end) else {
+                    println!("{:#05X}..{:#05X} is out of memory", addr, end);
+                    return;
+                };
+                memory.copy_from_slice(&bytes);
+                // Stepping back re-executes from before the change.
+                self.history = History::default();
+                println!("Wrote {} bytes at {:#05X}", bytes.len(), addr);
+            }
+            ("help" | "h", []) => println!("{}", HELP),
+            _ => println!(
+                "Invalid command `{}`, type `help` for a list of commands",
+                line
+            ),
+        }
+    }
+
+    fn handle_break(&mut self, command: &str, words: &[&str]) -> Result<(), String> {
+        match (command, words) {
+            ("break" | "b", []) => {
+                if self.breakpoints.is_empty() {
+                    println!("No breakpoints set");
+                }
+                for breakpoint in &self.breakpoints {
+                    println!("{}", breakpoint);
+                }
+            }
+            ("break" | "b", words) => {
+                let (target, rest) = Target::parse(words)?;
+                let condition = match rest {
+                    [] => None,
+                    ["if", condition @ ..] => Some(Condition::parse(condition)?),
+                    _ => return Err("Usage: break [where] [if <reg> <op> <value>]".to_string()),
+                };
+                self.breakpoints
+                    .retain(|breakpoint| breakpoint.target != target);
+                let breakpoint = Breakpoint { target, condition };
+                println!("Breakpoint set on {}", breakpoint);
+                self.breakpoints.push(breakpoint);
+            }
+            ("clear", words) => {
+                let (target, []) = Target::parse(words)? else {
+                    return Err("Usage: clear op <pattern> or clear <addr>".to_string());
+                };
+                let count = self.breakpoints.len();
+                self.breakpoints
+                    .retain(|breakpoint| breakpoint.target != target);
+                if self.breakpoints.len() < count {
+                    println!("Breakpoint on {} cleared", target);
+                } else {
+                    println!("No breakpoint on {}", target);
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "Invalid command `{} {}`, type `help` for a list of commands",
+                    command,
+                    words.join(" ")
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_watch(&mut self, command: &str, words: &[&str]) -> Result<(), String> {
+        match (command, words) {
+            ("watch" | "w", []) => {
+                if self.watchpoints.is_empty() {
+                    println!("No watchpoints set");
+                }
+                for watchpoint in &self.watchpoints {
+                    println!("{}", watchpoint);
+                }
+            }
+            ("watch" | "w", [range, kind @ ..]) if kind.len() <= 1 => {
+                let range = parse_range(range)?;
+                let (read, write) = match kind.first().copied() {
+                    None => (true, true),
+                    Some("read" | "r") => (true, false),
+                    Some("write" | "w") => (false, true),
+                    Some(kind) => return Err(format!("Invalid watchpoint kind `{}`", kind)),
+                };
+                self.watchpoints
+                    .retain(|watchpoint| watchpoint.range != range);
+                let watchpoint = Watchpoint { range, read, write };
+                println!("Watchpoint set on {}", watchpoint);
+                self.watchpoints.push(watchpoint);
+            }
+            ("unwatch", [range]) => {
+                let range = parse_range(range)?;
+                let count = self.watchpoints.len();
+                self.watchpoints
+                    .retain(|watchpoint| watchpoint.range != range);
+                if self.watchpoints.len() < count {
+                    println!("Watchpoint on {} cleared", format_range(&range));
+                } else {
+                    println!("No watchpoint on {}", format_range(&range));
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "Invalid command `{} {}`, type `help` for a list of commands",
+                    command,
+                    words.join(" ")
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Executed instructions, as snapshots taken every [`CHECKPOINT_INTERVAL`]
+/// instructions and the inputs before each instruction since. Stepping back
+/// restores the last snapshot and re-executes up to the previous
+/// instruction, with the timers and keys it saw.
+#[derive(Default)]
+struct History {
+    checkpoints: VecDeque<Checkpoint>,
+}
+
+struct Checkpoint {
+    snapshot: Snapshot,
+    inputs: Vec<Inputs>,
+}
+
+/// State which changes between instructions without executing any.
+struct Inputs {
+    delay_timer: u8,
+    sound_timer: u8,
+    keys: [bool; 16],
+}
+
+impl History {
+    /// Records the state before the instruction at PC executes.
+    fn record(&mut self, emulator: &Emulator) {
+        if self
+            .checkpoints
+            .back()
+            .is_none_or(|checkpoint| checkpoint.inputs.len() == CHECKPOINT_INTERVAL)
+        {
+            if self.checkpoints.len() == MAX_CHECKPOINTS {
+                self.checkpoints.pop_front();
+            }
+            self.checkpoints.push_back(Checkpoint {
+                snapshot: emulator.snapshot(),
+                inputs: Vec::new(),
+            });
+        }
+        let inputs = Inputs {
+            delay_timer: emulator.delay_timer,
+            sound_timer: emulator.sound_timer,
+            keys: emulator.keys,
+        };
+        self.checkpoints.back_mut().unwrap().inputs.push(inputs);
+    }
+
+    /// Rolls the emulator back to before the last recorded instruction,
+    /// returning false when there is none.
+    fn step_back(&mut self, emulator: &mut Emulator) -> bool {
+        let Some(checkpoint) = self.checkpoints.back_mut() else {
+            return false;
+        };
+        let last = checkpoint.inputs.pop().unwrap();
+        emulator.restore(&checkpoint.snapshot);
+        for inputs in &checkpoint.inputs {
+            inputs.apply(emulator);
+            // Instructions which failed the first time fail the same way.
+            let _ = emulator.step();
+        }
+        last.apply(emulator);
+        if checkpoint.inputs.is_empty() {
+            self.checkpoints.pop_back();
+        }
+        true
+    }
+}
+
+impl Inputs {
+    fn apply(&self, emulator: &mut Emulator) {
+        emulator.delay_timer = self.delay_timer;
+        emulator.sound_timer = self.sound_timer;
+        emulator.keys = self.keys;
+    }
+}
+
+/// Pauses execution before an instruction at an address or matching an
+/// opcode pattern executes, while the condition holds if there is one.
+struct Breakpoint {
+    target: Target,
+    condition: Option<Condition>,
+}
+
+impl Breakpoint {
+    fn hits(&self, emulator: &Emulator) -> bool {
+        let at = match &self.target {
+            Target::Address(addr) => emulator.pc == *addr,
+            Target::Opcode(pattern) => emulator
+                .current_opcode()
+                .is_ok_and(|opcode| pattern.matches(opcode)),
+        };
+        at && self.condition.as_ref().is_none_or(|c| c.holds(emulator))
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.target)?;
+        if let Some(condition) = &self.condition {
+            write!(f, " if {}", condition)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(PartialEq, Eq)]
+enum Target {
+    Address(usize),
+    Opcode(Pattern),
+}
+
+impl Target {
+    /// Parses `op <pattern>` as an opcode pattern, or else a number as an
+    /// address, returning the words after it. The keyword keeps patterns of
+    /// digits only, like `1228`, from being read as addresses.
+    fn parse<'a, 'b>(words: &'a [&'b str]) -> Result<(Target, &'a [&'b str]), String> {
+        match words {
+            ["op", pattern, rest @ ..] => Ok((Target::Opcode(pattern.parse()?), rest)),
+            [addr, rest @ ..] if *addr != "op" => Ok((Target::Address(parse_number(addr)?), rest)),
+            _ => Err("Expected an address or `op <pattern>`".to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Target::Address(addr) => write!(f, "{:#05X}", addr),
+            Target::Opcode(pattern) => write!(f, "op {}", pattern),
+        }
+    }
+}
+
+/// Comparison of a register with a value, like `V3 == 0`.
+struct Condition {
+    register: Register,
+    operator: &'static str,
+    value: usize,
+}
+
+impl Condition {
+    const OPERATORS: [&'static str; 6] = ["==", "!=", "<=", ">=", "<", ">"];
+
+    fn parse(words: &[&str]) -> Result<Condition, String> {
+        let &[register, operator, value] = words else {
+            return Err("Conditions are written as <reg> <op> <value>".to_string());
+        };
+        let register = Register::parse(register)?;
+        let operator = Condition::OPERATORS
+            .into_iter()
+            .find(|&op| op == operator)
+            .ok_or_else(|| format!("Unknown comparison `{}`", operator))?;
+        Ok(Condition {
+            register,
+            operator,
+            value: parse_number(value)?,
+        })
+    }
+
+    fn holds(&self, emulator: &Emulator) -> bool {
+        let register = self.register.read(emulator);
+        match self.operator {
+            "==" => register == self.value,
+            "!=" => register != self.value,
+            "<=" => register <= self.value,
+            ">=" => register >= self.value,
+            "<" => register < self.value,
+            _ => register > self.value,
+        }
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {} {:#X}", self.register, self.operator, self.value)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Register {
+    V(usize),
+    I,
+    Pc,
+    Dt,
+    St,
+}
+
+impl Register {
+    fn parse(name: &str) -> Result<Register, String> {
+        let register = match name.to_ascii_uppercase().as_str() {
+            "I" => Register::I,
+            "PC" => Register::Pc,
+            "DT" => Register::Dt,
+            "ST" => Register::St,
+            register => register
+                .strip_prefix('V')
+                .filter(|digit| digit.len() == 1)
+                .and_then(|digit| usize::from_str_radix(digit, 16).ok())
+                .map(Register::V)
+                .ok_or_else(|| format!("Unknown register `{}`", name))?,
+        };
+        Ok(register)
+    }
+
+    fn read(self, emulator: &Emulator) -> usize {
+        match self {
+            Register::V(index) => emulator.var_registers[index].into(),
+            Register::I => emulator.index_register,
+            Register::Pc => emulator.pc,
+            Register::Dt => emulator.delay_timer.into(),
+            Register::St => emulator.sound_timer.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Register::V(index) => write!(f, "V{:X}", index),
+            Register::I => write!(f, "I"),
+            Register::Pc => write!(f, "PC"),
+            Register::Dt => write!(f, "DT"),
+            Register::St => write!(f, "ST"),
+        }
+    }
+}
+
+/// A memory range which pauses execution when read or written.
+struct Watchpoint {
+    range: Range<usize>,
+    read: bool,
+    write: bool,
+}
+
+impl Watchpoint {
+    fn matches(&self, access: &Access) -> bool {
+        let kind = match access {
+            Access::Read(_) => self.read,
+            Access::Write(_) => self.write,
+        };
+        let range = access.range();
+        kind && range.start < self.range.end && self.range.start < range.end
+    }
+}
+
+impl std::fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let kind = match (self.read, self.write) {
+            (true, false) => "read",
+            (false, true) => "write",
+            _ => "read/write",
+        };
+        write!(f, "{} ({})", format_range(&self.range), kind)
+    }
+}
+
+/// An instruction which accessed watched memory.
+struct WatchHit {
+    pc: usize,
+    access: Access,
+}
+
+impl WatchHit {
+    /// Prints the instruction and the accessed bytes as they are after it
+    /// executed.
+    fn report(&self, emulator: &Emulator) {
+        let (verb, range) = match &self.access {
+            Access::Read(range) => ("read", range),
+            Access::Write(range) => ("wrote", range),
+        };
+        let values = emulator
+            .memory
+            .get(range.clone())
+            .unwrap_or_default()
+            .iter()
+            .map(|value| format!("{:02X}", value))
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!(
+            "Watchpoint hit: {:#05X} {} {}: {}",
+            self.pc,
+            verb,
+            format_range(range),
+            values
+        );
+    }
+}
+
+/// Parses an address, or an address range written as `start..end`.
+fn parse_range(s: &str) -> Result<Range<usize>, String> {
+    let range = match s.split_once("..") {
+        Some((start, end)) => parse_number(start)?..parse_number(end)?,
+        None => {
+            let addr = parse_number(s)?;
+            addr..addr + 1
+        }
+    };
+    if range.is_empty() {
+        return Err(format!("Empty range `{}`", s));
+    }
+    Ok(range)
+}
+
+fn format_range(range: &Range<usize>) -> String {
+    if range.len() == 1 {
+        format!("{:#05X}", range.start)
+    } else {
+        format!("{:#05X}..{:#05X}", range.start, range.end)
+    }
+}
+
+/// Parses a hexadecimal number prefixed with `0x`, or a decimal number.
+pub fn parse_number(s: &str) -> Result<usize, String> {
+    let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    result.map_err(|_| format!("Invalid number `{}`", s))
+}
+
+/// Writes all of memory to `path` as a raw image.
+pub fn dump_memory(emulator: &Emulator, path: &Path) -> std::io::Result<()> {
+    std::fs::write(path, &emulator.memory[..])
+}
+
+/// Loads the raw memory image at `path` into memory from address 0, such as
+/// one written by [`dump_memory`] or by another emulator, and returns its
+/// size. Memory past a smaller image is left as it is.
+pub fn restore_memory(emulator: &mut Emulator, path: &Path) -> Result<usize, String> {
+    let image = std::fs::read(path).map_err(|e| e.to_string())?;
+    let Some(memory) = emulator.memory.get_mut(..image.len()) else {
+        return Err(format!(
+            "the image is {} bytes, but memory is {}",
+            image.len(),
+            emulator.memory.len()
+        ));
+    };
+    memory.copy_from_slice(&image);
+    Ok(image.len())
+}
+
+/// Sets the register named by `words[0]` to the number `words[1]`.
+fn set_register(emulator: &mut Emulator, words: &[&str]) -> Result<(), String> {
+    let &[name, value] = words else {
+        return Err("Usage: set <reg> <value>".to_string());
+    };
+    let value = parse_number(value)?;
+    let byte = || u8::try_from(value).map_err(|_| format!("{} must be at most 0xFF", name));
+    match name.to_ascii_uppercase().as_str() {
+        "I" => emulator.index_register = value,
+        "PC" if value < emulator.memory.len() => emulator.pc = value,
+        "PC" => return Err(format!("{:#05X} is out of memory", value)),
+        "DT" => emulator.delay_timer = byte()?,
+        "ST" => emulator.sound_timer = byte()?,
+        register => {
+            let index = register
+                .strip_prefix('V')
+                .filter(|digit| digit.len() == 1)
+                .and_then(|digit| usize::from_str_radix(digit, 16).ok())
+                .ok_or_else(|| format!("Unknown register `{}`", name))?;
+            emulator.var_registers[index] = byte()?;
+        }
+    }
+    println!("{} = {:#X}", name.to_ascii_uppercase(), value);
+    Ok(())
+}
+
+/// Flips the quirk named by `words[0]`, or turns it on or off by
+/// `words[1]`, or lists the quirks without any words.
+fn set_quirk(emulator: &mut Emulator, words: &[&str]) -> Result<(), String> {
+    let (name, value) = match words {
+        [] => {
+            for name in Quirks::NAMES {
+                let on = emulator.quirks.get(name).unwrap();
+                println!("{:<16} {}", name, if on { "on" } else { "off" });
+            }
+            return Ok(());
+        }
+        [name] => (name, None),
+        [name, "on"] => (name, Some(true)),
+        [name, "off"] => (name, Some(false)),
+        _ => return Err("Usage: quirk [name] [on|off]".to_string()),
+    };
+    let quirk = emulator.quirks.get_mut(name).ok_or_else(|| {
+        format!(
+            "Unknown quirk `{}`, expected one of {}",
+            name,
+            Quirks::NAMES.join(", ")
+        )
+    })?;
+    *quirk = value.unwrap_or(!*quirk);
+    println!("{} {}", name, if *quirk { "on" } else { "off" });
+    Ok(())
+}
+
+fn print_current(emulator: &Emulator) {
+    let pc = emulator.pc;
+    match emulator.memory.get(pc..pc + 2) {
+        Some(bytes) => println!("{}", disasm::disassemble(bytes, pc).next().unwrap()),
+        None => println!("{:#05X}: <out of memory>", pc),
+    }
+}
+
+fn print_registers(emulator: &Emulator) {
+    print!("{}", emulator.registers_string());
+}
+
+/// Prints PC and the return addresses, innermost first, each with the
+/// subroutine it's in: the one the call before the return address went to,
+/// named by its label or else like `sub_0x2F0`.
+fn print_stack(emulator: &Emulator, labels: &Labels) {
+    let subroutine = |depth: usize| {
+        let Some(&return_address) = depth.checked_sub(1).and_then(|i| emulator.stack.get(i)) else {
+            return "the main program".to_string();
+        };
+        let call = emulator
+            .memory
+            .get(return_address.wrapping_sub(2)..return_address)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+        match call {
+            Some(opcode) if opcode >> 12 == 0x2 => {
+                let target = (opcode & 0xFFF) as usize;
+                labels
+                    .get(target)
+                    .map_or_else(|| disasm::subroutine_name(target), str::to_string)
+            }
+            _ => "an unknown subroutine".to_string(),
+        }
+    };
+    let depth = emulator.stack.len();
+    println!(
+        "[{:>2}]: {:#05X} in {}",
+        depth,
+        emulator.pc,
+        subroutine(depth)
+    );
+    for (i, addr) in emulator.stack.iter().enumerate().rev() {
+        println!("[{:>2}]: {:#05X} in {}", i, addr, subroutine(i));
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::analyze;
+use crate::instruction::{decode, Instruction};
+
+/// A single instruction at a memory address, displayed as
+/// `0x200: 6A02  LD VA, 0x02`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub address: usize,
+    pub opcode: u16,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:#05X}: {:04X}  {}",
+            self.address,
+            self.opcode,
+            mnemonic(self.opcode)
+        )
+    }
+}
+
+impl Line {
+    /// Formats the line like its `Display` impl, with jump, call and load
+    /// targets replaced by their labels.
+    pub fn labeled(&self, labels: &Labels) -> String {
+        format!(
+            "{:#05X}: {:04X}  {}",
+            self.address,
+            self.opcode,
+            labels.mnemonic(self.opcode)
+        )
+    }
+}
+
+/// An opcode pattern like `DXYN` or `8XY6`: four hex digits, with X, Y or N
+/// in place of any digit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    value: u16,
+    mask: u16,
+    text: String,
+}
+
+impl Pattern {
+    pub fn matches(&self, opcode: u16) -> bool {
+        opcode & self.mask == self.value
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Pattern, String> {
+        let invalid = || format!("Invalid opcode pattern `{}`", s);
+        if s.len() != 4 {
+            return Err(invalid());
+        }
+        let (mut value, mut mask) = (0, 0);
+        for c in s.chars() {
+            let (digit, digit_mask) = match c.to_ascii_uppercase() {
+                'X' | 'Y' | 'N' => (0, 0),
+                c => (c.to_digit(16).ok_or_else(invalid)? as u16, 0xF),
+            };
+            value = value << 4 | digit;
+            mask = mask << 4 | digit_mask;
+        }
+        Ok(Pattern {
+            value,
+            mask,
+            text: s.to_ascii_uppercase(),
+        })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Names of addresses, read from label files with one `<address> <name>`
+/// pair per line and `;` comments:
+///
+/// ```text
+/// 0x2F0 draw_player ; also clears the old position
+/// 0x3A8 sprites
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Labels {
+    names: BTreeMap<usize, String>,
+}
+
+impl Labels {
+    pub fn parse(text: &str) -> Result<Labels, String> {
+        let mut labels = Labels::default();
+        for (i, line) in text.lines().enumerate() {
+            let code = line.split(';').next().unwrap_or_default();
+            let words: Vec<&str> = code.split_whitespace().collect();
+            let error = || format!("line {}: expected `<address> <name>`", i + 1);
+            match words.as_slice() {
+                [] => {}
+                [address, name] => {
+                    let address = match address
+                        .strip_prefix("0x")
+                        .or_else(|| address.strip_prefix("0X"))
+                    {
+                        Some(hex) => usize::from_str_radix(hex, 16),
+                        None => address.parse(),
+                    };
+                    labels.insert(address.map_err(|_| error())?, name.to_string());
+                }
+                _ => return Err(error()),
+            }
+        }
+        Ok(labels)
+    }
+
+    /// Names the subroutines called from the code reachable in `rom`, loaded
+    /// at `load_address`, as `sub_0x2F0`.
+    pub fn infer(rom: &[u8], load_address: usize) -> Labels {
+        let (code, _) = analyze::trace(rom, load_address);
+        let mut labels = Labels::default();
+        for opcode in code.into_values() {
+            if let Some(Instruction::Call(target)) = decode(opcode) {
+                let target = target as usize;
+                labels.insert(target, subroutine_name(target));
+            }
+        }
+        labels
+    }
+
+    pub fn insert(&mut self, address: usize, name: String) {
+        self.names.insert(address, name);
+    }
+
+    /// Adds the labels of `other`, replacing those at the same addresses.
+    pub fn extend(&mut self, other: Labels) {
+        self.names.extend(other.names);
+    }
+
+    pub fn get(&self, address: usize) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    /// Returns the mnemonic for `op` like [`mnemonic`], with its target
+    /// address replaced by its label if it has one.
+    pub fn mnemonic(&self, op: u16) -> String {
+        let mnemonic = mnemonic(op);
+        let target = match decode(op) {
+            Some(
+                Instruction::Jump(nnn)
+                | Instruction::Call(nnn)
+                | Instruction::LoadI(nnn)
+                | Instruction::JumpOffset { nnn, .. },
+            ) => nnn as usize,
+            _ => return mnemonic,
+        };
+        match self.get(target) {
+            Some(name) => mnemonic.replace(&format!("{:#05X}", target), name),
+            None => mnemonic,
+        }
+    }
+}
+
+/// Name given to the subroutine at `address` when it has no label.
+pub fn subroutine_name(address: usize) -> String {
+    format!("sub_{:#05X}", address)
+}
+
+/// Decodes `bytes`, loaded at address `origin`, two bytes per instruction.
+/// A trailing odd byte is padded with zero, as it would be read from memory.
+pub fn disassemble(bytes: &[u8], origin: usize) -> impl Iterator<Item = Line> + '_ {
+    bytes.chunks(2).enumerate().map(move |(i, chunk)| Line {
+        address: origin + 2 * i,
+        opcode: (chunk[0] as u16) << 8 | *chunk.get(1).unwrap_or(&0) as u16,
+    })
+}
+
+/// Returns the mnemonic for `op`, or a `DW` data directive when it isn't a
+/// known instruction.
+pub fn mnemonic(op: u16) -> String {
+    match decode(op) {
+        Some(instruction) => instruction.to_string(),
+        None => format!("DW {:#06X}", op),
+    }
+}
@@ -0,0 +1,1102 @@
+use core::ops::{Deref, Range};
+
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+
+use crate::error::Chip8Error;
+use crate::instruction::{decode, Instruction};
+use crate::memory::Memory;
+#[cfg(feature = "std")]
+use crate::palette::{Palette, Rgb};
+use crate::quirks::Quirks;
+use crate::timing::{self, Timing, VIP_FRAME_CYCLES, VIP_MIN_CYCLES};
+
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+
+/// Height of the display in hires mode, the COSMAC VIP's two-page variant
+/// of the interpreter. The display buffer always has this many rows, the
+/// ones past [`Emulator::height`] staying unset.
+pub const HIRES_HEIGHT: usize = 64;
+
+/// First instruction of hires ROMs, a jump over the interpreter changes
+/// they carry.
+const HIRES_SIGNATURE: [u8; 2] = [0x12, 0x60];
+/// Address hires ROMs start executing from, where the interpreter changes
+/// would hand over to the program.
+pub(crate) const HIRES_ENTRY: usize = 0x2C0;
+/// Opcode which clears the display in hires mode.
+pub(crate) const HIRES_CLS: u16 = 0x0230;
+
+/// Returns whether `rom` runs in hires mode when loaded at `address`.
+pub(crate) fn is_hires(rom: &[u8], address: usize) -> bool {
+    address == PROGRAM_ADDRESS && rom.starts_with(&HIRES_SIGNATURE)
+}
+
+/// Rate at which the timers tick and the display is redrawn.
+pub const FRAME_RATE: u32 = 60;
+
+/// Default speed in instructions per second.
+pub const DEFAULT_SPEED: u32 = 700;
+
+/// Address ROMs are loaded at and start executing from, unless loaded with
+/// [`Emulator::load_rom_at`].
+pub const PROGRAM_ADDRESS: usize = 0x200;
+
+/// Address the hexadecimal digit sprites used by FX29 are stored at.
+pub const FONT_ADDRESS: usize = 0x050;
+
+/// Size of the font, 5 bytes for each of the 16 hexadecimal digits.
+pub const FONT_SIZE: usize = 80;
+
+/// Address the SUPER-CHIP's large digit sprites used by FX30 are stored at,
+/// right after the small ones.
+pub const BIG_FONT_ADDRESS: usize = FONT_ADDRESS + FONT_SIZE;
+
+/// Bytes of memory, including the interpreter's below 0x200.
+pub const MEMORY_SIZE: usize = 4096;
+
+/// Subroutine calls which can be nested, at most and by default.
+pub const STACK_SIZE: usize = 16;
+
+/// Start of saved states, identifying the format and its version.
+const STATE_MAGIC: &[u8; 4] = b"C8S1";
+/// Size of a state saved by [`Emulator::save_state`]: the magic, memory,
+/// display, PC and I, the registers, the stack with its length, the timers
+/// and the key FX0A waits to be released.
+pub const STATE_SIZE: usize =
+    STATE_MAGIC.len() + MEMORY_SIZE + WIDTH * HIRES_HEIGHT / 8 + 8 + 16 + 1 + STACK_SIZE * 4 + 3;
+
+/// Memory accessed by an instruction, other than fetching the instruction
+/// itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read(Range<usize>),
+    Write(Range<usize>),
+}
+
+impl Access {
+    pub fn range(&self) -> &Range<usize> {
+        match self {
+            Access::Read(range) | Access::Write(range) => range,
+        }
+    }
+}
+
+const FONT: [u8; FONT_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/// Sprites 8 pixels wide and 10 high, with SUPER-CHIP's digits and Octo's
+/// letters.
+const BIG_FONT: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/// Outcome of [`Emulator::run_frame`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameResult {
+    pub display_changed: bool,
+    /// The sound timer is still running, so the buzzer should sound until
+    /// the next frame.
+    pub sound: bool,
+}
+
+/// Return addresses of the subroutines being executed, innermost last.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stack {
+    addresses: [usize; STACK_SIZE],
+    len: usize,
+}
+
+impl Stack {
+    /// Pushes `address`, returning false when the stack is full.
+    pub fn push(&mut self, address: usize) -> bool {
+        let Some(slot) = self.addresses.get_mut(self.len) else {
+            return false;
+        };
+        *slot = address;
+        self.len += 1;
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<usize> {
+        self.len = self.len.checked_sub(1)?;
+        Some(self.addresses[self.len])
+    }
+}
+
+impl Deref for Stack {
+    type Target = [usize];
+
+    fn deref(&self) -> &[usize] {
+        &self.addresses[..self.len]
+    }
+}
+
+/// A CHIP-8 machine: memory, registers, timers, keypad and display.
+///
+/// CXNN takes its random numbers from `R`, any [`RngCore`], so targets
+/// without a `StdRng` can use their own generator with
+/// [`Emulator::with_rng`].
+///
+/// An emulator takes about 37 KB, 24 KB of it for the decoded instruction
+/// cache, so box it on small stacks and copy it with [`Emulator::snapshot`]
+/// rather than `clone` where the cache isn't needed.
+#[derive(Clone)]
+pub struct Emulator<R = StdRng> {
+    pub memory: Memory,
+    /// Memory as the font and ROM were loaded into it, which
+    /// [`Emulator::reset`] restores since programs modify themselves.
+    loaded: Memory,
+    pub pc: usize,
+    pub display: [[bool; WIDTH]; HIRES_HEIGHT],
+    /// Incremented whenever the display changes.
+    generation: u64,
+    pub index_register: usize,
+    pub var_registers: [u8; 16],
+    pub stack: Stack,
+    /// Deepest the calls may nest, at most [`STACK_SIZE`].
+    stack_limit: usize,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub keys: [bool; 16],
+    /// Keys pressed with [`Emulator::set_key`] count as pressed for EX9E and
+    /// EXA1 until one of them checks the key, even if released before, so
+    /// that short taps aren't missed between checks.
+    pub latch_keys: bool,
+    /// Keys pressed since EX9E or EXA1 last checked them, with `latch_keys`.
+    latched: [bool; 16],
+    /// RPL user flags saved and loaded by FX75 and FX85. They live outside
+    /// the machine on the HP-48, so they survive resets.
+    pub flags: [u8; 16],
+    /// Address the ROM was loaded at.
+    load_address: usize,
+    /// Set when the ROM was loaded in hires mode.
+    hires: bool,
+    speed: u32,
+    /// Instructions carried over to the next frame when the speed isn't a
+    /// multiple of the frame rate, in 1/FRAME_RATE units.
+    cycle_remainder: u32,
+    timing: Timing,
+    /// Machine cycles left in the frame with [`Timing::Vip`], below zero
+    /// when the last instruction overran it.
+    vip_cycles: i32,
+    pub quirks: Quirks,
+    /// Set by a draw with the display wait quirk, until the next frame.
+    vblank_wait: bool,
+    /// Set while FX0A waits for a key, until the next frame.
+    key_wait: bool,
+    /// Key pressed while FX0A waits, registered once it's released.
+    pressed_key: Option<u8>,
+    /// Seed the random number generator was last seeded with, so that runs
+    /// can be reproduced.
+    seed: u64,
+    rng: R,
+    /// Instructions by address along with the opcode they were decoded
+    /// from, which is compared with memory before each use so that
+    /// self-modifying code, or any other write to `memory`, is picked up.
+    /// One entry per byte of memory, since code can start at odd addresses.
+    decoded: [Option<(u16, Instruction)>; MEMORY_SIZE],
+}
+
+/// Machine state captured at a frame boundary, with the display packed
+/// into bits to keep rewind history compact.
+#[derive(Clone)]
+pub struct Snapshot<R = StdRng> {
+    memory: Memory,
+    pc: usize,
+    display: [u8; WIDTH * HIRES_HEIGHT / 8],
+    index_register: usize,
+    var_registers: [u8; 16],
+    stack: Stack,
+    delay_timer: u8,
+    sound_timer: u8,
+    cycle_remainder: u32,
+    vip_cycles: i32,
+    pressed_key: Option<u8>,
+    rng: R,
+}
+
+/// Memory of a new machine, holding only the fonts.
+fn initial_memory() -> Memory {
+    let mut memory = Memory::new();
+    memory[FONT_ADDRESS..FONT_ADDRESS + FONT_SIZE].copy_from_slice(&FONT);
+    memory[BIG_FONT_ADDRESS..BIG_FONT_ADDRESS + BIG_FONT.len()].copy_from_slice(&BIG_FONT);
+    memory
+}
+
+#[cfg(feature = "std")]
+impl Default for Emulator {
+    fn default() -> Emulator {
+        Emulator::new()
+    }
+}
+
+/// Any machine state the public API can reach: arbitrary memory, registers,
+/// timers, keys, stack and quirks, with PC and I anywhere in memory and
+/// beyond, for fuzzing.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Emulator {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Emulator> {
+        let mut emulator = Emulator::with_seed(u.arbitrary()?);
+        emulator.quirks = u.arbitrary()?;
+        emulator.latch_keys = u.arbitrary()?;
+        emulator.hires = u.arbitrary()?;
+        emulator.set_speed(u.int_in_range(1..=100_000)?);
+        emulator.set_timing(u.arbitrary()?);
+        emulator.set_stack_limit(u.int_in_range(1..=STACK_SIZE)?);
+        u.fill_buffer(&mut emulator.memory[..])?;
+        emulator.pc = u.int_in_range(0..=MEMORY_SIZE + 2)?;
+        emulator.index_register = u.arbitrary::<u16>()? as usize;
+        emulator.var_registers = u.arbitrary()?;
+        emulator.delay_timer = u.arbitrary()?;
+        emulator.sound_timer = u.arbitrary()?;
+        emulator.keys = u.arbitrary()?;
+        emulator.flags = u.arbitrary()?;
+        for _ in 0..u.int_in_range(0..=STACK_SIZE)? {
+            emulator.stack.push(u.int_in_range(0..=MEMORY_SIZE)?);
+        }
+        for row in &mut emulator.display {
+            *row = u.arbitrary()?;
+        }
+        Ok(emulator)
+    }
+}
+
+impl Emulator {
+    /// Creates an emulator with a randomly seeded random number generator.
+    #[cfg(feature = "std")]
+    pub fn new() -> Emulator {
+        Emulator::with_seed(rand::random())
+    }
+
+    /// Creates an emulator whose CXNN results are determined by `seed`.
+    pub fn with_seed(seed: u64) -> Emulator {
+        let mut emulator = Emulator::with_rng(StdRng::seed_from_u64(seed));
+        emulator.seed = seed;
+        emulator
+    }
+
+    /// Restarts the random number generator from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+impl<R: RngCore> Emulator<R> {
+    /// Creates an emulator which takes the CXNN results from `rng`.
+    pub fn with_rng(rng: R) -> Emulator<R> {
+        let memory = initial_memory();
+        Emulator {
+            memory,
+            loaded: memory,
+            pc: PROGRAM_ADDRESS,
+            display: [[false; WIDTH]; HIRES_HEIGHT],
+            generation: 0,
+            index_register: 0,
+            var_registers: [0; 16],
+            stack: Stack::default(),
+            stack_limit: STACK_SIZE,
+            delay_timer: 0,
+            sound_timer: 0,
+            keys: [false; 16],
+            latch_keys: false,
+            latched: [false; 16],
+            flags: [0; 16],
+            load_address: PROGRAM_ADDRESS,
+            hires: false,
+            speed: DEFAULT_SPEED,
+            cycle_remainder: 0,
+            timing: Timing::Fixed,
+            vip_cycles: VIP_FRAME_CYCLES,
+            quirks: Quirks::default(),
+            vblank_wait: false,
+            key_wait: false,
+            pressed_key: None,
+            seed: 0,
+            rng,
+            decoded: [None; MEMORY_SIZE],
+        }
+    }
+
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<&mut Self, Chip8Error> {
+        self.load_rom_at(rom, PROGRAM_ADDRESS)
+    }
+
+    /// Loads `rom` at `address` and starts executing from there, for
+    /// interpreters like the ETI-660's which load programs at 0x600. ROMs
+    /// at 0x200 starting with a jump to 0x260 are run in hires mode, with
+    /// a 64x64 display, from 0x2C0.
+    pub fn load_rom_at(&mut self, rom: &[u8], address: usize) -> Result<&mut Self, Chip8Error> {
+        if address >= self.memory.len() {
+            return Err(Chip8Error::MemoryOutOfBounds { address });
+        }
+        let max = self.memory.len() - address;
+        if rom.len() > max {
+            return Err(Chip8Error::RomTooLarge {
+                size: rom.len(),
+                max,
+            });
+        }
+        self.memory[address..address + rom.len()].copy_from_slice(rom);
+        self.loaded[address..address + rom.len()].copy_from_slice(rom);
+        self.hires = is_hires(rom, address);
+        self.pc = if self.hires { HIRES_ENTRY } else { address };
+        self.load_address = address;
+        Ok(self)
+    }
+
+    /// Returns the number of rows of the display in use, [`HEIGHT`] or
+    /// [`HIRES_HEIGHT`] in hires mode.
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_HEIGHT
+        } else {
+            HEIGHT
+        }
+    }
+
+    /// Returns the address the ROM was loaded at.
+    pub fn load_address(&self) -> usize {
+        self.load_address
+    }
+
+    /// Replaces the hexadecimal digit sprites with `font`, which has to be
+    /// [`FONT_SIZE`] bytes.
+    pub fn load_font(&mut self, font: &[u8]) -> Result<&mut Self, Chip8Error> {
+        if font.len() != FONT_SIZE {
+            return Err(Chip8Error::InvalidFont { size: font.len() });
+        }
+        self.memory[FONT_ADDRESS..FONT_ADDRESS + FONT_SIZE].copy_from_slice(font);
+        self.loaded[FONT_ADDRESS..FONT_ADDRESS + FONT_SIZE].copy_from_slice(font);
+        Ok(self)
+    }
+
+    /// Restarts the loaded ROM: memory goes back to how the font and ROM
+    /// were loaded into it, and the registers, stack, timers, keys and
+    /// display are cleared. The speed, stack limit, quirks, key latching,
+    /// memory protection and RPL flags are kept, and the random number
+    /// generator carries on.
+    pub fn reset(&mut self) {
+        self.memory.copy_from_slice(&self.loaded);
+        self.pc = if self.hires {
+            HIRES_ENTRY
+        } else {
+            self.load_address
+        };
+        self.display = [[false; WIDTH]; HIRES_HEIGHT];
+        self.generation += 1;
+        self.index_register = 0;
+        self.var_registers = [0; 16];
+        self.stack = Stack::default();
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.keys = [false; 16];
+        self.latched = [false; 16];
+        self.cycle_remainder = 0;
+        self.vip_cycles = VIP_FRAME_CYCLES;
+        self.vblank_wait = false;
+        self.key_wait = false;
+        self.pressed_key = None;
+    }
+
+    /// Resets the machine to how [`Emulator::with_rng`] creates it, with no
+    /// ROM loaded, the built-in font and no RPL flags, keeping only the
+    /// settings [`Emulator::reset`] keeps.
+    pub fn hard_reset(&mut self) {
+        let protected = self.memory.protected();
+        self.memory = initial_memory();
+        self.memory.protect_below(protected);
+        self.loaded = initial_memory();
+        self.load_address = PROGRAM_ADDRESS;
+        self.hires = false;
+        self.flags = [0; 16];
+        self.reset();
+    }
+
+    /// Returns the hexadecimal digit sprites.
+    pub fn font(&self) -> &[u8] {
+        &self.memory[FONT_ADDRESS..FONT_ADDRESS + FONT_SIZE]
+    }
+
+    /// Executes the instruction at PC. Returns true when display has
+    /// changed, false otherwise.
+    pub fn step(&mut self) -> Result<bool, Chip8Error> {
+        let address = self.pc;
+        let opcode = self.current_opcode()?;
+        let instruction = match self.decoded[address] {
+            Some((decoded, instruction)) if decoded == opcode => instruction,
+            _ => {
+                let instruction =
+                    decode(opcode).ok_or(Chip8Error::UnknownOpcode { opcode, address })?;
+                self.decoded[address] = Some((opcode, instruction));
+                instruction
+            }
+        };
+        self.pc += 2;
+        if self.timing == Timing::Vip {
+            self.vip_cycles -= timing::vip_cycles(instruction);
+        }
+        let changed = self.execute(instruction)?;
+        self.generation += changed as u64;
+        Ok(changed)
+    }
+
+    /// Returns the instruction at PC.
+    pub fn current_opcode(&self) -> Result<u16, Chip8Error> {
+        let bytes = self.memory.read(self.pc, 2)?;
+        Ok((bytes[0] as u16) << 8 | bytes[1] as u16)
+    }
+
+    /// Returns the memory the instruction at PC will read or write.
+    pub fn memory_access(&self) -> Option<Access> {
+        let i = self.index_register;
+        match decode(self.current_opcode().ok()?)? {
+            Instruction::Bcd { .. } => Some(Access::Write(i..i + 3)),
+            Instruction::Store { x } => Some(Access::Write(i..i + x as usize + 1)),
+            Instruction::Restore { x } => Some(Access::Read(i..i + x as usize + 1)),
+            Instruction::Draw { n, .. } => Some(Access::Read(i..i + n as usize)),
+            _ => None,
+        }
+    }
+
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        self.keys[key as usize] = pressed;
+        self.latched[key as usize] |= pressed && self.latch_keys;
+    }
+
+    /// Returns whether `key` is held or was latched since the last check,
+    /// clearing the latch.
+    fn check_key(&mut self, key: usize) -> bool {
+        core::mem::take(&mut self.latched[key]) || self.keys[key]
+    }
+
+    /// Replaces the machine with `other`, keeping the speed, timing, stack
+    /// limit, quirks, key latching, memory protection and RPL flags.
+    pub fn replace(&mut self, mut other: Emulator<R>) {
+        other.speed = self.speed;
+        other.timing = self.timing;
+        other.stack_limit = self.stack_limit;
+        other.quirks = self.quirks;
+        other.latch_keys = self.latch_keys;
+        other.memory.protect_below(self.memory.protected());
+        other.flags = self.flags;
+        other.generation = self.generation + 1;
+        *self = other;
+    }
+
+    /// Returns true while execution is held until the next frame, by the
+    /// display wait quirk, by FX0A waiting for a key or by the frame's
+    /// machine cycles running out with [`Timing::Vip`].
+    pub fn is_waiting(&self) -> bool {
+        self.vblank_wait || self.key_wait || self.timing == Timing::Vip && self.vip_cycles <= 0
+    }
+
+    /// Returns the seed the random number generator was last seeded with,
+    /// or 0 when it was passed to [`Emulator::with_rng`].
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn speed(&self) -> u32 {
+        self.speed
+    }
+
+    /// Sets the speed in instructions per second.
+    pub fn set_speed(&mut self, instructions_per_second: u32) {
+        self.speed = instructions_per_second.max(1);
+    }
+
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Sets how the instructions run in a frame are counted, starting the
+    /// count of the current frame over.
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+        self.vip_cycles = VIP_FRAME_CYCLES;
+    }
+
+    pub fn stack_limit(&self) -> usize {
+        self.stack_limit
+    }
+
+    /// Sets how deep calls may nest before 2NNN stops with
+    /// [`Chip8Error::StackOverflow`], from 1 to [`STACK_SIZE`]. The COSMAC
+    /// VIP fit 12 return addresses, later interpreters 16.
+    pub fn set_stack_limit(&mut self, depth: usize) {
+        self.stack_limit = depth.clamp(1, STACK_SIZE);
+    }
+
+    /// Returns the number of instructions to execute in the next frame, so
+    /// that on average `speed` instructions run every second. With
+    /// [`Timing::Vip`] it's the most which may fit in the frame, and
+    /// [`Emulator::is_waiting`] tells when its machine cycles ran out.
+    pub fn frame_instructions(&mut self) -> u32 {
+        if self.timing == Timing::Vip {
+            return (self.vip_cycles.max(0) / VIP_MIN_CYCLES) as u32 + 1;
+        }
+        self.cycle_remainder += self.speed;
+        let count = self.cycle_remainder / FRAME_RATE;
+        self.cycle_remainder %= FRAME_RATE;
+        count
+    }
+
+    /// Runs the instructions of one frame at the emulator's speed with
+    /// `keys` held, then ticks the timers. This is all an embedder needs to
+    /// call every 1/[`FRAME_RATE`] seconds.
+    pub fn run_frame(&mut self, keys: &[bool; 16]) -> Result<FrameResult, Chip8Error> {
+        for (key, &pressed) in keys.iter().enumerate() {
+            self.set_key(key as u8, pressed);
+        }
+        let mut result = FrameResult::default();
+        for _ in 0..self.frame_instructions() {
+            if self.is_waiting() {
+                break;
+            }
+            result.display_changed |= self.step()?;
+        }
+        self.tick_timers();
+        result.sound = self.sound_timer > 0;
+        Ok(result)
+    }
+
+    /// Decrements the delay and sound timers, called at 60Hz.
+    pub fn tick_timers(&mut self) {
+        // An instruction overrunning the frame delays the next.
+        self.vip_cycles = self.vip_cycles.min(0) + VIP_FRAME_CYCLES;
+        self.vblank_wait = false;
+        self.key_wait = false;
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    /// Executes `instruction`, with PC already pointing past it.
+    fn execute(&mut self, instruction: Instruction) -> Result<bool, Chip8Error> {
+        let address = self.pc - 2;
+        let changed = match instruction {
+            Instruction::Cls => {
+                self.display = [[false; WIDTH]; HIRES_HEIGHT];
+                true
+            }
+            Instruction::Sys(HIRES_CLS) if self.hires => {
+                self.display = [[false; WIDTH]; HIRES_HEIGHT];
+                true
+            }
+            Instruction::Sys(nnn) => {
+                return Err(Chip8Error::UnknownOpcode {
+                    opcode: nnn,
+                    address,
+                })
+            }
+            Instruction::Ret => {
+                self.pc = self
+                    .stack
+                    .pop()
+                    .ok_or(Chip8Error::StackUnderflow { address })?;
+                false
+            }
+            Instruction::Jump(nnn) => {
+                self.pc = nnn as usize;
+                false
+            }
+            Instruction::Call(nnn) => {
+                if self.stack.len() >= self.stack_limit || !self.stack.push(self.pc) {
+                    return Err(Chip8Error::StackOverflow { address });
+                }
+                self.pc = nnn as usize;
+                false
+            }
+            Instruction::SkipEqByte { x, nn } => {
+                self.pc += if self.var_registers[x as usize] == nn {
+                    2
+                } else {
+                    0
+                };
+                false
+            }
+            Instruction::SkipNeByte { x, nn } => {
+                self.pc += if self.var_registers[x as usize] != nn {
+                    2
+                } else {
+                    0
+                };
+                false
+            }
+            Instruction::SkipEq { x, y } => {
+                self.pc += if self.var_registers[x as usize] == self.var_registers[y as usize] {
+                    2
+                } else {
+                    0
+                };
+                false
+            }
+            Instruction::LoadByte { x, nn } => {
+                self.var_registers[x as usize] = nn;
+                false
+            }
+            Instruction::AddByte { x, nn } => {
+                let x = x as usize;
+                self.var_registers[x] = self.var_registers[x].wrapping_add(nn);
+                false
+            }
+            Instruction::Load { x, y } => {
+                self.var_registers[x as usize] = self.var_registers[y as usize];
+                false
+            }
+            Instruction::Or { x, y } => {
+                self.var_registers[x as usize] |= self.var_registers[y as usize];
+                if self.quirks.vf_reset {
+                    self.var_registers[0xF] = 0;
+                }
+                false
+            }
+            Instruction::And { x, y } => {
+                self.var_registers[x as usize] &= self.var_registers[y as usize];
+                if self.quirks.vf_reset {
+                    self.var_registers[0xF] = 0;
+                }
+                false
+            }
+            Instruction::Xor { x, y } => {
+                self.var_registers[x as usize] ^= self.var_registers[y as usize];
+                if self.quirks.vf_reset {
+                    self.var_registers[0xF] = 0;
+                }
+                false
+            }
+            Instruction::Add { x, y } => {
+                let (x, y) = (x as usize, y as usize);
+                let (result, overflowing) =
+                    self.var_registers[x].overflowing_add(self.var_registers[y]);
+                self.var_registers[x] = result;
+                self.var_registers[0xF] = overflowing as u8;
+                false
+            }
+            Instruction::Sub { x, y } => {
+                let (x, y) = (x as usize, y as usize);
+                let (result, overflowing) =
+                    self.var_registers[x].overflowing_sub(self.var_registers[y]);
+                self.var_registers[x] = result;
+                self.var_registers[0xF] = !overflowing as u8;
+                false
+            }
+            Instruction::ShiftRight { x, y } => {
+                let (x, y) = (x as usize, y as usize);
+                let value = self.var_registers[if self.quirks.shift_vy { y } else { x }];
+                self.var_registers[x] = value >> 1;
+                self.var_registers[0xF] = value & 1;
+                false
+            }
+            Instruction::SubN { x, y } => {
+                let (x, y) = (x as usize, y as usize);
+                let (result, overflowing) =
+                    self.var_registers[y].overflowing_sub(self.var_registers[x]);
+                self.var_registers[x] = result;
+                self.var_registers[0xF] = if overflowing { 0 } else { 1 };
+                false
+            }
+            Instruction::ShiftLeft { x, y } => {
+                let (x, y) = (x as usize, y as usize);
+                let value = self.var_registers[if self.quirks.shift_vy { y } else { x }];
+                self.var_registers[x] = value << 1;
+                self.var_registers[0xF] = value >> 7;
+                false
+            }
+            Instruction::SkipNe { x, y } => {
+                self.pc += if self.var_registers[x as usize] != self.var_registers[y as usize] {
+                    2
+                } else {
+                    0
+                };
+                false
+            }
+            Instruction::LoadI(nnn) => {
+                self.index_register = nnn as usize;
+                false
+            }
+            Instruction::JumpOffset { x, nnn } => {
+                let register = if self.quirks.jump_vx { x as usize } else { 0 };
+                self.pc = nnn as usize + self.var_registers[register] as usize;
+                false
+            }
+            Instruction::Random { x, nn } => {
+                self.var_registers[x as usize] = self.rng.gen::<u8>() & nn;
+                false
+            }
+            Instruction::Draw { x, y, n } => {
+                self.vblank_wait = self.quirks.display_wait;
+                self.draw(x as usize, y as usize, n as usize)?
+            }
+            Instruction::SkipKey { x } => {
+                let key = self.var_registers[x as usize] as usize & 0xF;
+                self.pc += if self.check_key(key) { 2 } else { 0 };
+                false
+            }
+            Instruction::SkipNotKey { x } => {
+                let key = self.var_registers[x as usize] as usize & 0xF;
+                self.pc += if !self.check_key(key) { 2 } else { 0 };
+                false
+            }
+            Instruction::LoadDelay { x } => {
+                self.var_registers[x as usize] = self.delay_timer;
+                false
+            }
+            Instruction::WaitKey { x } => {
+                let x = x as usize;
+                match self.pressed_key {
+                    Some(key) if !self.keys[key as usize] => {
+                        self.var_registers[x] = key;
+                        self.pressed_key = None;
+                    }
+                    Some(_) => self.wait_for_key(),
+                    None => match self.keys.iter().position(|&pressed| pressed) {
+                        Some(key) if self.quirks.key_press => self.var_registers[x] = key as u8,
+                        Some(key) => {
+                            self.pressed_key = Some(key as u8);
+                            self.wait_for_key();
+                        }
+                        None => self.wait_for_key(),
+                    },
+                }
+                false
+            }
+            Instruction::SetDelay { x } => {
+                self.delay_timer = self.var_registers[x as usize];
+                false
+            }
+            Instruction::SetSound { x } => {
+                self.sound_timer = self.var_registers[x as usize];
+                false
+            }
+            Instruction::AddI { x } => {
+                self.index_register += self.var_registers[x as usize] as usize;
+                false
+            }
+            Instruction::Font { x } => {
+                let digit = self.var_registers[x as usize] as usize & 0xF;
+                self.index_register = FONT_ADDRESS + digit * 5;
+                false
+            }
+            Instruction::BigFont { x } => {
+                let digit = self.var_registers[x as usize] as usize & 0xF;
+                self.index_register = BIG_FONT_ADDRESS + digit * 10;
+                false
+            }
+            Instruction::Bcd { x } => {
+                let value = self.var_registers[x as usize];
+                let digits = [value / 100, value / 10 % 10, value % 10];
+                self.memory
+                    .write(self.index_register, 3)?
+                    .copy_from_slice(&digits);
+                false
+            }
+            Instruction::Store { x } => {
+                let x = x as usize;
+                let registers = self.var_registers;
+                self.memory
+                    .write(self.index_register, x + 1)?
+                    .copy_from_slice(&registers[..=x]);
+                if self.quirks.memory_increment {
+                    self.index_register += x + 1;
+                }
+                false
+            }
+            Instruction::Restore { x } => {
+                let x = x as usize;
+                let values = self.memory.read(self.index_register, x + 1)?;
+                self.var_registers[..=x].copy_from_slice(values);
+                if self.quirks.memory_increment {
+                    self.index_register += x + 1;
+                }
+                false
+            }
+            Instruction::SaveFlags { x } => {
+                let x = x as usize;
+                self.flags[..=x].copy_from_slice(&self.var_registers[..=x]);
+                false
+            }
+            Instruction::LoadFlags { x } => {
+                let x = x as usize;
+                self.var_registers[..=x].copy_from_slice(&self.flags[..=x]);
+                false
+            }
+        };
+        Ok(changed)
+    }
+
+    /// Executes the current FX0A again in the next frame, yielding until
+    /// then.
+    fn wait_for_key(&mut self) {
+        self.pc -= 2;
+        self.key_wait = true;
+    }
+
+    /// XORs the sprite at I onto the display, setting VF when it erases a
+    /// pixel.
+    fn draw(&mut self, x: usize, y: usize, height: usize) -> Result<bool, Chip8Error> {
+        let mut changed = false;
+        let rows = self.height();
+        let coord_x = (self.var_registers[x] % WIDTH as u8) as usize;
+        let coord_y = self.var_registers[y] as usize % rows;
+        let (height, width) = if self.quirks.wrap {
+            (height, 8)
+        } else {
+            (height.min(rows - coord_y), 8.min(WIDTH - coord_x))
+        };
+        let mut collision = false;
+        for i in 0..height {
+            let sprite = self.memory.read(self.index_register.saturating_add(i), 1)?[0];
+            let row = (coord_y + i) % rows;
+            for j in 0..width {
+                if 1 & (sprite >> (7 - j)) == 1 {
+                    let pixel = &mut self.display[row][(coord_x + j) % WIDTH];
+                    if *pixel {
+                        collision = true;
+                    }
+                    *pixel = !*pixel;
+                    changed = true;
+                }
+            }
+        }
+        self.var_registers[0xF] = collision as u8;
+
+        Ok(changed)
+    }
+}
+
+impl<R: RngCore + Clone> Emulator<R> {
+    pub fn snapshot(&self) -> Snapshot<R> {
+        Snapshot {
+            memory: self.memory,
+            pc: self.pc,
+            display: self.packed_display(),
+            index_register: self.index_register,
+            var_registers: self.var_registers,
+            stack: self.stack,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            cycle_remainder: self.cycle_remainder,
+            vip_cycles: self.vip_cycles,
+            pressed_key: self.pressed_key,
+            rng: self.rng.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot<R>) {
+        self.unpack_display(&snapshot.display);
+        self.memory = snapshot.memory;
+        self.pc = snapshot.pc;
+        self.index_register = snapshot.index_register;
+        self.var_registers = snapshot.var_registers;
+        self.stack = snapshot.stack;
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.cycle_remainder = snapshot.cycle_remainder;
+        self.vip_cycles = snapshot.vip_cycles;
+        self.pressed_key = snapshot.pressed_key;
+        self.rng.clone_from(&snapshot.rng);
+        self.generation += 1;
+    }
+}
+
+impl<R: RngCore> Emulator<R> {
+    /// Returns the display with 8 pixels to a byte, most significant bit
+    /// left.
+    fn packed_display(&self) -> [u8; WIDTH * HIRES_HEIGHT / 8] {
+        let mut display = [0; WIDTH * HIRES_HEIGHT / 8];
+        for (i, &pixel) in self.display.iter().flatten().enumerate() {
+            if pixel {
+                display[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+        display
+    }
+
+    fn unpack_display(&mut self, display: &[u8]) {
+        for (i, pixel) in self.display.iter_mut().flatten().enumerate() {
+            *pixel = display[i / 8] & (0x80 >> (i % 8)) != 0;
+        }
+    }
+
+    /// Saves the machine state to bytes which [`Emulator::load_state`] can
+    /// restore, such as after a restart. Unlike a [`Snapshot`] it leaves
+    /// out the random number generator, the speed and the quirks.
+    pub fn save_state(&self) -> [u8; STATE_SIZE] {
+        let mut state = [0; STATE_SIZE];
+        let mut len = 0;
+        let mut put = |bytes: &[u8]| {
+            state[len..len + bytes.len()].copy_from_slice(bytes);
+            len += bytes.len();
+        };
+        put(STATE_MAGIC);
+        put(&self.memory);
+        put(&self.packed_display());
+        put(&(self.pc as u32).to_be_bytes());
+        put(&(self.index_register as u32).to_be_bytes());
+        put(&self.var_registers);
+        put(&[self.stack.len() as u8]);
+        for i in 0..STACK_SIZE {
+            let address = self.stack.get(i).copied().unwrap_or(0);
+            put(&(address as u32).to_be_bytes());
+        }
+        put(&[
+            self.delay_timer,
+            self.sound_timer,
+            self.pressed_key.unwrap_or(0xFF),
+        ]);
+        state
+    }
+
+    /// Restores a state saved by [`Emulator::save_state`], keeping the
+    /// memory protection. Fails without changing anything when `state`
+    /// isn't one.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), Chip8Error> {
+        if state.len() != STATE_SIZE || !state.starts_with(STATE_MAGIC) {
+            return Err(Chip8Error::InvalidState);
+        }
+        let mut rest = &state[STATE_MAGIC.len()..];
+        let mut take = |len: usize| {
+            let (bytes, tail) = rest.split_at(len);
+            rest = tail;
+            bytes
+        };
+        let word = |bytes: &[u8]| u32::from_be_bytes(bytes.try_into().unwrap()) as usize;
+        let memory = take(MEMORY_SIZE);
+        let display = take(WIDTH * HIRES_HEIGHT / 8);
+        let pc = word(take(4));
+        let index_register = word(take(4));
+        let var_registers = take(16);
+        let stack_len = take(1)[0] as usize;
+        let stack_addresses = take(STACK_SIZE * 4);
+        let &[delay_timer, sound_timer, pressed_key] = take(3) else {
+            unreachable!();
+        };
+        if stack_len > STACK_SIZE || pressed_key > 0xF && pressed_key != 0xFF {
+            return Err(Chip8Error::InvalidState);
+        }
+
+        self.memory.copy_from_slice(memory);
+        self.unpack_display(display);
+        self.pc = pc;
+        self.index_register = index_register;
+        self.var_registers.copy_from_slice(var_registers);
+        self.stack = Stack::default();
+        for address in stack_addresses.chunks_exact(4).take(stack_len) {
+            self.stack.push(word(address));
+        }
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.pressed_key = Some(pressed_key).filter(|&key| key != 0xFF);
+        self.vblank_wait = false;
+        self.key_wait = false;
+        self.generation += 1;
+        Ok(())
+    }
+
+    /// Returns a counter which is incremented whenever the display changes,
+    /// so frontends can skip redrawing or encoding unchanged frames. Writes
+    /// to `display` made from outside the emulator aren't counted.
+    pub fn display_generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the rows of the display in use as RGBA bytes, row by row,
+    /// colored with `palette`.
+    #[cfg(feature = "std")]
+    pub fn framebuffer_rgba(&self, palette: &Palette) -> Vec<u8> {
+        let mut rgba = vec![0; WIDTH * self.height() * 4];
+        self.fill_framebuffer_rgba(palette, &mut rgba);
+        rgba
+    }
+
+    /// Writes the display into `rgba` like [`Emulator::framebuffer_rgba`],
+    /// to reuse a buffer across frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rgba` isn't `WIDTH * self.height() * 4` bytes long.
+    #[cfg(feature = "std")]
+    pub fn fill_framebuffer_rgba(&self, palette: &Palette, rgba: &mut [u8]) {
+        assert_eq!(rgba.len(), WIDTH * self.height() * 4, "framebuffer size");
+        for (out, &pixel) in rgba.chunks_exact_mut(4).zip(self.display.iter().flatten()) {
+            let Rgb(r, g, b) = palette.colors[pixel as usize];
+            out.copy_from_slice(&[r, g, b, 0xFF]);
+        }
+    }
+
+    /// Returns a 64-bit FNV-1a hash of the display, to compare the output of
+    /// a run against a known-good one. It only depends on the pixels, so it
+    /// stays the same across platforms and builds.
+    pub fn display_hash(&self) -> u64 {
+        self.display[..self.height()]
+            .iter()
+            .flatten()
+            .fold(0xCBF2_9CE4_8422_2325, |hash, &pixel| {
+                (hash ^ pixel as u64).wrapping_mul(0x0100_0000_01B3)
+            })
+    }
+
+    /// Formats the display as text, one line per row with `#` for set pixels.
+    #[cfg(feature = "std")]
+    pub fn display_string(&self) -> String {
+        let mut s = String::with_capacity((WIDTH + 1) * self.height());
+        for row in &self.display[..self.height()] {
+            s.extend(row.iter().map(|&pixel| if pixel { '#' } else { '.' }));
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Formats V0-VF, I, PC, SP and the timers.
+    #[cfg(feature = "std")]
+    pub fn registers_string(&self) -> String {
+        let mut s = String::new();
+        for (i, v) in self.var_registers.iter().enumerate() {
+            let separator = if i % 8 == 7 { "\n" } else { "  " };
+            s += &format!("V{:X}: {:02X}{}", i, v, separator);
+        }
+        s += &format!(
+            "PC: {:#05X}  I: {:#05X}  SP: {}  DT: {}  ST: {}\n",
+            self.pc,
+            self.index_register,
+            self.stack.len(),
+            self.delay_timer,
+            self.sound_timer,
+        );
+        s
+    }
+}
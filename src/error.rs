@@ -0,0 +1,71 @@
+use core::fmt;
+
+/// An error which stops the emulator, instead of panicking.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    UnknownOpcode {
+        opcode: u16,
+        address: usize,
+    },
+    /// A return (`00EE`) with no subroutine to return from.
+    StackUnderflow {
+        address: usize,
+    },
+    /// A call (`2NNN`) nested deeper than the stack fits.
+    StackOverflow {
+        address: usize,
+    },
+    /// The ROM doesn't fit in memory from its load address on.
+    RomTooLarge {
+        size: usize,
+        max: usize,
+    },
+    MemoryOutOfBounds {
+        address: usize,
+    },
+    /// A write below the address memory is protected below.
+    WriteProtected {
+        address: usize,
+    },
+    /// A font which isn't 5 bytes for each of the 16 digits.
+    InvalidFont {
+        size: usize,
+    },
+    /// Bytes which aren't a state saved by this version.
+    InvalidState,
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Chip8Error::UnknownOpcode { opcode, address } => {
+                write!(f, "unknown opcode {:04X} at {:#05X}", opcode, address)
+            }
+            Chip8Error::StackUnderflow { address } => {
+                write!(f, "return with an empty stack at {:#05X}", address)
+            }
+            Chip8Error::StackOverflow { address } => {
+                write!(f, "call with a full stack at {:#05X}", address)
+            }
+            Chip8Error::RomTooLarge { size, max } => {
+                write!(
+                    f,
+                    "ROM is {} bytes, but at most {} bytes fit in memory",
+                    size, max
+                )
+            }
+            Chip8Error::MemoryOutOfBounds { address } => {
+                write!(f, "memory access out of bounds at {:#05X}", address)
+            }
+            Chip8Error::WriteProtected { address } => {
+                write!(f, "write to protected memory at {:#05X}", address)
+            }
+            Chip8Error::InvalidFont { size } => {
+                write!(f, "font is {} bytes instead of 16 digits of 5 bytes", size)
+            }
+            Chip8Error::InvalidState => write!(f, "not a saved state of this version"),
+        }
+    }
+}
+
+impl core::error::Error for Chip8Error {}
@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use chip8::{decode, Chip8Error, Emulator, Instruction};
+use serde::Serialize;
+
+/// Something that happened in the emulator, logged as a JSON object whose
+/// `event` field names the variant.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum Event {
+    Instruction {
+        pc: usize,
+        opcode: u16,
+        text: String,
+    },
+    /// DXYN with the coordinates read from VX and VY.
+    Draw {
+        x: u8,
+        y: u8,
+        height: u8,
+        collision: bool,
+    },
+    Key {
+        key: u8,
+        pressed: bool,
+    },
+    Tick {
+        delay_timer: u8,
+        sound_timer: u8,
+    },
+    Save {
+        slot: u8,
+    },
+    Load {
+        slot: u8,
+    },
+}
+
+#[derive(Serialize)]
+struct Line {
+    /// Emulated frames before the event.
+    frame: u64,
+    #[serde(flatten)]
+    event: Event,
+}
+
+/// Logs events for other programs to read, one JSON object per line:
+///
+/// ```text
+/// {"frame":0,"event":"instruction","pc":512,"opcode":41514,"text":"LD I, 0x22A"}
+/// {"frame":0,"event":"draw","x":12,"y":8,"height":5,"collision":false}
+/// {"frame":0,"event":"tick","delay_timer":0,"sound_timer":0}
+/// {"frame":1,"event":"key","key":5,"pressed":true}
+/// ```
+pub struct EventLog {
+    out: BufWriter<File>,
+    frame: u64,
+}
+
+impl EventLog {
+    /// Creates a log writing to the file or named pipe at `path`.
+    pub fn new(path: &Path) -> io::Result<EventLog> {
+        Ok(EventLog {
+            out: BufWriter::new(File::create(path)?),
+            frame: 0,
+        })
+    }
+
+    fn log(&mut self, event: Event) {
+        let line = Line {
+            frame: self.frame,
+            event,
+        };
+        // A failing log shouldn't stop the emulator, so errors are ignored.
+        let _ = serde_json::to_writer(&mut self.out, &line);
+        let _ = self.out.write_all(b"\n");
+    }
+
+    /// Executes the instruction at PC with `step` and logs it, and the
+    /// sprite it drew if any.
+    pub fn step(
+        &mut self,
+        emulator: &mut Emulator,
+        step: impl FnOnce(&mut Emulator) -> Result<bool, Chip8Error>,
+    ) -> Result<bool, Chip8Error> {
+        let Ok(opcode) = emulator.current_opcode() else {
+            return step(emulator);
+        };
+        let pc = emulator.pc;
+        let instruction = decode(opcode);
+        let registers = emulator.var_registers;
+        let result = step(emulator)?;
+        let text = instruction.map_or_else(|| format!("DW {:#06X}", opcode), |i| i.to_string());
+        self.log(Event::Instruction { pc, opcode, text });
+        if let Some(Instruction::Draw { x, y, n }) = instruction {
+            self.log(Event::Draw {
+                x: registers[x as usize],
+                y: registers[y as usize],
+                height: n,
+                collision: emulator.var_registers[0xF] == 1,
+            });
+        }
+        Ok(result)
+    }
+
+    pub fn key(&mut self, key: u8, pressed: bool) {
+        self.log(Event::Key { key, pressed });
+    }
+
+    /// Logs the timers after they ticked at the end of a frame, and flushes
+    /// the frame's events to readers.
+    pub fn tick(&mut self, emulator: &Emulator) {
+        self.log(Event::Tick {
+            delay_timer: emulator.delay_timer,
+            sound_timer: emulator.sound_timer,
+        });
+        self.frame += 1;
+        let _ = self.out.flush();
+    }
+
+    pub fn state(&mut self, slot: u8, saved: bool) {
+        self.log(if saved {
+            Event::Save { slot }
+        } else {
+            Event::Load { slot }
+        });
+    }
+}
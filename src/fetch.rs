@@ -0,0 +1,47 @@
+use std::io::Read;
+use std::path::PathBuf;
+
+use sha1::{Digest, Sha1};
+
+/// Largest file downloaded, which leaves room for packs of ROMs.
+const MAX_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Downloads the ROM at `url` into the user's cache directory, unless an
+/// earlier download is cached there, and returns its path. The file keeps
+/// its name from the URL, so archives are still recognized.
+pub fn fetch(url: &str) -> Result<PathBuf, String> {
+    let dir = dirs::cache_dir()
+        .ok_or("no cache directory")?
+        .join("chip8")
+        .join("roms")
+        .join(format!("{:x}", Sha1::digest(url)));
+    let name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|url| url.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("rom.ch8");
+    let path = dir.join(name);
+    if path.is_file() {
+        return Ok(path);
+    }
+    println!("Downloading {}", url);
+    let response = ureq::get(url).call().map_err(|e| e.to_string())?;
+    let mut rom = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_SIZE + 1)
+        .read_to_end(&mut rom)
+        .map_err(|e| e.to_string())?;
+    if rom.len() as u64 > MAX_SIZE {
+        return Err(format!("larger than {} MiB", MAX_SIZE / 1024 / 1024));
+    }
+    // Written under another name first, so that an interrupted download
+    // isn't taken for a cached one.
+    let partial = dir.join(format!("{}.part", name));
+    std::fs::create_dir_all(&dir)
+        .and_then(|()| std::fs::write(&partial, &rom))
+        .and_then(|()| std::fs::rename(&partial, &path))
+        .map_err(|e| format!("failed to cache it: {}", e))?;
+    Ok(path)
+}
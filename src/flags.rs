@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use chip8::Emulator;
+use sha1::{Digest, Sha1};
+
+/// RPL user flags of the running ROM, kept in the user's data directory
+/// under the ROM's SHA-1 hash so that settings and high scores games store
+/// with FX75 persist across runs.
+pub struct Flags {
+    path: Option<PathBuf>,
+    saved: [u8; 16],
+}
+
+impl Flags {
+    /// Loads the flags saved for `rom` into the emulator.
+    pub fn load(emulator: &mut Emulator, rom: &[u8]) -> Flags {
+        let path = dirs::data_dir().map(|dir| {
+            let hash = format!("{:x}", Sha1::digest(rom));
+            dir.join("chip8").join("flags").join(hash)
+        });
+        let mut saved = [0; 16];
+        if let Some(data) = path.as_ref().and_then(|path| std::fs::read(path).ok()) {
+            let len = data.len().min(saved.len());
+            saved[..len].copy_from_slice(&data[..len]);
+        }
+        emulator.flags = saved;
+        Flags { path, saved }
+    }
+
+    /// Writes the emulator's flags when they changed since they were last
+    /// loaded or saved.
+    pub fn save(&mut self, emulator: &Emulator) {
+        if emulator.flags == self.saved {
+            return;
+        }
+        self.saved = emulator.flags;
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = std::fs::create_dir_all(path.parent().unwrap())
+            .and_then(|()| std::fs::write(path, self.saved));
+        if let Err(e) = result {
+            eprintln!("Failed to save RPL flags to {}: {}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use sdl2::controller::GameController;
+use sdl2::{GameControllerSubsystem, Sdl};
+
+/// How long a rumble lasts unless renewed, which it is every frame while
+/// the sound timer is active.
+const RUMBLE_DURATION_MS: u32 = 100;
+
+/// Game controllers which are plugged in. SDL2 reports the ones plugged in
+/// at startup like ones plugged in later, so they're all opened through
+/// [`Gamepads::add`].
+pub struct Gamepads {
+    subsystem: GameControllerSubsystem,
+    controllers: Vec<GameController>,
+    /// Rumble motor speed, 0 when rumbling is off.
+    rumble: u16,
+    rumbling: bool,
+}
+
+impl Gamepads {
+    /// Starts listening for controllers, knowing the ones in the SDL2
+    /// `gamecontrollerdb.txt` at `mappings` on top of SDL's own, and
+    /// rumbling with strength `rumble` from 0 to 1.
+    pub fn new(
+        sdl_context: &Sdl,
+        mappings: Option<&Path>,
+        rumble: f32,
+    ) -> Result<Gamepads, String> {
+        let subsystem = sdl_context.game_controller()?;
+        if let Some(path) = mappings {
+            subsystem
+                .load_mappings(path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok(Gamepads {
+            subsystem,
+            controllers: Vec::new(),
+            rumble: (rumble.clamp(0.0, 1.0) * u16::MAX as f32) as u16,
+            rumbling: false,
+        })
+    }
+
+    /// Opens the controller with joystick index `index`.
+    pub fn add(&mut self, index: u32) {
+        match self.subsystem.open(index) {
+            Ok(controller) => {
+                println!("Connected {}", controller.name());
+                self.controllers.push(controller);
+            }
+            Err(e) => eprintln!("Failed to open game controller: {}", e),
+        }
+    }
+
+    /// Closes the controller with instance ID `id`.
+    pub fn remove(&mut self, id: u32) {
+        if let Some(i) = self.controllers.iter().position(|c| c.instance_id() == id) {
+            println!("Disconnected {}", self.controllers.remove(i).name());
+        }
+    }
+
+    /// Starts or keeps rumbling the controllers that can while `on`, and
+    /// stops them otherwise.
+    pub fn set_rumble(&mut self, on: bool) {
+        if self.rumble == 0 || !on && !self.rumbling {
+            return;
+        }
+        self.rumbling = on;
+        let strength = if on { self.rumble } else { 0 };
+        for controller in &mut self.controllers {
+            // Fails for controllers without rumble motors.
+            let _ = controller.set_rumble(strength, strength, RUMBLE_DURATION_MS);
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::BTreeSet;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use chip8::{Chip8Error, Emulator};
+
+use crate::debugger::Control;
+
+/// Stop reply reason for breakpoints and steps.
+const SIGTRAP: &str = "S05";
+/// Stop reply reason for a pause requested with Ctrl-C.
+const SIGINT: &str = "S02";
+/// Stop reply reason for an instruction which failed.
+const SIGILL: &str = "S04";
+
+/// A minimal GDB remote serial protocol server, accepting one client at a
+/// time. Execution is paused until a client connects and continues, and
+/// stays paused when it detaches until the next one continues.
+///
+/// GDB has no CHIP-8 architecture, so the register file is laid out as V0
+/// to VF, then I and PC as 16-bit little endian values, then SP, DT and ST,
+/// one byte each. Registers and memory can be read and written, and
+/// software breakpoints, stepping and continuing are supported.
+pub struct GdbServer {
+    listener: TcpListener,
+    client: Option<TcpStream>,
+    buffer: Vec<u8>,
+    breakpoints: BTreeSet<usize>,
+    paused: bool,
+    /// Set when resuming from a breakpoint, so it doesn't trigger again
+    /// before the instruction at PC has executed.
+    skip_breakpoint: bool,
+}
+
+impl GdbServer {
+    pub fn bind(port: u16) -> std::io::Result<GdbServer> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        println!("Waiting for a GDB connection on port {}", port);
+        Ok(GdbServer {
+            listener,
+            client: None,
+            buffer: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            paused: true,
+            skip_breakpoint: false,
+        })
+    }
+
+    /// Accepts a pending connection, when no client is connected.
+    fn accept(&mut self) {
+        let Ok((stream, addr)) = self.listener.accept() else {
+            return;
+        };
+        if stream.set_nonblocking(true).is_err() {
+            return;
+        }
+        println!("GDB connected from {}", addr);
+        self.client = Some(stream);
+        self.buffer.clear();
+        self.paused = true;
+    }
+
+    /// Reads whatever the client has sent, dropping it when it disconnects.
+    fn receive(&mut self) {
+        let Some(client) = &mut self.client else {
+            return;
+        };
+        let mut bytes = [0; 1024];
+        loop {
+            match client.read(&mut bytes) {
+                Ok(0) => break self.disconnect(),
+                Ok(len) => self.buffer.extend_from_slice(&bytes[..len]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => break self.disconnect(),
+            }
+        }
+    }
+
+    fn disconnect(&mut self) {
+        println!("GDB disconnected");
+        self.client = None;
+        self.buffer.clear();
+        self.breakpoints.clear();
+        self.paused = true;
+    }
+
+    /// Removes and returns the next complete packet from the buffer,
+    /// acknowledging it. Ctrl-C is returned as a packet of its own.
+    fn next_packet(&mut self) -> Option<String> {
+        loop {
+            match *self.buffer.first()? {
+                b'$' => break,
+                0x03 => {
+                    self.buffer.remove(0);
+                    return Some("\x03".to_string());
+                }
+                // Acknowledgements, and anything else outside a packet.
+                _ => {
+                    self.buffer.remove(0);
+                }
+            }
+        }
+        let end = self.buffer.iter().position(|&b| b == b'#')?;
+        if self.buffer.len() < end + 3 {
+            return None;
+        }
+        let packet: Vec<u8> = self.buffer.drain(..end + 3).collect();
+        let data = &packet[1..end];
+        let checksum = std::str::from_utf8(&packet[end + 1..])
+            .ok()
+            .and_then(|s| u8::from_str_radix(s, 16).ok());
+        if checksum != Some(checksum_of(data)) {
+            self.send_raw(b"-");
+            return None;
+        }
+        self.send_raw(b"+");
+        Some(String::from_utf8_lossy(data).into_owned())
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) {
+        if let Some(client) = &mut self.client {
+            if client.write_all(bytes).is_err() {
+                self.disconnect();
+            }
+        }
+    }
+
+    fn send(&mut self, data: &str) {
+        let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+        self.send_raw(packet.as_bytes());
+    }
+
+    /// Handles a packet, returning the reply to send, if any.
+    fn handle(&mut self, emulator: &mut Emulator, packet: &str) -> Option<String> {
+        let (command, args) = packet.split_at(packet.chars().next()?.len_utf8());
+        let reply = match command {
+            "\x03" => {
+                self.paused = true;
+                SIGINT.to_string()
+            }
+            "?" => SIGTRAP.to_string(),
+            "g" => hex(&registers(emulator)),
+            "G" => match parse_hex(args) {
+                Some(bytes) if bytes.len() == REGISTERS_LEN => {
+                    set_registers(emulator, &bytes);
+                    "OK".to_string()
+                }
+                _ => "E01".to_string(),
+            },
+            "m" => match parse_range(args, emulator) {
+                Some((addr, len)) => hex(&emulator.memory[addr..addr + len]),
+                None => "E01".to_string(),
+            },
+            "M" => {
+                let write = args.split_once(':').and_then(|(range, data)| {
+                    let (addr, len) = parse_range(range, emulator)?;
+                    let bytes = parse_hex(data).filter(|bytes| bytes.len() == len)?;
+                    emulator.memory[addr..addr + len].copy_from_slice(&bytes);
+                    Some(())
+                });
+                match write {
+                    Some(()) => "OK".to_string(),
+                    None => "E01".to_string(),
+                }
+            }
+            "c" => {
+                self.paused = false;
+                self.skip_breakpoint = true;
+                return None;
+            }
+            "s" => {
+                self.paused = true;
+                match emulator.step() {
+                    Ok(_) => SIGTRAP.to_string(),
+                    Err(_) => SIGILL.to_string(),
+                }
+            }
+            "Z" | "z" => match parse_breakpoint(args) {
+                Some(addr) => {
+                    if command == "Z" {
+                        self.breakpoints.insert(addr);
+                    } else {
+                        self.breakpoints.remove(&addr);
+                    }
+                    "OK".to_string()
+                }
+                None => String::new(),
+            },
+            "D" => {
+                self.send("OK");
+                self.disconnect();
+                return None;
+            }
+            "k" => {
+                self.disconnect();
+                return None;
+            }
+            // Only one thread, so selecting one is a no-op.
+            "H" => "OK".to_string(),
+            "q" if args.starts_with("Supported") => "PacketSize=4000".to_string(),
+            "q" if args == "Attached" => "1".to_string(),
+            // An empty reply tells GDB the packet isn't supported.
+            _ => String::new(),
+        };
+        Some(reply)
+    }
+}
+
+impl Control for GdbServer {
+    fn poll(&mut self, emulator: &mut Emulator) {
+        if self.client.is_none() {
+            self.accept();
+        }
+        self.receive();
+        while let Some(packet) = self.next_packet() {
+            if let Some(reply) = self.handle(emulator, &packet) {
+                self.send(&reply);
+            }
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn stop(&mut self, _emulator: &Emulator, error: &Chip8Error) {
+        self.paused = true;
+        println!("Stopped: {}", error);
+        self.send(SIGILL);
+    }
+
+    fn can_run(&mut self, emulator: &Emulator) -> bool {
+        if self.paused {
+            return false;
+        }
+        if std::mem::take(&mut self.skip_breakpoint) {
+            return true;
+        }
+        if self.breakpoints.contains(&emulator.pc) {
+            self.paused = true;
+            self.send(SIGTRAP);
+            return false;
+        }
+        true
+    }
+}
+
+/// Size of the register file sent for `g`.
+const REGISTERS_LEN: usize = 16 + 2 + 2 + 3;
+
+fn registers(emulator: &Emulator) -> Vec<u8> {
+    let mut bytes = emulator.var_registers.to_vec();
+    bytes.extend_from_slice(&(emulator.index_register as u16).to_le_bytes());
+    bytes.extend_from_slice(&(emulator.pc as u16).to_le_bytes());
+    bytes.push(emulator.stack.len() as u8);
+    bytes.push(emulator.delay_timer);
+    bytes.push(emulator.sound_timer);
+    bytes
+}
+
+/// Writes the registers sent with `G`. SP is read only, since the stack
+/// contents aren't part of the register file.
+fn set_registers(emulator: &mut Emulator, bytes: &[u8]) {
+    emulator.var_registers.copy_from_slice(&bytes[..16]);
+    emulator.index_register = u16::from_le_bytes([bytes[16], bytes[17]]) as usize;
+    emulator.pc = u16::from_le_bytes([bytes[18], bytes[19]]) as usize % emulator.memory.len();
+    emulator.delay_timer = bytes[21];
+    emulator.sound_timer = bytes[22];
+}
+
+/// Parses `addr,len`, checking that the range is within memory.
+fn parse_range(s: &str, emulator: &Emulator) -> Option<(usize, usize)> {
+    let (addr, len) = s.split_once(',')?;
+    let addr = usize::from_str_radix(addr, 16).ok()?;
+    let len = usize::from_str_radix(len, 16).ok()?;
+    (addr.checked_add(len)? <= emulator.memory.len()).then_some((addr, len))
+}
+
+/// Parses the address of a `Z0,addr,kind` software breakpoint packet.
+fn parse_breakpoint(s: &str) -> Option<usize> {
+    let mut fields = s.split(',');
+    if fields.next()? != "0" {
+        return None;
+    }
+    usize::from_str_radix(fields.next()?, 16).ok()
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &b| sum.wrapping_add(b))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use chip8::palette::Palette;
+use chip8::Emulator;
+
+use crate::config::UnknownOpcode;
+use crate::crash::CrashLog;
+use crate::events::EventLog;
+use crate::profile::Profiler;
+use crate::screenshot;
+use crate::script::Script;
+use crate::trace::Tracer;
+
+/// Runs the emulator without a window for at most `cycles` instructions,
+/// stopping early when it halts on a jump to itself or on an error. The
+/// timers tick as if running in real time at the emulator's speed, and a
+/// script error stops it like an emulator error, and an emulator error also
+/// writes a crash dump. Unknown opcodes are skipped with the skip policy,
+/// and otherwise stop it.
+pub fn run(
+    emulator: &mut Emulator,
+    cycles: u64,
+    mut tracer: Option<&mut Tracer>,
+    mut profiler: Option<&mut Profiler>,
+    mut script: Option<&mut Script>,
+    mut events: Option<&mut EventLog>,
+    unknown_opcode: UnknownOpcode,
+) {
+    let mut crash_log = CrashLog::new();
+    let mut executed = 0;
+    let mut frame = 0;
+    'frames: while executed < cycles {
+        for _ in 0..emulator.frame_instructions() {
+            if emulator.is_waiting() {
+                break;
+            }
+            if let Some(Err(e)) = script.as_mut().map(|s| s.on_instruction(emulator)) {
+                eprintln!("Script stopped after {} instructions: {}", executed, e);
+                break 'frames;
+            }
+            let pc = emulator.pc;
+            let result = crate::step(
+                emulator,
+                &mut crash_log,
+                tracer.as_deref_mut(),
+                profiler.as_deref_mut(),
+                events.as_deref_mut(),
+            );
+            match result {
+                Err(e) if crate::recover(emulator, &e, unknown_opcode) => {}
+                Err(e) => {
+                    let report = crash_log.report(emulator, &e);
+                    eprintln!("Stopped after {} instructions: {}", executed, report);
+                    break 'frames;
+                }
+                Ok(_) => {}
+            }
+            executed += 1;
+            if emulator.pc == pc && !emulator.is_waiting() {
+                eprintln!("Halted at {:#05X} after {} instructions", pc, executed);
+                break 'frames;
+            }
+            if executed == cycles {
+                break 'frames;
+            }
+        }
+        emulator.tick_timers();
+        if let Some(events) = events.as_deref_mut() {
+            events.tick(emulator);
+        }
+        frame += 1;
+        if let Some(Err(e)) = script.as_mut().map(|s| s.on_frame(emulator, frame)) {
+            eprintln!("Script stopped after {} instructions: {}", executed, e);
+            break;
+        }
+    }
+}
+
+/// Instructions and frames executed by [`bench`], and how long they took.
+pub struct Bench {
+    pub instructions: u64,
+    pub frames: u64,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for Bench {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self.elapsed.as_secs_f64();
+        writeln!(
+            f,
+            "{} instructions in {:.2} s: {:.2} million instructions per second",
+            self.instructions,
+            seconds,
+            self.instructions as f64 / seconds / 1e6
+        )?;
+        write!(
+            f,
+            "{} frames: {:.2} µs per frame",
+            self.frames,
+            seconds * 1e6 / self.frames.max(1) as f64
+        )
+    }
+}
+
+/// Emulates frame after frame without waiting in between for `duration`,
+/// as fast as the interpreter goes, stopping early on an error.
+pub fn bench(emulator: &mut Emulator, duration: Duration) -> Bench {
+    let start = Instant::now();
+    let mut bench = Bench {
+        instructions: 0,
+        frames: 0,
+        elapsed: Duration::ZERO,
+    };
+    'frames: while bench.elapsed < duration {
+        for _ in 0..emulator.frame_instructions() {
+            if emulator.is_waiting() {
+                break;
+            }
+            if let Err(e) = emulator.step() {
+                eprintln!("Stopped after {} instructions: {}", bench.instructions, e);
+                break 'frames;
+            }
+            bench.instructions += 1;
+        }
+        emulator.tick_timers();
+        bench.frames += 1;
+        bench.elapsed = start.elapsed();
+    }
+    bench.elapsed = start.elapsed();
+    bench
+}
+
+/// Writes the display and registers to `path`, or to stdout when it's
+/// `None`.
+pub fn dump(emulator: &Emulator, path: Option<&Path>, palette: &Palette) -> io::Result<()> {
+    match path {
+        Some(path) if path.extension().is_some_and(|ext| ext == "png") => {
+            screenshot::write_png(path, &emulator.framebuffer_rgba(palette), 1)?;
+            print!("{}", emulator.registers_string());
+        }
+        Some(path) => std::fs::write(
+            path,
+            emulator.display_string() + &emulator.registers_string(),
+        )?,
+        None => print!(
+            "{}{}",
+            emulator.display_string(),
+            emulator.registers_string()
+        ),
+    }
+    Ok(())
+}
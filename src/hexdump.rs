@@ -0,0 +1,43 @@
+use std::fmt::Write;
+
+/// Bytes shown per line.
+pub const LINE_BYTES: usize = 16;
+
+const HIGHLIGHT: &str = "\x1b[7m";
+const RESET: &str = "\x1b[0m";
+
+/// Formats `bytes`, loaded at address `origin`, as hex and ASCII, 16 bytes
+/// per line:
+///
+/// ```text
+/// 0x200: 00 E0 A2 2A 60 0C 61 08  D0 1F 70 09 A2 39 D0 1F  ...*`.a...p..9..
+/// ```
+///
+/// The bytes at the addresses in `highlight` are shown in reverse video.
+pub fn hexdump(bytes: &[u8], origin: usize, highlight: &[usize]) -> String {
+    let mut s = String::new();
+    for (i, chunk) in bytes.chunks(LINE_BYTES).enumerate() {
+        let address = origin + i * LINE_BYTES;
+        write!(s, "{:#05X}:", address).unwrap();
+        for j in 0..LINE_BYTES {
+            let separator = if j == LINE_BYTES / 2 { "  " } else { " " };
+            match chunk.get(j) {
+                Some(b) if highlight.contains(&(address + j)) => {
+                    write!(s, "{}{}{:02X}{}", separator, HIGHLIGHT, b, RESET).unwrap()
+                }
+                Some(b) => write!(s, "{}{:02X}", separator, b).unwrap(),
+                None => write!(s, "{}  ", separator).unwrap(),
+            }
+        }
+        s.push_str("  ");
+        s.extend(chunk.iter().map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        }));
+        s.push('\n');
+    }
+    s
+}
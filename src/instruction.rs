@@ -0,0 +1,226 @@
+use core::fmt;
+
+/// A CHIP-8 instruction with its operands, as decoded by [`decode`] and
+/// displayed in the syntax of the assembler. `x` and `y` are register
+/// numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Cls,
+    /// A call to a machine code routine, which the emulator can't execute.
+    Sys(u16),
+    Ret,
+    Jump(u16),
+    Call(u16),
+    SkipEqByte {
+        x: u8,
+        nn: u8,
+    },
+    SkipNeByte {
+        x: u8,
+        nn: u8,
+    },
+    SkipEq {
+        x: u8,
+        y: u8,
+    },
+    LoadByte {
+        x: u8,
+        nn: u8,
+    },
+    AddByte {
+        x: u8,
+        nn: u8,
+    },
+    Load {
+        x: u8,
+        y: u8,
+    },
+    Or {
+        x: u8,
+        y: u8,
+    },
+    And {
+        x: u8,
+        y: u8,
+    },
+    Xor {
+        x: u8,
+        y: u8,
+    },
+    Add {
+        x: u8,
+        y: u8,
+    },
+    Sub {
+        x: u8,
+        y: u8,
+    },
+    ShiftRight {
+        x: u8,
+        y: u8,
+    },
+    SubN {
+        x: u8,
+        y: u8,
+    },
+    ShiftLeft {
+        x: u8,
+        y: u8,
+    },
+    SkipNe {
+        x: u8,
+        y: u8,
+    },
+    LoadI(u16),
+    JumpOffset {
+        x: u8,
+        nnn: u16,
+    },
+    Random {
+        x: u8,
+        nn: u8,
+    },
+    Draw {
+        x: u8,
+        y: u8,
+        n: u8,
+    },
+    SkipKey {
+        x: u8,
+    },
+    SkipNotKey {
+        x: u8,
+    },
+    LoadDelay {
+        x: u8,
+    },
+    WaitKey {
+        x: u8,
+    },
+    SetDelay {
+        x: u8,
+    },
+    SetSound {
+        x: u8,
+    },
+    AddI {
+        x: u8,
+    },
+    Font {
+        x: u8,
+    },
+    BigFont {
+        x: u8,
+    },
+    Bcd {
+        x: u8,
+    },
+    Store {
+        x: u8,
+    },
+    Restore {
+        x: u8,
+    },
+    SaveFlags {
+        x: u8,
+    },
+    LoadFlags {
+        x: u8,
+    },
+}
+
+/// Decodes `op`, returning `None` when it isn't an instruction.
+pub fn decode(op: u16) -> Option<Instruction> {
+    let x = (op >> 8 & 0xF) as u8;
+    let y = (op >> 4 & 0xF) as u8;
+    let n = (op & 0xF) as u8;
+    let nn = op as u8;
+    let nnn = op & 0xFFF;
+    let instruction = match (op >> 12, x, y, n) {
+        (0x0, 0x0, 0xE, 0x0) => Instruction::Cls,
+        (0x0, 0x0, 0xE, 0xE) => Instruction::Ret,
+        (0x0, _, _, _) => Instruction::Sys(nnn),
+        (0x1, _, _, _) => Instruction::Jump(nnn),
+        (0x2, _, _, _) => Instruction::Call(nnn),
+        (0x3, _, _, _) => Instruction::SkipEqByte { x, nn },
+        (0x4, _, _, _) => Instruction::SkipNeByte { x, nn },
+        (0x5, _, _, 0x0) => Instruction::SkipEq { x, y },
+        (0x6, _, _, _) => Instruction::LoadByte { x, nn },
+        (0x7, _, _, _) => Instruction::AddByte { x, nn },
+        (0x8, _, _, 0x0) => Instruction::Load { x, y },
+        (0x8, _, _, 0x1) => Instruction::Or { x, y },
+        (0x8, _, _, 0x2) => Instruction::And { x, y },
+        (0x8, _, _, 0x3) => Instruction::Xor { x, y },
+        (0x8, _, _, 0x4) => Instruction::Add { x, y },
+        (0x8, _, _, 0x5) => Instruction::Sub { x, y },
+        (0x8, _, _, 0x6) => Instruction::ShiftRight { x, y },
+        (0x8, _, _, 0x7) => Instruction::SubN { x, y },
+        (0x8, _, _, 0xE) => Instruction::ShiftLeft { x, y },
+        (0x9, _, _, 0x0) => Instruction::SkipNe { x, y },
+        (0xA, _, _, _) => Instruction::LoadI(nnn),
+        (0xB, _, _, _) => Instruction::JumpOffset { x, nnn },
+        (0xC, _, _, _) => Instruction::Random { x, nn },
+        (0xD, _, _, _) => Instruction::Draw { x, y, n },
+        (0xE, _, 0x9, 0xE) => Instruction::SkipKey { x },
+        (0xE, _, 0xA, 0x1) => Instruction::SkipNotKey { x },
+        (0xF, _, 0x0, 0x7) => Instruction::LoadDelay { x },
+        (0xF, _, 0x0, 0xA) => Instruction::WaitKey { x },
+        (0xF, _, 0x1, 0x5) => Instruction::SetDelay { x },
+        (0xF, _, 0x1, 0x8) => Instruction::SetSound { x },
+        (0xF, _, 0x1, 0xE) => Instruction::AddI { x },
+        (0xF, _, 0x2, 0x9) => Instruction::Font { x },
+        (0xF, _, 0x3, 0x0) => Instruction::BigFont { x },
+        (0xF, _, 0x3, 0x3) => Instruction::Bcd { x },
+        (0xF, _, 0x5, 0x5) => Instruction::Store { x },
+        (0xF, _, 0x6, 0x5) => Instruction::Restore { x },
+        (0xF, _, 0x7, 0x5) => Instruction::SaveFlags { x },
+        (0xF, _, 0x8, 0x5) => Instruction::LoadFlags { x },
+        _ => return None,
+    };
+    Some(instruction)
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Sys(nnn) => write!(f, "SYS {:#05X}", nnn),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::Jump(nnn) => write!(f, "JP {:#05X}", nnn),
+            Instruction::Call(nnn) => write!(f, "CALL {:#05X}", nnn),
+            Instruction::SkipEqByte { x, nn } => write!(f, "SE V{:X}, {:#04X}", x, nn),
+            Instruction::SkipNeByte { x, nn } => write!(f, "SNE V{:X}, {:#04X}", x, nn),
+            Instruction::SkipEq { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::LoadByte { x, nn } => write!(f, "LD V{:X}, {:#04X}", x, nn),
+            Instruction::AddByte { x, nn } => write!(f, "ADD V{:X}, {:#04X}", x, nn),
+            Instruction::Load { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::Add { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Sub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::ShiftRight { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubN { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShiftLeft { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SkipNe { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LoadI(nnn) => write!(f, "LD I, {:#05X}", nnn),
+            Instruction::JumpOffset { nnn, .. } => write!(f, "JP V0, {:#05X}", nnn),
+            Instruction::Random { x, nn } => write!(f, "RND V{:X}, {:#04X}", x, nn),
+            Instruction::Draw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {:#X}", x, y, n),
+            Instruction::SkipKey { x } => write!(f, "SKP V{:X}", x),
+            Instruction::SkipNotKey { x } => write!(f, "SKNP V{:X}", x),
+            Instruction::LoadDelay { x } => write!(f, "LD V{:X}, DT", x),
+            Instruction::WaitKey { x } => write!(f, "LD V{:X}, K", x),
+            Instruction::SetDelay { x } => write!(f, "LD DT, V{:X}", x),
+            Instruction::SetSound { x } => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddI { x } => write!(f, "ADD I, V{:X}", x),
+            Instruction::Font { x } => write!(f, "LD F, V{:X}", x),
+            Instruction::BigFont { x } => write!(f, "LD HF, V{:X}", x),
+            Instruction::Bcd { x } => write!(f, "LD B, V{:X}", x),
+            Instruction::Store { x } => write!(f, "LD [I], V{:X}", x),
+            Instruction::Restore { x } => write!(f, "LD V{:X}, [I]", x),
+            Instruction::SaveFlags { x } => write!(f, "LD R, V{:X}", x),
+            Instruction::LoadFlags { x } => write!(f, "LD V{:X}, R", x),
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use sdl2::controller;
+use sdl2::keyboard::Keycode;
+use serde::Deserialize;
+
+use crate::config::RomConfig;
+use crate::states::SLOTS;
+
+/// A keyboard key, written using its SDL2 name such as `X`, `Space` or `PageUp`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Key(pub Keycode);
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Key, String> {
+        Keycode::from_name(&name)
+            .map(Key)
+            .ok_or_else(|| format!("unknown key `{}`", name))
+    }
+}
+
+/// A game controller button, written using its SDL2 name such as `a`,
+/// `start` or `dpup`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct PadButton(pub controller::Button);
+
+impl TryFrom<String> for PadButton {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<PadButton, String> {
+        controller::Button::from_string(&name)
+            .map(PadButton)
+            .ok_or_else(|| format!("unknown controller button `{}`", name))
+    }
+}
+
+/// Emulator control actions which can be bound to a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    SpeedUp,
+    SpeedDown,
+    Rewind,
+    Turbo,
+    Pause,
+    FrameAdvance,
+    Reset,
+    Screenshot,
+    Record,
+    Launcher,
+    NextRom,
+    PreviousRom,
+    Trace,
+    Overlay,
+    KeypadOverlay,
+    Mute,
+    VolumeUp,
+    VolumeDown,
+    Stats,
+    /// Loads the save state in a slot, numbered from 1, or saves to it with
+    /// Shift held.
+    State(u8),
+}
+
+/// Key bindings as written in the `[keys]` table of the configuration.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Bindings {
+    /// Keys for the keypad keys 0 to F, in that order.
+    pub keypad: [Key; 16],
+    /// Keypad keys by game controller button.
+    pub gamepad: HashMap<PadButton, u8>,
+    pub speed_up: Key,
+    pub speed_down: Key,
+    pub rewind: Key,
+    pub turbo: Key,
+    pub pause: Key,
+    /// Pauses, or while paused emulates a single frame.
+    pub frame_advance: Key,
+    pub reset: Key,
+    pub screenshot: Key,
+    pub record: Key,
+    pub launcher: Key,
+    /// Boot the next or previous ROM of the playlist.
+    pub next_rom: Key,
+    pub previous_rom: Key,
+    pub trace: Key,
+    pub overlay: Key,
+    pub keypad_overlay: Key,
+    pub mute: Key,
+    pub volume_up: Key,
+    pub volume_down: Key,
+    pub stats: Key,
+    /// Keys for the save state slots 1 to 10, in that order.
+    pub states: [Key; SLOTS as usize],
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        Bindings {
+            keypad: parse_keypad("X123QWEASDZC4RFV").unwrap(),
+            // The d-pad on 2, 4, 6 and 8 as on a phone keypad, which most
+            // games move with, and 5 between them for the main action.
+            gamepad: [
+                (controller::Button::DPadUp, 0x2),
+                (controller::Button::DPadLeft, 0x4),
+                (controller::Button::DPadRight, 0x6),
+                (controller::Button::DPadDown, 0x8),
+                (controller::Button::A, 0x5),
+                (controller::Button::B, 0x0),
+                (controller::Button::X, 0xA),
+                (controller::Button::Y, 0xB),
+            ]
+            .into_iter()
+            .map(|(button, key)| (PadButton(button), key))
+            .collect(),
+            speed_up: Key(Keycode::PageUp),
+            speed_down: Key(Keycode::PageDown),
+            rewind: Key(Keycode::Backspace),
+            turbo: Key(Keycode::Tab),
+            pause: Key(Keycode::P),
+            frame_advance: Key(Keycode::N),
+            reset: Key(Keycode::F11),
+            screenshot: Key(Keycode::F12),
+            record: Key(Keycode::Insert),
+            launcher: Key(Keycode::Escape),
+            next_rom: Key(Keycode::RightBracket),
+            previous_rom: Key(Keycode::LeftBracket),
+            trace: Key(Keycode::T),
+            overlay: Key(Keycode::Backquote),
+            keypad_overlay: Key(Keycode::K),
+            mute: Key(Keycode::M),
+            volume_up: Key(Keycode::Equals),
+            volume_down: Key(Keycode::Minus),
+            stats: Key(Keycode::I),
+            states: [
+                Keycode::F1,
+                Keycode::F2,
+                Keycode::F3,
+                Keycode::F4,
+                Keycode::F5,
+                Keycode::F6,
+                Keycode::F7,
+                Keycode::F8,
+                Keycode::F9,
+                Keycode::F10,
+            ]
+            .map(Key),
+        }
+    }
+}
+
+/// Parses a keypad layout, given either as 16 single character key names or
+/// as 16 comma separated key names, for the keypad keys 0 to F in order.
+///
+/// The default QWERTY layout is `X123QWEASDZC4RFV`, which maps the left side
+/// of the keyboard onto the keypad:
+///
+/// ```text
+/// 1 2 3 4      1 2 3 C
+/// Q W E R      4 5 6 D
+/// A S D F  ->  7 8 9 E
+/// Z X C V      A 0 B F
+/// ```
+pub fn parse_keypad(s: &str) -> Result<[Key; 16], String> {
+    let names: Vec<String> = if s.contains(',') {
+        s.split(',').map(|name| name.trim().to_string()).collect()
+    } else {
+        s.chars().map(String::from).collect()
+    };
+    let keys = names
+        .into_iter()
+        .map(Key::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    let count = keys.len();
+    keys.try_into()
+        .map_err(|_| format!("expected 16 keypad keys, found {}", count))
+}
+
+/// Checks that game controller buttons are bound to keypad keys that exist.
+pub fn check_gamepad(gamepad: &HashMap<PadButton, u8>) -> Result<(), String> {
+    match gamepad.iter().find(|(_, &key)| key > 0xF) {
+        Some((button, key)) => Err(format!(
+            "controller button `{}` is bound to {:#X}, but keypad keys go up to 0xF",
+            button.0.string(),
+            key
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Looks up which keypad key or action a keyboard key or game controller
+/// button is bound to.
+pub struct Keymap {
+    keypad: HashMap<Keycode, u8>,
+    actions: HashMap<Keycode, Action>,
+    buttons: HashMap<controller::Button, u8>,
+}
+
+impl Keymap {
+    /// Creates a keymap from `bindings`, with the keypad and controller
+    /// bindings of `rom_config` replacing theirs when given.
+    pub fn new(bindings: &Bindings, rom_config: &RomConfig) -> Keymap {
+        let keypad = rom_config
+            .keypad
+            .as_ref()
+            .unwrap_or(&bindings.keypad)
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key.0, i as u8))
+            .collect();
+        let actions = [
+            (bindings.speed_up, Action::SpeedUp),
+            (bindings.speed_down, Action::SpeedDown),
+            (bindings.rewind, Action::Rewind),
+            (bindings.turbo, Action::Turbo),
+            (bindings.pause, Action::Pause),
+            (bindings.frame_advance, Action::FrameAdvance),
+            (bindings.reset, Action::Reset),
+            (bindings.screenshot, Action::Screenshot),
+            (bindings.record, Action::Record),
+            (bindings.launcher, Action::Launcher),
+            (bindings.next_rom, Action::NextRom),
+            (bindings.previous_rom, Action::PreviousRom),
+            (bindings.trace, Action::Trace),
+            (bindings.overlay, Action::Overlay),
+            (bindings.keypad_overlay, Action::KeypadOverlay),
+            (bindings.mute, Action::Mute),
+            (bindings.volume_up, Action::VolumeUp),
+            (bindings.volume_down, Action::VolumeDown),
+            (bindings.stats, Action::Stats),
+        ]
+        .into_iter()
+        .chain(
+            (1..)
+                .zip(bindings.states)
+                .map(|(slot, key)| (key, Action::State(slot))),
+        )
+        .map(|(key, action)| (key.0, action))
+        .collect();
+        let buttons = rom_config
+            .gamepad
+            .as_ref()
+            .unwrap_or(&bindings.gamepad)
+            .iter()
+            .map(|(button, &key)| (button.0, key))
+            .collect();
+        Keymap {
+            keypad,
+            actions,
+            buttons,
+        }
+    }
+
+    pub fn button(&self, button: controller::Button) -> Option<u8> {
+        self.buttons.get(&button).copied()
+    }
+
+    pub fn keypad(&self, keycode: Keycode) -> Option<u8> {
+        self.keypad.get(&keycode).copied()
+    }
+
+    /// Returns the name of a keyboard key bound to keypad key `key`, the
+    /// first by name when there are several.
+    pub fn key_name(&self, key: u8) -> Option<String> {
+        self.keypad
+            .iter()
+            .filter(|&(_, &bound)| bound == key)
+            .map(|(keycode, _)| keycode.name())
+            .min()
+    }
+
+    pub fn action(&self, keycode: Keycode) -> Option<Action> {
+        self.actions.get(&keycode).copied()
+    }
+}
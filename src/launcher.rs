@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+
+use chip8::palette::{Palette, Rgb};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
+
+use crate::archive;
+use crate::text::{draw_text, GLYPH_WIDTH, LINE_HEIGHT};
+
+/// Lists the `.ch8` files in `dir`, or in the ZIP archive `dir`, and lets
+/// the user pick one with the arrow keys and Enter. Returns `None` when the window is closed or Escape is
+/// pressed.
+pub fn run(
+    canvas: &mut Canvas<Window>,
+    event_pump: &mut EventPump,
+    palette: &Palette,
+    dir: &Path,
+) -> Option<PathBuf> {
+    let mut menu = Menu {
+        dir,
+        roms: find_roms(dir),
+        selected: 0,
+        first: 0,
+        visible: 1,
+    };
+    loop {
+        menu.draw(canvas, palette);
+        let last = menu.roms.len().saturating_sub(1);
+        match event_pump.wait_event() {
+            Event::Quit { .. } => return None,
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match keycode {
+                Keycode::Escape => return None,
+                Keycode::Return | Keycode::KpEnter if !menu.roms.is_empty() => {
+                    return Some(menu.roms.swap_remove(menu.selected));
+                }
+                Keycode::Up => menu.selected = menu.selected.saturating_sub(1),
+                Keycode::Down => menu.selected = (menu.selected + 1).min(last),
+                Keycode::PageUp => menu.selected = menu.selected.saturating_sub(menu.visible),
+                Keycode::PageDown => menu.selected = (menu.selected + menu.visible).min(last),
+                Keycode::Home => menu.selected = 0,
+                Keycode::End => menu.selected = last,
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Returns the `.ch8` files in `dir` and in the ZIP archives there, sorted
+/// by name.
+pub fn find_roms(dir: &Path) -> Vec<PathBuf> {
+    if archive::is_zip(dir) {
+        return archive::list_roms(dir).unwrap_or_default();
+    }
+    let mut roms: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .flat_map(|path| {
+            if archive::is_zip(&path) {
+                archive::list_roms(&path).unwrap_or_default()
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("ch8"))
+            {
+                vec![path]
+            } else {
+                Vec::new()
+            }
+        })
+        .collect();
+    roms.sort();
+    roms
+}
+
+struct Menu<'a> {
+    dir: &'a Path,
+    roms: Vec<PathBuf>,
+    selected: usize,
+    /// Index of the first ROM shown, scrolled to keep the selection visible.
+    first: usize,
+    /// Number of ROMs which fit in the window.
+    visible: usize,
+}
+
+impl Menu<'_> {
+    fn draw(&mut self, canvas: &mut Canvas<Window>, palette: &Palette) {
+        let (width, height) = canvas.output_size().unwrap();
+        // Font pixel size, so that 640x320 windows show two window pixels
+        // per font pixel.
+        let px = (height as i32 / 160).max(1);
+        let columns = ((width as i32 / px - 2 * GLYPH_WIDTH) / GLYPH_WIDTH).max(0) as usize;
+        self.visible = ((height as i32 / px - 2 * LINE_HEIGHT) / LINE_HEIGHT).max(1) as usize;
+        self.first = self.first.clamp(
+            self.selected.saturating_sub(self.visible - 1),
+            self.selected,
+        );
+
+        let set_color = |canvas: &mut Canvas<Window>, Rgb(r, g, b): Rgb| {
+            canvas.set_draw_color(Color::RGB(r, g, b));
+        };
+        set_color(canvas, palette.background());
+        canvas.clear();
+        let x = GLYPH_WIDTH * px;
+        set_color(canvas, palette.foreground());
+        if self.roms.is_empty() {
+            let text = format!("NO ROMS FOUND IN {}", self.dir.display());
+            draw_text(canvas, x, px, px, &text, columns);
+        } else {
+            draw_text(canvas, x, px, px, "SELECT A ROM", columns);
+        }
+        let shown = self
+            .roms
+            .iter()
+            .enumerate()
+            .skip(self.first)
+            .take(self.visible);
+        for (i, rom) in shown {
+            let y = (i - self.first + 2) as i32 * LINE_HEIGHT * px;
+            let name = rom.file_stem().unwrap_or_default().to_string_lossy();
+            if i == self.selected {
+                let row = Rect::new(0, y - px, width, (LINE_HEIGHT * px) as u32);
+                set_color(canvas, palette.foreground());
+                canvas.fill_rect(row).unwrap();
+                set_color(canvas, palette.background());
+            } else {
+                set_color(canvas, palette.foreground());
+            }
+            draw_text(canvas, x, y, px, &name, columns);
+        }
+        canvas.present();
+    }
+}
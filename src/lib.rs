@@ -0,0 +1,37 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod analyze;
+#[cfg(feature = "std")]
+pub mod asm;
+#[cfg(feature = "chip8-capi")]
+pub mod capi;
+#[cfg(feature = "std")]
+pub mod disasm;
+mod emulator;
+mod error;
+#[cfg(feature = "std")]
+pub mod hexdump;
+mod instruction;
+mod memory;
+#[cfg(feature = "std")]
+pub mod palette;
+#[cfg(feature = "python")]
+mod python;
+mod quirks;
+#[cfg(feature = "serve")]
+pub mod serve;
+#[cfg(feature = "serve")]
+pub mod theme;
+mod timing;
+
+pub use emulator::{
+    Access, Emulator, FrameResult, Snapshot, Stack, BIG_FONT_ADDRESS, DEFAULT_SPEED, FONT_ADDRESS,
+    FONT_SIZE, FRAME_RATE, HEIGHT, HIRES_HEIGHT, MEMORY_SIZE, PROGRAM_ADDRESS, STACK_SIZE,
+    STATE_SIZE, WIDTH,
+};
+pub use error::Chip8Error;
+pub use instruction::{decode, Instruction};
+pub use memory::Memory;
+pub use quirks::{Platform, Quirks};
+pub use timing::Timing;
@@ -1,211 +1,1788 @@
 extern crate sdl2;
 
-use sdl2::{pixels::Color, rect::Rect, render::Canvas, video::Window, Sdl};
+mod archive;
+mod audio;
+mod compare;
+mod config;
+mod crash;
+mod database;
+mod debug_window;
+mod debugger;
+mod events;
+#[cfg(feature = "net")]
+mod fetch;
+mod flags;
+mod gamepad;
+mod gdb;
+mod headless;
+mod keymap;
+mod launcher;
+mod osd;
+mod overlay;
+mod pacer;
+mod phosphor;
+mod playlist;
+mod profile;
+mod recorder;
+mod remote;
+mod replay;
+mod rewind;
+mod screen;
+mod screenshot;
+mod script;
+mod states;
+mod stats;
+mod text;
+mod trace;
+mod watch;
 
-const WIDTH: usize = 64;
-const HEIGHT: usize = 32;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+use audio::{Beeper, Waveform};
+use chip8::disasm::Labels;
+use chip8::palette::Palette;
+use chip8::serve::{EmulatorOptions, ServeOptions};
+use chip8::{
+    analyze, asm, disasm, Access, Chip8Error, Emulator, Platform, FRAME_RATE, HEIGHT,
+    PROGRAM_ADDRESS, WIDTH,
+};
+use clap::{Parser, Subcommand};
+use compare::Comparison;
+use config::{Config, RomConfig, UnknownOpcode};
+use crash::CrashLog;
+use database::Database;
+use debug_window::DebugWindow;
+use debugger::{Control, Debugger};
+use events::EventLog;
+use flags::Flags;
+use gamepad::Gamepads;
+use gdb::GdbServer;
+use keymap::{Action, Key, Keymap};
+use osd::{Osd, VisualBeep};
+use overlay::{Button, Overlay};
+use pacer::Pacer;
+use phosphor::Phosphor;
+use playlist::Playlist;
+use profile::Profiler;
+use recorder::Recorder;
+use remote::{RemoteServer, Request};
+use replay::{Input, Player, Replay};
+use rewind::Rewind;
+use screen::{Screen, Viewport};
+use script::Script;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::messagebox::{show_simple_message_box, MessageBoxFlag};
+use sdl2::video::{FullscreenType, Window};
+use sdl2::{render::Canvas, EventPump, Sdl};
+use serde_json::Value;
+use states::States;
+use stats::Stats;
+use trace::Tracer;
+use watch::RomWatcher;
+
+/// Default initial scale, in window pixels per CHIP-8 pixel.
 const BLOCK_SIZE: u32 = 10;
+/// Title of the game window.
+const TITLE: &str = "CHIP-8 emulator!";
+
+/// Amount the volume hotkeys change the volume by.
+const VOLUME_STEP: f32 = 0.05;
+
+/// Warnings about a ROM shown in the dialog when loading it.
+const MAX_WARNINGS: usize = 10;
+
+/// Amount the speed hotkeys change the speed by.
+const SPEED_STEP: u32 = 100;
+
+/// Frames emulated per displayed frame while the turbo key is held.
+const TURBO_FRAMES: u32 = 8;
+
+#[derive(Parser)]
+#[command(about = "A CHIP-8 emulator", args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    // Running a ROM is the default when no subcommand is given.
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a ROM in a window
+    Run(RunArgs),
+    /// Run a ROM in a window, starting paused with a debugger attached
+    Debug {
+        #[command(flatten)]
+        run: RunArgs,
+        /// Wait for a GDB remote protocol connection on PORT instead of
+        /// reading debugger commands from stdin
+        #[arg(long, value_name = "PORT")]
+        gdb: Option<u16>,
+    },
+    /// Print the disassembly of a ROM
+    Disasm {
+        /// Path to the ROM to disassemble
+        rom: PathBuf,
+        /// Address the ROM is loaded at [default: 0x200]
+        #[arg(long, value_parser = debugger::parse_number)]
+        load_address: Option<usize>,
+        /// File naming addresses, with a `<address> <name>` pair per line,
+        /// to name them by instead of naming only subroutines
+        #[arg(long)]
+        labels: Option<PathBuf>,
+    },
+    /// Run a ROM without a window, then dump the display and registers
+    Check(CheckArgs),
+    /// Run a ROM without a window as fast as possible, and report how fast
+    /// it ran
+    Bench(BenchArgs),
+    /// Run a ROM with the quirks of two platforms side by side, and report
+    /// the first instruction after which they differ
+    Compare(CompareArgs),
+    /// Print the registers, stack entries and memory ranges which differ
+    /// between two save states
+    Diff {
+        /// Path to the earlier save state
+        old: PathBuf,
+        /// Path to the later save state
+        new: PathBuf,
+    },
+    /// Run a ROM without a window, streaming the display to browsers
+    /// viewing a page served over HTTP and taking their key presses
+    Serve(ServeArgs),
+    /// Assemble a source file into a ROM
+    Asm {
+        /// Path to the assembly source
+        input: PathBuf,
+        /// Path to write the ROM to
+        #[arg(short, long, default_value = "out.ch8")]
+        output: PathBuf,
+    },
+}
 
-struct Emulator {
-    memory: [u8; 4096],
-    pc: usize,
-    display: [[bool; WIDTH]; HEIGHT],
-    index_register: usize,
-    var_registers: [u8; 16],
-    stack: Vec<usize>,
+/// Flags shared by every subcommand which runs a ROM.
+#[derive(clap::Args)]
+struct EmulationArgs {
+    /// Configuration file to use instead of the `chip8.toml` found in the
+    /// working directory or user config directory
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// The CHIP-8 Archive's `programs.json`, to apply the speed, quirks and
+    /// colors it lists for known ROMs over the configured ones
+    #[arg(long)]
+    database: Option<PathBuf>,
+    /// File with 5 bytes for each of the digits 0 to F, to replace the
+    /// built-in font with
+    #[arg(long)]
+    font: Option<PathBuf>,
+    /// Address to load the ROM at and start executing from [default: 0x200],
+    /// 0x600 for ETI-660 programs
+    #[arg(long, value_parser = debugger::parse_number)]
+    load_address: Option<usize>,
+    /// Guess the quirks of ROMs missing from the database from idioms in
+    /// their code, printing the reasons
+    #[arg(long)]
+    guess_quirks: bool,
+    /// Stop with an error when the ROM writes below its load address, where
+    /// the interpreter and font live
+    #[arg(long)]
+    protect_interpreter: bool,
+    /// Count keys tapped between two EX9E or EXA1 checks as pressed for the
+    /// next check, so that slow ROMs don't miss them
+    #[arg(long)]
+    latch_keys: bool,
+    /// What to do when the ROM executes an opcode which isn't an
+    /// instruction: halt, skip it, or break into the debugger [default: halt]
+    #[arg(long, value_name = "POLICY")]
+    unknown_opcode: Option<UnknownOpcode>,
+    /// Log every executed instruction to a file, or to stderr when no file
+    /// is given. Toggle while running with T
+    #[arg(long, num_args = 0..=1, default_missing_value = "-")]
+    trace: Option<PathBuf>,
+    /// Log instructions, sprites drawn, key presses, timer ticks and saved
+    /// and loaded states as JSON lines to a file or named pipe, for other
+    /// programs to follow
+    #[arg(long)]
+    events: Option<PathBuf>,
+    /// File naming addresses, with a `<address> <name>` pair per line, to
+    /// name them by in traces and the debugger
+    #[arg(long)]
+    labels: Option<PathBuf>,
+    /// Count executed instructions, and print the hottest addresses and
+    /// loops and an opcode histogram on exit
+    #[arg(long)]
+    profile: bool,
+    /// Write which bytes of the ROM were executed and which were drawn as
+    /// sprites to a file on exit, as a map and an annotated disassembly
+    #[arg(long, value_name = "FILE")]
+    coverage: Option<PathBuf>,
+    /// Rhai script to run alongside the emulator, which can define
+    /// `on_frame(frame)` and `on_instruction(pc, op)` hooks
+    #[arg(long)]
+    script: Option<PathBuf>,
+    /// Raw memory image to load over memory from address 0 after loading
+    /// the ROM, such as one dumped by the debugger or another emulator
+    #[arg(long, value_name = "FILE")]
+    load_memory: Option<PathBuf>,
+    #[command(flatten)]
+    emulator: EmulatorOptions,
 }
 
-impl Emulator {
-    fn new() -> Emulator {
-        Emulator {
-            memory: [0; 4096],
-            pc: 0x200,
-            display: [[false; WIDTH]; HEIGHT],
-            index_register: 0,
-            var_registers: [0; 16],
-            stack: Vec::new(),
+impl EmulationArgs {
+    /// Loads the configuration, overriding its values with the flags that
+    /// were passed.
+    fn config(&self) -> Config {
+        let mut config = Config::load(self.config.as_deref()).unwrap_or_else(|e| {
+            eprintln!("Failed to load configuration: {}", e);
+            std::process::exit(1);
+        });
+        if let Some(database) = &self.database {
+            config.database = Some(database.clone());
+        }
+        if let Some(font) = &self.font {
+            config.font = Some(font.clone());
         }
+        let emulator = &self.emulator;
+        emulator.apply(&mut config.speed, &mut config.quirks);
+        config.timing = emulator.timing.unwrap_or(config.timing);
+        config.stack_limit = emulator.stack_limit.map_or(config.stack_limit, usize::from);
+        config.load_address = self.load_address.unwrap_or(config.load_address);
+        config.guess_quirks |= self.guess_quirks;
+        config.protect_interpreter |= self.protect_interpreter;
+        config.latch_keys |= self.latch_keys;
+        config.unknown_opcode = self.unknown_opcode.unwrap_or(config.unknown_opcode);
+        config.theme = emulator.theme.unwrap_or(config.theme);
+        config.colors.foreground = emulator.foreground.or(config.colors.foreground);
+        config.colors.background = emulator.background.or(config.colors.background);
+        config
+    }
+
+    fn tracer(&self) -> Option<Tracer> {
+        self.trace.as_deref().map(|path| {
+            let path = Some(path).filter(|path| *path != Path::new("-"));
+            Tracer::new(path, self.labels()).unwrap_or_else(|e| {
+                eprintln!("Failed to create trace file: {}", e);
+                std::process::exit(1);
+            })
+        })
     }
 
-    fn load_rom(&mut self, rom: Vec<u8>) -> &mut Self {
-        for (i, b) in rom.into_iter().enumerate() {
-            let idx = 0x200 + i;
-            self.memory[idx] = b;
+    /// Loads the image passed with --load-memory, exiting when it fails.
+    fn load_memory(&self, emulator: &mut Emulator) {
+        if let Some(path) = &self.load_memory {
+            if let Err(e) = debugger::restore_memory(emulator, path) {
+                eprintln!("Failed to load memory image {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
         }
-        self
     }
 
-    /// Returns true when display has changed, false otherwise.
-    fn execute_current(&mut self) -> bool {
-        let instruction = (self.memory[self.pc] as u16) << 8 | self.memory[self.pc + 1] as u16;
-        self.pc += 2;
-        self.execute(instruction)
+    fn profiler(&self) -> Option<Profiler> {
+        (self.profile || self.coverage.is_some()).then(Profiler::new)
     }
 
-    fn execute(&mut self, op: u16) -> bool {
-        let nibbles = (
-            (0xF000 & op) >> 12,
-            (0x0F00 & op) >> 8,
-            (0x00F0 & op) >> 4,
-            0x000F & op,
-        );
-        let nnn = 0xFFF & op as usize;
-        let nn = 0xFF & op as u8;
-        let n = nibbles.3 as u8;
-        let x = nibbles.1 as usize;
-        let y = nibbles.2 as usize;
-        match nibbles {
-            (0x0, 0x0, 0xE, 0x0) => {
-                self.display = [[false; WIDTH]; HEIGHT];
-                true
-            }
-            (0x0, 0x0, 0xE, 0xE) => {
-                self.pc = self.stack.pop().unwrap();
-                false
+    /// Prints the profile and writes the coverage of `rom` when enabled.
+    fn report(&self, profiler: Option<&Profiler>, rom: &[u8], load_address: usize) {
+        let Some(profiler) = profiler else {
+            return;
+        };
+        if self.profile {
+            eprint!("{}", profiler.report());
+        }
+        if let Some(path) = &self.coverage {
+            if let Err(e) = std::fs::write(path, profiler.coverage(rom, load_address)) {
+                eprintln!("Failed to write coverage to {}: {}", path.display(), e);
             }
-            (0x1, _, _, _) => {
-                self.pc = nnn;
-                false
+        }
+    }
+
+    fn labels(&self) -> Labels {
+        self.labels.as_deref().map(load_labels).unwrap_or_default()
+    }
+
+    fn events(&self) -> Option<EventLog> {
+        self.events.as_deref().map(|path| {
+            EventLog::new(path).unwrap_or_else(|e| {
+                eprintln!("Failed to create event log: {}", e);
+                std::process::exit(1);
+            })
+        })
+    }
+}
+
+#[derive(clap::Args)]
+struct RunArgs {
+    /// Path or, with the net feature, URL of the ROM to run, picked from a
+    /// launcher when omitted
+    rom: Option<PathBuf>,
+    #[command(flatten)]
+    emulation: EmulationArgs,
+    /// Directory the launcher lists ROMs from [default: rom]
+    #[arg(long)]
+    rom_dir: Option<PathBuf>,
+    /// Accept remote control commands as lines of JSON on PORT, to press
+    /// keys, pause, reset, load ROMs and read the registers and display
+    #[arg(long, value_name = "PORT")]
+    remote: Option<u16>,
+    /// Directory or ZIP archive of ROMs to cycle through, starting with the
+    /// first, with ] and [ booting the next and previous one
+    #[arg(long, conflicts_with = "rom")]
+    playlist: Option<PathBuf>,
+    /// Memory budget for rewind history in MiB, 0 disables rewinding
+    /// [default: 4]. Hold Backspace to rewind
+    #[arg(long)]
+    rewind_buffer: Option<usize>,
+    /// Initial window size in window pixels per CHIP-8 pixel [default: 10]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    scale: Option<u32>,
+    /// Fade out erased pixels over a few frames to reduce flicker
+    #[arg(long)]
+    phosphor: bool,
+    /// Start in fullscreen mode, toggled while running with Alt+Enter
+    #[arg(long)]
+    fullscreen: bool,
+    /// Present frames in sync with the display's refresh, for smooth and
+    /// tear-free output at any refresh rate, emulating as many frames for
+    /// each refresh as the time it took calls for
+    #[arg(long)]
+    vsync: bool,
+    /// Show the registers, stack, code and keypad in a second window
+    #[arg(long)]
+    debug_window: bool,
+    /// Show the keypad over the display, with the keys held highlighted and
+    /// the keyboard key each is bound to, toggled while running with K
+    #[arg(long)]
+    keypad_overlay: bool,
+    /// Show the rendered frames, executed instructions and timer ticks per
+    /// second in the window title, toggled while running with I
+    #[arg(long)]
+    stats: bool,
+    /// Volume of the tone from 0 to 1 [default: 0.25], adjustable while
+    /// running with - and =, and muted with M
+    #[arg(long)]
+    volume: Option<f32>,
+    /// Shape of the tone's wave [default: square]
+    #[arg(long)]
+    waveform: Option<Waveform>,
+    /// Frequency of the tone in Hz [default: 440]
+    #[arg(long)]
+    frequency: Option<f32>,
+    /// Show the buzzer on screen while it sounds, as a border around the
+    /// window or a speaker icon, for players who can't hear it
+    #[arg(long)]
+    visual_beep: Option<VisualBeep>,
+    /// Rumble game controllers while the sound timer is active, with a
+    /// strength from 0 to 1 [default: 0, off]
+    #[arg(long)]
+    rumble: Option<f32>,
+    /// Keys for the keypad keys 0 to F, as 16 characters or 16 comma
+    /// separated key names [default: X123QWEASDZC4RFV]
+    #[arg(long, value_parser = keymap::parse_keypad)]
+    keymap: Option<[Key; 16]>,
+    /// Record keypad input to a replay file, saved on exit
+    #[arg(long, conflicts_with = "replay")]
+    record_input: Option<PathBuf>,
+    /// Play back the input recorded in a replay file
+    #[arg(long, conflicts_with = "seed")]
+    replay: Option<PathBuf>,
+    /// Reset and reload the ROM whenever its file changes, such as when
+    /// it's assembled again
+    #[arg(long, requires = "rom")]
+    watch: bool,
+}
+
+impl RunArgs {
+    fn config(&self) -> Config {
+        let mut config = self.emulation.config();
+        if let Some(dir) = &self.rom_dir {
+            config.rom_dir.clone_from(dir);
+        }
+        config.rewind_buffer = self.rewind_buffer.unwrap_or(config.rewind_buffer);
+        config.scale = self.scale.unwrap_or(config.scale);
+        config.phosphor |= self.phosphor;
+        config.fullscreen |= self.fullscreen;
+        config.vsync |= self.vsync;
+        config.debug_window |= self.debug_window;
+        config.keypad_overlay |= self.keypad_overlay;
+        config.stats |= self.stats;
+        config.audio.volume = self.volume.unwrap_or(config.audio.volume);
+        config.audio.waveform = self.waveform.unwrap_or(config.audio.waveform);
+        config.audio.frequency = self.frequency.unwrap_or(config.audio.frequency);
+        config.audio.visual_beep = self.visual_beep.unwrap_or(config.audio.visual_beep);
+        config.gamepad.rumble = self.rumble.unwrap_or(config.gamepad.rumble);
+        config.keys.keypad = self.keymap.unwrap_or(config.keys.keypad);
+        if let Err(e) = config.check() {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(1);
+        }
+        config
+    }
+}
+
+#[derive(clap::Args)]
+struct CheckArgs {
+    /// Path to the ROM to run
+    rom: PathBuf,
+    #[command(flatten)]
+    emulation: EmulationArgs,
+    /// Maximum number of instructions to execute
+    #[arg(long, default_value_t = 1_000_000)]
+    cycles: u64,
+    /// File to dump to instead of stdout, as PNG when the extension is
+    /// `.png` and as text otherwise
+    #[arg(long, conflicts_with = "hash")]
+    dump: Option<PathBuf>,
+    /// Print a hash of the display instead of dumping it, to compare runs
+    /// against a known-good one
+    #[arg(long)]
+    hash: bool,
+    /// Write all of memory to a file as a raw image when done
+    #[arg(long, value_name = "FILE")]
+    dump_memory: Option<PathBuf>,
+    /// Scan the ROM's code for the extensions and quirks it needs instead
+    /// of running it, failing when it has instructions this build doesn't
+    /// execute
+    #[arg(long, conflicts_with_all = ["dump", "hash", "dump_memory"])]
+    scan: bool,
+}
+
+#[derive(clap::Args)]
+struct ServeArgs {
+    /// Path to the ROM to run
+    rom: PathBuf,
+    #[command(flatten)]
+    emulation: EmulationArgs,
+    #[command(flatten)]
+    serve: ServeOptions,
+}
+
+#[derive(clap::Args)]
+struct BenchArgs {
+    /// Path to the ROM to run
+    rom: PathBuf,
+    #[command(flatten)]
+    emulation: EmulationArgs,
+    /// How long to run for
+    #[arg(long, default_value_t = 5.0)]
+    seconds: f64,
+}
+
+#[derive(clap::Args)]
+struct CompareArgs {
+    /// Path to the ROM to run
+    rom: PathBuf,
+    /// Platforms to use the quirks of: chip8, chip48, schip or xochip
+    #[arg(num_args = 2, required = true)]
+    platforms: Vec<Platform>,
+    #[command(flatten)]
+    emulation: EmulationArgs,
+    /// Size of a CHIP-8 pixel in window pixels
+    #[arg(long)]
+    scale: Option<u32>,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    match cli.command.unwrap_or(Command::Run(cli.run)) {
+        Command::Run(args) => run(args, None),
+        Command::Debug { run: args, gdb } => {
+            let debugger: Box<dyn Control> = match gdb {
+                Some(port) => match GdbServer::bind(port) {
+                    Ok(server) => Box::new(server),
+                    Err(e) => {
+                        eprintln!("Failed to listen on port {}: {}", port, e);
+                        std::process::exit(1);
+                    }
+                },
+                None => Box::new(Debugger::new(args.emulation.labels())),
+            };
+            run(args, Some(debugger));
+        }
+        Command::Disasm {
+            rom,
+            load_address,
+            labels,
+        } => {
+            let load_address = load_address.unwrap_or(PROGRAM_ADDRESS);
+            let rom = archive::read_rom(&fetch_rom(&rom)).unwrap_or_else(|e| {
+                eprintln!("Failed to read {}: {}", rom.display(), e);
+                std::process::exit(1);
+            });
+            let mut names = Labels::infer(&rom, load_address);
+            names.extend(labels.as_deref().map(load_labels).unwrap_or_default());
+            for line in disasm::disassemble(&rom, load_address) {
+                if let Some(name) = names.get(line.address) {
+                    println!("{}:", name);
+                }
+                println!("{}", line.labeled(&names));
             }
-            (0x2, _, _, _) => {
-                self.stack.push(self.pc);
-                self.pc = nnn;
-                false
+        }
+        Command::Check(args) => check(args),
+        Command::Bench(args) => bench(args),
+        Command::Compare(args) => compare(args),
+        Command::Serve(args) => serve(args),
+        Command::Diff { old, new } => {
+            let read = |path: &Path| {
+                states::read(path).unwrap_or_else(|e| {
+                    eprintln!("Failed to read {}: {}", path.display(), e);
+                    std::process::exit(1);
+                })
+            };
+            print!("{}", states::diff(&read(&old), &read(&new)));
+        }
+        Command::Asm { input, output } => {
+            let source = std::fs::read_to_string(&input).unwrap_or_else(|e| {
+                eprintln!("Failed to read {}: {}", input.display(), e);
+                std::process::exit(1);
+            });
+            match asm::assemble(&source) {
+                Ok(rom) => {
+                    if let Err(e) = std::fs::write(&output, rom) {
+                        eprintln!("Failed to write {}: {}", output.display(), e);
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}: {}", input.display(), e);
+                    std::process::exit(1);
+                }
             }
-            (0x3, _, _, _) => {
-                self.pc += if self.var_registers[x] == nn { 2 } else { 0 };
-                false
+        }
+    }
+}
+
+/// Runs a ROM without a window, for `check`.
+fn check(args: CheckArgs) {
+    let config = args.emulation.config();
+    let mut tracer = args.emulation.tracer();
+    let mut profiler = args.emulation.profiler();
+    let mut events = args.emulation.events();
+    let database = config.database.as_deref().map(load_database);
+    let mut palette = config.palette();
+    let (mut emulator, rom, rom_config) = load_game(&args.rom, &config);
+    if args.scan {
+        let scan = analyze::scan(&rom, emulator.load_address());
+        print!("{}", scan);
+        std::process::exit(if scan.unsupported.is_empty() { 0 } else { 1 });
+    }
+    configure(
+        &mut emulator,
+        &mut palette,
+        &config,
+        database.as_ref(),
+        &rom,
+        &rom_config,
+    );
+    for diagnostic in analyze::analyze(&rom, emulator.load_address()) {
+        eprintln!("Warning: {}", diagnostic);
+    }
+    if let Some(seed) = args.emulation.emulator.seed {
+        emulator.reseed(seed);
+    }
+    args.emulation.load_memory(&mut emulator);
+    let mut script = args
+        .emulation
+        .script
+        .as_deref()
+        .map(|path| load_script(path, &mut emulator));
+    headless::run(
+        &mut emulator,
+        args.cycles,
+        tracer.as_mut(),
+        profiler.as_mut(),
+        script.as_mut(),
+        events.as_mut(),
+        config.unknown_opcode,
+    );
+    if args.hash {
+        println!("{:016x}", emulator.display_hash());
+    } else if let Err(e) = headless::dump(&emulator, args.dump.as_deref(), &palette) {
+        eprintln!("Failed to write dump: {}", e);
+        std::process::exit(1);
+    }
+    if let Some(path) = &args.dump_memory {
+        if let Err(e) = debugger::dump_memory(&emulator, path) {
+            eprintln!("Failed to write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+    args.emulation
+        .report(profiler.as_ref(), &rom, emulator.load_address());
+}
+
+/// Runs a ROM without a window as fast as possible, for `bench`.
+fn bench(args: BenchArgs) {
+    let config = args.emulation.config();
+    let database = config.database.as_deref().map(load_database);
+    let (mut emulator, rom, rom_config) = load_game(&args.rom, &config);
+    configure(
+        &mut emulator,
+        &mut config.palette(),
+        &config,
+        database.as_ref(),
+        &rom,
+        &rom_config,
+    );
+    if let Some(seed) = args.emulation.emulator.seed {
+        emulator.reseed(seed);
+    }
+    args.emulation.load_memory(&mut emulator);
+    let duration = Duration::try_from_secs_f64(args.seconds).unwrap_or_else(|e| {
+        eprintln!("Invalid duration {}: {}", args.seconds, e);
+        std::process::exit(1);
+    });
+    println!("{}", headless::bench(&mut emulator, duration));
+}
+
+/// Runs a ROM without a window in real time, for `serve`.
+fn serve(args: ServeArgs) {
+    let config = args.emulation.config();
+    let database = config.database.as_deref().map(load_database);
+    let mut palette = config.palette();
+    let (mut emulator, rom, rom_config) = load_game(&args.rom, &config);
+    configure(
+        &mut emulator,
+        &mut palette,
+        &config,
+        database.as_ref(),
+        &rom,
+        &rom_config,
+    );
+    if let Some(seed) = args.emulation.emulator.seed {
+        emulator.reseed(seed);
+    }
+    args.emulation.load_memory(&mut emulator);
+    let addr = args.serve.addr();
+    if let Err(e) = chip8::serve::serve(&mut emulator, &palette, addr) {
+        eprintln!("Failed to serve on {}: {}", addr, e);
+        std::process::exit(1);
+    }
+}
+
+/// Runs a ROM with two sets of quirks side by side, for `compare`.
+fn compare(args: CompareArgs) {
+    let config = args.emulation.config();
+    let database = config.database.as_deref().map(load_database);
+    let mut palette = config.palette();
+    let (mut emulator, rom, rom_config) = load_game(&args.rom, &config);
+    configure(
+        &mut emulator,
+        &mut palette,
+        &config,
+        database.as_ref(),
+        &rom,
+        &rom_config,
+    );
+    if let Some(seed) = args.emulation.emulator.seed {
+        emulator.reseed(seed);
+    }
+    args.emulation.load_memory(&mut emulator);
+    let platforms = [args.platforms[0], args.platforms[1]];
+    let comparison = Comparison::new(emulator, platforms);
+    let keymap = Keymap::new(&config.keys, &rom_config);
+    let scale = args.scale.unwrap_or(config.scale);
+    if let Err(e) = compare::run(comparison, &keymap, &palette, scale) {
+        eprintln!("Failed to open a window: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Runs a ROM in a window, picking it from the launcher when none was
+/// given, with `debugger` controlling execution when attached.
+fn run(args: RunArgs, mut debugger: Option<Box<dyn Control>>) {
+    let mut config = args.config();
+    let mut playlist = args.playlist.as_deref().map(|dir| {
+        Playlist::new(dir).unwrap_or_else(|e| {
+            eprintln!("Failed to load playlist {}: {}", dir.display(), e);
+            std::process::exit(1);
+        })
+    });
+    let fetched = args.rom.as_deref().map(fetch_rom).or_else(|| {
+        playlist
+            .as_ref()
+            .map(|playlist| playlist.current().to_path_buf())
+    });
+    // The ROMs of a pack are picked from in the launcher.
+    let rom_path = match fetched.as_deref() {
+        Some(path) if archive::is_pack(path) => {
+            config.rom_dir = path.to_path_buf();
+            None
+        }
+        path => path,
+    };
+    let mut tracer = args.emulation.tracer();
+    let mut events = args.emulation.events();
+    let mut profiler = args.emulation.profiler();
+    let database = config.database.as_deref().map(load_database);
+    let mut palette = config.palette();
+    let game = rom_path.map(|path| load_game(path, &config));
+
+    let sdl_context = sdl2::init().unwrap();
+    let rows = game
+        .as_ref()
+        .map_or(HEIGHT, |(emulator, ..)| emulator.height());
+    let mut canvas = create_canvas(
+        &sdl_context,
+        WIDTH as u32 * config.scale,
+        rows as u32 * config.scale,
+        config.vsync,
+    )
+    .unwrap();
+    if config.fullscreen {
+        toggle_fullscreen(canvas.window_mut());
+    }
+    if let Some(playlist) = &playlist {
+        let title = format!("{} - {}", TITLE, playlist);
+        canvas.window_mut().set_title(&title).unwrap();
+    }
+    let texture_creator = canvas.texture_creator();
+    let mut screen = Screen::new(&texture_creator, config.crt);
+    let mut event_pump = sdl_context.event_pump().unwrap();
+    let mut debug_window = if config.debug_window {
+        DebugWindow::new(&sdl_context.video().unwrap())
+            .map_err(|e| eprintln!("Failed to open debug window: {}", e))
+            .ok()
+    } else {
+        None
+    };
+
+    let launched = game.or_else(|| launch(&mut canvas, &mut event_pump, &palette, &config));
+    let Some((mut emulator, mut rom, rom_config)) = launched else {
+        return;
+    };
+    let mut viewport = Viewport::fit(canvas.output_size().unwrap(), emulator.height());
+    configure(
+        &mut emulator,
+        &mut palette,
+        &config,
+        database.as_ref(),
+        &rom,
+        &rom_config,
+    );
+    check_rom(canvas.window(), &rom, emulator.load_address());
+    if let Some(seed) = args.emulation.emulator.seed {
+        emulator.reseed(seed);
+    }
+    args.emulation.load_memory(&mut emulator);
+    let mut flags = Flags::load(&mut emulator, &rom);
+    let mut states = States::new(&rom);
+
+    let mut player = args.replay.as_ref().map(|path| match Replay::load(path) {
+        Ok(replay) => {
+            emulator.reseed(replay.seed);
+            emulator.set_speed(replay.speed);
+            Player::new(replay)
+        }
+        Err(e) => {
+            eprintln!("Failed to load replay {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    });
+    let mut recording = args
+        .record_input
+        .clone()
+        .map(|path| (path, Replay::new(emulator.seed(), emulator.speed())));
+
+    let mut script = args
+        .emulation
+        .script
+        .as_deref()
+        .map(|path| load_script(path, &mut emulator));
+
+    let mut beeper = if config.audio.enabled {
+        let volume = config.audio.volume.clamp(0.0, 1.0);
+        Beeper::new(&sdl_context, volume, config.audio.tone())
+            .map_err(|e| eprintln!("Failed to open audio device, sound is disabled: {}", e))
+            .ok()
+    } else {
+        None
+    };
+
+    let mut gamepads = if config.gamepad.enabled {
+        Gamepads::new(
+            &sdl_context,
+            config.gamepad.mappings.as_deref(),
+            config.gamepad.rumble,
+        )
+        .map_err(|e| eprintln!("Failed to set up game controllers: {}", e))
+        .ok()
+    } else {
+        None
+    };
+
+    let mut remote = args.remote.map(|port| {
+        RemoteServer::bind(port).unwrap_or_else(|e| {
+            eprintln!("Failed to listen on port {}: {}", port, e);
+            std::process::exit(1);
+        })
+    });
+    // Set while a ROM load requested remotely is pending, to answer with
+    // its result.
+    let mut remote_load = false;
+    let mut watcher = rom_path.filter(|_| args.watch).and_then(|path| {
+        RomWatcher::new(path)
+            .map_err(|e| eprintln!("Failed to watch {}: {}", path.display(), e))
+            .ok()
+    });
+
+    let mut keymap = Keymap::new(&config.keys, &rom_config);
+    let mut rewind = Rewind::new(config.rewind_buffer * 1024 * 1024);
+    let mut crash_log = CrashLog::new();
+    let mut tracing = tracer.is_some();
+    let mut phosphor = Phosphor::new(config.phosphor);
+    let mut overlay = Overlay::new(canvas.window());
+    let mut keypad_overlay = config.keypad_overlay;
+    // Set while the visual beep indicator is shown.
+    let mut beeped = false;
+    let mut osd = Osd::new();
+    let mut stats = Stats::new(config.stats);
+    // Set by the overlay's step button, to execute one instruction while
+    // paused.
+    let mut step_once = false;
+    // Set by the frame advance key, to emulate one frame while paused.
+    let mut advance_frame = false;
+    let mut rewinding = false;
+    let mut turbo = false;
+    let mut paused = false;
+    // Set while another application has focus, separately from `paused` so
+    // that regaining focus doesn't resume a game paused by the user.
+    let mut in_background = false;
+    let mut recorder: Option<Recorder> = None;
+    let mut frame = 0;
+    // Frames actually emulated, which replay inputs are timestamped with.
+    let mut emulated_frame = 0;
+    let mut inputs = Vec::new();
+
+    let frame_duration = Duration::from_secs(1) / FRAME_RATE;
+    let mut next_frame = Instant::now();
+    let mut pacer = config.vsync.then(Pacer::new);
+    'running: loop {
+        let mut open_launcher = false;
+        // ROM to boot in place of the running one, dropped on the window or
+        // changed on disk.
+        let mut load = watcher
+            .as_ref()
+            .filter(|watcher| watcher.changed())
+            .map(|watcher| watcher.path().to_path_buf());
+        for event in event_pump.poll_iter() {
+            overlay.handle_event(canvas.window(), &event);
+            match event {
+                Event::Quit { .. } => break 'running,
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::Close,
+                    ..
+                } => {
+                    if debug_window.as_ref().is_some_and(|w| w.id() == window_id) {
+                        debug_window = None;
+                    } else {
+                        break 'running;
+                    }
+                }
+                Event::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } if config.pause_in_background => in_background = true,
+                Event::Window {
+                    win_event: WindowEvent::FocusGained,
+                    ..
+                } => in_background = false,
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::SizeChanged(..),
+                    ..
+                } if window_id == canvas.window().id() => {
+                    viewport = Viewport::fit(canvas.output_size().unwrap(), emulator.height());
+                    screen.invalidate();
+                }
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::Exposed,
+                    ..
+                } if window_id == canvas.window().id() => screen.invalidate(),
+                Event::DropFile { filename, .. } => load = Some(PathBuf::from(filename)),
+                Event::KeyDown {
+                    keycode: Some(Keycode::Return),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                    toggle_fullscreen(canvas.window_mut());
+                    viewport = Viewport::fit(canvas.output_size().unwrap(), emulator.height());
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    keymod,
+                    repeat,
+                    ..
+                } => {
+                    if let Some(key) = keymap.keypad(keycode) {
+                        inputs.push(Input::Key { key, pressed: true });
+                    }
+                    match keymap.action(keycode).filter(|_| !repeat) {
+                        Some(Action::SpeedUp) => {
+                            inputs.push(Input::Speed(emulator.speed() + SPEED_STEP));
+                        }
+                        Some(Action::SpeedDown) => {
+                            let speed = emulator.speed().saturating_sub(SPEED_STEP);
+                            inputs.push(Input::Speed(speed.max(SPEED_STEP)));
+                        }
+                        Some(Action::Rewind) if recording.is_some() || player.is_some() => {
+                            osd.show("Rewinding is disabled while recording or replaying input");
+                        }
+                        Some(Action::Rewind) => rewinding = true,
+                        Some(Action::Turbo) => turbo = true,
+                        Some(Action::Launcher) if recording.is_some() || player.is_some() => {
+                            osd.show("Can't change ROMs while recording or replaying input");
+                        }
+                        Some(Action::Launcher) => open_launcher = true,
+                        Some(Action::NextRom | Action::PreviousRom)
+                            if recording.is_some() || player.is_some() =>
+                        {
+                            osd.show("Can't change ROMs while recording or replaying input");
+                        }
+                        Some(action @ (Action::NextRom | Action::PreviousRom)) => {
+                            match &mut playlist {
+                                Some(playlist) if action == Action::NextRom => {
+                                    load = Some(playlist.next().to_path_buf());
+                                }
+                                Some(playlist) => load = Some(playlist.previous().to_path_buf()),
+                                None => osd.show("No playlist to change ROMs in"),
+                            }
+                        }
+                        Some(Action::Pause) => {
+                            paused = !paused;
+                            osd.show(if paused { "Paused" } else { "Resumed" });
+                        }
+                        Some(Action::FrameAdvance) if paused => advance_frame = true,
+                        Some(Action::FrameAdvance) => {
+                            paused = true;
+                            osd.show("Paused");
+                        }
+                        Some(Action::Screenshot) => {
+                            let rgba = emulator.framebuffer_rgba(&palette);
+                            match screenshot::save(&rgba, viewport.scale) {
+                                Ok(path) => {
+                                    osd.show(format!("Saved screenshot to {}", path.display()))
+                                }
+                                Err(e) => eprintln!("Failed to save screenshot: {}", e),
+                            }
+                        }
+                        Some(Action::Overlay) => {
+                            overlay.visible = !overlay.visible;
+                            screen.invalidate();
+                        }
+                        Some(Action::KeypadOverlay) => {
+                            keypad_overlay = !keypad_overlay;
+                            screen.invalidate();
+                        }
+                        Some(Action::Stats) => {
+                            stats.visible = !stats.visible;
+                            if !stats.visible {
+                                canvas.window_mut().set_title(TITLE).unwrap();
+                            }
+                        }
+                        Some(Action::Mute) => match beeper.as_mut() {
+                            Some(beeper) => {
+                                if beeper.toggle_mute() {
+                                    osd.show("Muted");
+                                } else {
+                                    osd.show(format!("Unmuted, volume {:.2}", beeper.volume()));
+                                }
+                            }
+                            None => osd.show("Sound is disabled"),
+                        },
+                        Some(action @ (Action::VolumeUp | Action::VolumeDown)) => {
+                            if let Some(beeper) = beeper.as_mut() {
+                                let step = if action == Action::VolumeUp {
+                                    VOLUME_STEP
+                                } else {
+                                    -VOLUME_STEP
+                                };
+                                beeper.set_volume(beeper.volume() + step);
+                                osd.show(format!("Volume: {:.2}", beeper.volume()));
+                            }
+                        }
+                        Some(Action::Trace) => {
+                            tracing = !tracing;
+                            if tracing && tracer.is_none() {
+                                tracer = Some(Tracer::new(None, args.emulation.labels()).unwrap());
+                            }
+                            osd.show(format!("Tracing {}", if tracing { "on" } else { "off" }));
+                        }
+                        Some(Action::Record) => match recorder.take() {
+                            Some(recording) => stop_recording(recording, frame, &mut osd),
+                            None => {
+                                let path = screenshot::timestamped_path("gif");
+                                let (scale, rows) = (viewport.scale, viewport.rows);
+                                match Recorder::start(path, &palette, scale, rows) {
+                                    Ok(recording) => {
+                                        recording.capture(&emulator.display, frame);
+                                        recorder = Some(recording);
+                                        osd.show("Recording started");
+                                    }
+                                    Err(e) => eprintln!("Failed to start recording: {}", e),
+                                }
+                            }
+                        },
+                        Some(Action::Reset) => inputs.push(Input::Reset),
+                        Some(Action::State(slot))
+                            if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) =>
+                        {
+                            match states.save(&emulator, slot) {
+                                Ok(()) => {
+                                    if let Some(events) = &mut events {
+                                        events.state(slot, true);
+                                    }
+                                    osd.show(format!("State saved to slot {}", slot));
+                                }
+                                Err(e) => osd.show(format!("Failed to save slot {}: {}", slot, e)),
+                            }
+                        }
+                        Some(Action::State(_)) if recording.is_some() || player.is_some() => {
+                            osd.show(
+                                "Loading states is disabled while recording or replaying input",
+                            );
+                        }
+                        Some(Action::State(slot)) => match states.load(&mut emulator, slot) {
+                            Ok(()) => {
+                                if let Some(events) = &mut events {
+                                    events.state(slot, false);
+                                }
+                                osd.show(format!("State loaded from slot {}", slot));
+                            }
+                            Err(e) => osd.show(format!("Failed to load slot {}: {}", slot, e)),
+                        },
+                        None => {}
+                    }
+                }
+                Event::ControllerDeviceAdded { which, .. } => {
+                    if let Some(gamepads) = &mut gamepads {
+                        gamepads.add(which);
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    if let Some(gamepads) = &mut gamepads {
+                        gamepads.remove(which);
+                    }
+                }
+                Event::ControllerButtonDown { button, .. } => {
+                    if let Some(key) = keymap.button(button) {
+                        inputs.push(Input::Key { key, pressed: true });
+                    }
+                }
+                Event::ControllerButtonUp { button, .. } => {
+                    if let Some(key) = keymap.button(button) {
+                        inputs.push(Input::Key {
+                            key,
+                            pressed: false,
+                        });
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    if let Some(key) = keymap.keypad(keycode) {
+                        inputs.push(Input::Key {
+                            key,
+                            pressed: false,
+                        });
+                    }
+                    match keymap.action(keycode) {
+                        Some(Action::Rewind) => rewinding = false,
+                        Some(Action::Turbo) => turbo = false,
+                        _ => {}
+                    }
+                }
+                _ => {}
             }
-            (0x4, _, _, _) => {
-                self.pc += if self.var_registers[x] != nn { 2 } else { 0 };
-                false
+        }
+        while let Some(remote) = remote.as_mut().filter(|_| !remote_load) {
+            let Some(request) = remote.poll() else {
+                break;
+            };
+            let reply = match request {
+                Request::Press { key } | Request::Release { key } if key > 0xF => {
+                    Err(format!("no key {:#X}, keys are 0 to 0xF", key))
+                }
+                Request::Press { key } => {
+                    inputs.push(Input::Key { key, pressed: true });
+                    Ok(Value::Null)
+                }
+                Request::Release { key } => {
+                    inputs.push(Input::Key {
+                        key,
+                        pressed: false,
+                    });
+                    Ok(Value::Null)
+                }
+                Request::Pause => {
+                    paused = true;
+                    Ok(Value::Null)
+                }
+                Request::Resume => {
+                    paused = false;
+                    Ok(Value::Null)
+                }
+                Request::Reset => {
+                    inputs.push(Input::Reset);
+                    Ok(Value::Null)
+                }
+                // Answered once loaded.
+                Request::Load { path } => {
+                    load = Some(path);
+                    remote_load = true;
+                    continue;
+                }
+                Request::Registers => Ok(remote::registers(&emulator)),
+                Request::Framebuffer => Ok(remote::framebuffer(&emulator)),
+            };
+            remote.reply(reply);
+        }
+        // Live input is ignored while a replay is being played back.
+        for input in inputs.drain(..).filter(|_| player.is_none()) {
+            if let Some((_, replay)) = &mut recording {
+                replay.inputs.push((emulated_frame, input));
             }
-            (0x6, _, _, _) => {
-                self.var_registers[x] = nn;
-                false
+            apply_input(&mut emulator, input, &mut osd, events.as_mut());
+        }
+        match load {
+            Some(path) if recording.is_some() || player.is_some() => {
+                let message = format!(
+                    "Can't load {} while recording or replaying input",
+                    path.display()
+                );
+                match remote.as_mut().filter(|_| remote_load) {
+                    Some(remote) => remote.reply(Err(message)),
+                    None => osd.show(message),
+                }
             }
-            (0x7, _, _, _) => {
-                self.var_registers[x] += nn;
-                false
+            Some(path) => {
+                match boot(&path, &config) {
+                    Ok((loaded, data, rom_config)) => {
+                        emulator = loaded;
+                        rom = data;
+                        configure(
+                            &mut emulator,
+                            &mut palette,
+                            &config,
+                            database.as_ref(),
+                            &rom,
+                            &rom_config,
+                        );
+                        keymap = Keymap::new(&config.keys, &rom_config);
+                        check_rom(canvas.window(), &rom, emulator.load_address());
+                        flags = Flags::load(&mut emulator, &rom);
+                        states = States::new(&rom);
+                        rewind.clear();
+                        viewport = Viewport::fit(canvas.output_size().unwrap(), emulator.height());
+                        match playlist
+                            .as_ref()
+                            .filter(|playlist| playlist.current() == path)
+                        {
+                            Some(playlist) => {
+                                osd.show(playlist.to_string());
+                                let title = format!("{} - {}", TITLE, playlist);
+                                canvas.window_mut().set_title(&title).unwrap();
+                            }
+                            None => osd.show(format!("Loaded {}", path.display())),
+                        }
+                        if let Some(remote) = remote.as_mut().filter(|_| remote_load) {
+                            remote.reply(Ok(Value::Null));
+                        }
+                    }
+                    Err(e) => {
+                        let message = format!("Failed to load {}: {}", path.display(), e);
+                        // A dialog would hold up the client.
+                        match remote.as_mut().filter(|_| remote_load) {
+                            Some(remote) => remote.reply(Err(message)),
+                            None => show_error(canvas.window(), &message),
+                        }
+                    }
+                }
+                let path = path.canonicalize().ok();
+                if let Some(old) = watcher.take_if(|old| path.as_deref() != Some(old.path())) {
+                    osd.show(format!("Stopped watching {}", old.path().display()));
+                }
             }
-            (0x8, _, _, 0x0) => {
-                self.var_registers[x] = self.var_registers[y];
-                false
+            None => {}
+        }
+        remote_load = false;
+        if open_launcher {
+            if let Some(beeper) = &beeper {
+                beeper.set_playing(false);
             }
-            (0x8, _, _, 0x7) => {
-                let (result, overflowing) =
-                    self.var_registers[y].overflowing_sub(self.var_registers[x]);
-                self.var_registers[x] = result;
-                self.var_registers[0xF] = if overflowing { 0 } else { 1 };
-                false
+            if let Some(recording) = recorder.take() {
+                stop_recording(recording, frame, &mut osd);
             }
-            (0xA, _, _, _) => {
-                self.index_register = nnn;
-                false
+            let launched = launch(&mut canvas, &mut event_pump, &config.palette(), &config);
+            let Some((launched, data, rom_config)) = launched else {
+                break 'running;
+            };
+            emulator = launched;
+            rom = data;
+            if let Some(old) = watcher.take() {
+                osd.show(format!("Stopped watching {}", old.path().display()));
             }
-            (0xC, _, _, _) => {
-                self.var_registers[x] = rand::random::<u8>() & nn;
-                false
+            configure(
+                &mut emulator,
+                &mut palette,
+                &config,
+                database.as_ref(),
+                &rom,
+                &rom_config,
+            );
+            keymap = Keymap::new(&config.keys, &rom_config);
+            check_rom(canvas.window(), &rom, emulator.load_address());
+            flags = Flags::load(&mut emulator, &rom);
+            states = States::new(&rom);
+            rewind.clear();
+            paused = false;
+            viewport = Viewport::fit(canvas.output_size().unwrap(), emulator.height());
+            screen.invalidate();
+            next_frame = Instant::now();
+            if let Some(pacer) = &mut pacer {
+                pacer.reset();
             }
-            (0xD, _, _, _) => self.draw(x, y, n as usize),
-            _ => todo!("{:>4X?}", op),
         }
-    }
-
-    fn draw(&mut self, x: usize, y: usize, height: usize) -> bool {
-        let mut changed = false;
-        let coord_x = (self.var_registers[x] % WIDTH as u8) as usize;
-        let coord_y = (self.var_registers[y] % HEIGHT as u8) as usize;
-        for (i, row) in (coord_y..coord_y + height).enumerate() {
-            let sprite = self.memory[self.index_register + i];
-            for (j, col) in (coord_x..coord_x + 8).enumerate() {
-                if col < WIDTH {
-                    let pixel = &mut self.display[row][col];
-                    let sprite_pixel = 1 & (sprite >> (7 - j)) == 1;
-                    if sprite_pixel != *pixel {
-                        *pixel = !*pixel;
-                        changed = true;
-                        if !*pixel {
-                            self.var_registers[0xF] = 1;
+        if let Some(debugger) = debugger.as_mut() {
+            debugger.poll(&mut emulator);
+        }
+        // Frames of real time to emulate, one per iteration unless paced by
+        // vsync.
+        let due = pacer.as_mut().map_or(1, Pacer::due);
+        let advance = std::mem::take(&mut advance_frame);
+        let mut display_changed = false;
+        if rewinding {
+            if let Some(snapshot) = rewind.pop() {
+                emulator.restore(&snapshot);
+                display_changed = true;
+            }
+        } else if advance || !paused && !in_background {
+            // Whole frames are emulated while fast-forwarding, so timers,
+            // sound and replays advance at the same rate as instructions.
+            let frames = if turbo {
+                TURBO_FRAMES
+            } else if advance {
+                1
+            } else {
+                due
+            };
+            for _ in 0..frames {
+                if let Some(replay) = &mut player {
+                    while let Some(input) = replay.next_input(emulated_frame) {
+                        apply_input(&mut emulator, input, &mut osd, events.as_mut());
+                    }
+                    if replay.is_finished() {
+                        player = None;
+                        osd.show("Replay finished");
+                    }
+                }
+                for _ in 0..emulator.frame_instructions() {
+                    if emulator.is_waiting() {
+                        break;
+                    }
+                    if !debugger.as_mut().is_none_or(|d| d.can_run(&emulator)) {
+                        break;
+                    }
+                    if let Some(Err(e)) = script.as_mut().map(|s| s.on_instruction(&mut emulator)) {
+                        let message = format!("The script stopped: {}", e);
+                        show_error(canvas.window(), &message);
+                        paused = true;
+                        break;
+                    }
+                    let tracer = tracer.as_mut().filter(|_| tracing);
+                    match step(
+                        &mut emulator,
+                        &mut crash_log,
+                        tracer,
+                        profiler.as_mut(),
+                        events.as_mut(),
+                    ) {
+                        Ok(changed) => {
+                            display_changed |= changed;
+                            stats.record_instruction();
                         }
+                        Err(e) if recover(&mut emulator, &e, config.unknown_opcode) => {}
+                        Err(e) => {
+                            let stopped =
+                                stop(&emulator, &e, &config, &mut debugger, &args, &crash_log);
+                            if let Some(message) = stopped {
+                                show_error(canvas.window(), &message);
+                                paused = true;
+                            }
+                            break;
+                        }
+                    }
+                }
+                if debugger.as_ref().is_none_or(|d| !d.is_paused()) {
+                    emulator.tick_timers();
+                    if let Some(events) = &mut events {
+                        events.tick(&emulator);
+                    }
+                    stats.record_tick();
+                    rewind.push(emulator.snapshot());
+                    emulated_frame += 1;
+                    if let Some(Err(e)) = script
+                        .as_mut()
+                        .map(|s| s.on_frame(&mut emulator, emulated_frame))
+                    {
+                        let message = format!("The script stopped: {}", e);
+                        show_error(canvas.window(), &message);
+                        paused = true;
+                    }
+                }
+                if paused || debugger.as_ref().is_some_and(|d| d.is_paused()) {
+                    break;
+                }
+            }
+        } else if std::mem::take(&mut step_once) {
+            let tracer = tracer.as_mut().filter(|_| tracing);
+            match step(
+                &mut emulator,
+                &mut crash_log,
+                tracer,
+                profiler.as_mut(),
+                events.as_mut(),
+            ) {
+                Ok(changed) => {
+                    display_changed = changed;
+                    stats.record_instruction();
+                }
+                Err(e) if recover(&mut emulator, &e, config.unknown_opcode) => {}
+                Err(e) => {
+                    let stopped = stop(&emulator, &e, &config, &mut debugger, &args, &crash_log);
+                    if let Some(message) = stopped {
+                        show_error(canvas.window(), &message);
+                        paused = true;
                     }
                 }
             }
         }
+        flags.save(&emulator);
+        // The timers don't tick while a debugger is paused, so neither does
+        // the buzzer sound.
+        let sound = !paused
+            && !in_background
+            && debugger.as_ref().is_none_or(|d| !d.is_paused())
+            && emulator.sound_timer > 0;
+        if let Some(beeper) = &beeper {
+            beeper.set_playing(sound);
+        }
+        if let Some(gamepads) = &mut gamepads {
+            gamepads.set_rumble(sound);
+        }
+        if let Some(recorder) = &recorder {
+            if display_changed {
+                recorder.capture(&emulator.display, frame);
+            }
+        }
+        phosphor.update(&emulator.display);
+        // With vsync every refresh is presented, which needs a full redraw.
+        // The indicator is drawn over the display, so it's erased by
+        // drawing the display again.
+        let beep = sound && config.audio.visual_beep != VisualBeep::Off;
+        if osd.update() || overlay.visible || keypad_overlay || pacer.is_some() || beep || beeped {
+            screen.invalidate();
+        }
+        beeped = beep;
+        let drawn = screen.draw(
+            &mut canvas,
+            phosphor.intensity(),
+            phosphor.dirty_rows(),
+            viewport,
+            &palette,
+        );
+        // Leave the window as it is when nothing changed.
+        if drawn {
+            if keypad_overlay {
+                overlay::draw_keypad(&mut canvas, &emulator, &keymap, &palette);
+            }
+            if beep {
+                osd::draw_beep(&mut canvas, config.audio.visual_beep, &palette);
+            }
+            osd.draw(&mut canvas, &palette);
+            match overlay.draw(&mut canvas, &emulator, paused) {
+                Some(Button::Pause) => {
+                    paused = !paused;
+                    osd.show(if paused { "Paused" } else { "Resumed" });
+                }
+                Some(Button::Step) => {
+                    paused = true;
+                    step_once = true;
+                }
+                None => {}
+            }
+            canvas.present();
+        }
+        stats.record_frame();
+        if stats.update() && stats.visible {
+            let title = format!("{} - {}", TITLE, stats);
+            canvas.window_mut().set_title(&title).unwrap();
+        }
+        if let Some(debug_window) = &mut debug_window {
+            debug_window.draw(&emulator, &palette);
+        }
+        frame += u64::from(due);
 
-        changed
+        if pacer.is_some() {
+            // Presenting waited for the refresh.
+            continue;
+        }
+        next_frame += frame_duration;
+        let now = Instant::now();
+        if next_frame > now && !turbo {
+            std::thread::sleep(next_frame - now);
+        } else {
+            // Running behind or fast-forwarding, skip the missed frames
+            // instead of catching up.
+            next_frame = now;
+        }
     }
 
-    fn debug_display(&self) {
-        print!("{}[2J", 27 as char); // clear screen
-        for r in 0..HEIGHT {
-            print!("[{:0>2}]: ", r);
-            for c in 0..WIDTH {
-                print!("{}", if self.display[r][c] { '#' } else { ' ' });
-            }
-            print!("\n");
+    if let Some(recording) = recorder.take() {
+        stop_recording(recording, frame, &mut osd);
+    }
+    if let Some((path, replay)) = &recording {
+        match replay.save(path) {
+            Ok(()) => println!("Saved replay to {}", path.display()),
+            Err(e) => eprintln!("Failed to save replay: {}", e),
         }
     }
+    args.emulation
+        .report(profiler.as_ref(), &rom, emulator.load_address());
 }
 
-fn main() {
-    let rom = std::fs::read("rom/br8kout.ch8").unwrap();
-    let mut emulator = Emulator::new();
+/// Loads the database at `path`, exiting when it fails to load.
+fn load_database(path: &Path) -> Database {
+    Database::load(path).unwrap_or_else(|e| {
+        eprintln!("Failed to load database {}: {}", path.display(), e);
+        std::process::exit(1);
+    })
+}
 
-    emulator.load_rom(rom);
+/// Boots the ROM at `path`, exiting when it fails to load.
+fn load_game(path: &Path, config: &Config) -> (Emulator, Vec<u8>, RomConfig) {
+    let path = &fetch_rom(path);
+    boot(path, config).unwrap_or_else(|e| {
+        eprintln!("Failed to load {}: {}", path.display(), e);
+        std::process::exit(1);
+    })
+}
 
-    let sdl_context = sdl2::init().unwrap();
-    let mut canvas = create_canvas(
-        &sdl_context,
-        WIDTH as u32 * BLOCK_SIZE,
-        HEIGHT as u32 * BLOCK_SIZE,
-    )
-    .unwrap();
-    let mut event_pump = sdl_context.event_pump().unwrap();
+/// Downloads the ROM when `path` is a URL, returning the path it was cached
+/// at, and returns other paths as they are. Exits when the download fails.
+fn fetch_rom(path: &Path) -> PathBuf {
+    let url = path
+        .to_str()
+        .filter(|path| path.starts_with("http://") || path.starts_with("https://"));
+    let Some(url) = url else {
+        return path.to_path_buf();
+    };
+    #[cfg(feature = "net")]
+    return fetch::fetch(url).unwrap_or_else(|e| {
+        eprintln!("Failed to download {}: {}", url, e);
+        std::process::exit(1);
+    });
+    #[cfg(not(feature = "net"))]
+    {
+        eprintln!("Can't download {}: built without the net feature", url);
+        std::process::exit(1);
+    }
+}
+
+/// Loads the label file at `path`, exiting when it's invalid.
+fn load_labels(path: &Path) -> Labels {
+    std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| Labels::parse(&text))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to load labels {}: {}", path.display(), e);
+            std::process::exit(1);
+        })
+}
 
+/// Loads the script at `path`, exiting when it fails to compile or run.
+fn load_script(path: &Path, emulator: &mut Emulator) -> Script {
+    Script::load(path, emulator).unwrap_or_else(|e| {
+        eprintln!("Failed to load script {}: {}", path.display(), e);
+        std::process::exit(1);
+    })
+}
+
+/// Executes the instruction at PC, logging it for crash dumps and tracing
+/// and profiling it when enabled.
+fn step(
+    emulator: &mut Emulator,
+    crash_log: &mut CrashLog,
+    tracer: Option<&mut Tracer>,
+    profiler: Option<&mut Profiler>,
+    events: Option<&mut EventLog>,
+) -> Result<bool, Chip8Error> {
+    let pc = emulator.pc;
+    let opcode = emulator.current_opcode();
+    let read = match emulator.memory_access() {
+        Some(Access::Read(range)) if profiler.is_some() => Some(range),
+        _ => None,
+    };
+    crash_log.record(emulator);
+    let step = |emulator: &mut Emulator| match tracer {
+        Some(tracer) => tracer.step(emulator),
+        None => emulator.step(),
+    };
+    let result = match events {
+        Some(events) => events.step(emulator, step),
+        None => step(emulator),
+    };
+    if let (Some(profiler), Ok(opcode), Ok(_)) = (profiler, opcode, &result) {
+        profiler.record(pc, opcode, emulator.pc, read);
+    }
+    result
+}
+
+/// Carries out `policy` for an unknown opcode `step` failed with, returning
+/// whether execution continues after it. When breaking into the debugger,
+/// PC is moved back to the opcode.
+fn recover(emulator: &mut Emulator, error: &Chip8Error, policy: UnknownOpcode) -> bool {
+    let Chip8Error::UnknownOpcode { opcode, address } = *error else {
+        return false;
+    };
+    match policy {
+        UnknownOpcode::Halt => false,
+        UnknownOpcode::Skip => {
+            eprintln!("Skipped unknown opcode {:04X} at {:#05X}", opcode, address);
+            emulator.pc = address + 2;
+            true
+        }
+        UnknownOpcode::Break => {
+            emulator.pc = address;
+            false
+        }
+    }
+}
+
+/// Stops execution after an instruction failed with `error` and couldn't be
+/// recovered from: in the attached debugger, or one attached for unknown
+/// opcodes when configured to break on them. Without a debugger, returns
+/// the message to show with the crash report.
+fn stop(
+    emulator: &Emulator,
+    error: &Chip8Error,
+    config: &Config,
+    debugger: &mut Option<Box<dyn Control>>,
+    args: &RunArgs,
+    crash_log: &CrashLog,
+) -> Option<String> {
+    let breaks = config.unknown_opcode == UnknownOpcode::Break
+        && matches!(error, Chip8Error::UnknownOpcode { .. });
+    if breaks && debugger.is_none() {
+        *debugger = Some(Box::new(Debugger::new(args.emulation.labels())));
+    }
+    match debugger {
+        Some(debugger) => {
+            debugger.stop(emulator, error);
+            None
+        }
+        None => Some(format!(
+            "The emulator stopped: {}",
+            crash_log.report(emulator, error)
+        )),
+    }
+}
+
+/// Applies the configuration to a newly loaded ROM, with the settings the
+/// database lists for it taking precedence, or when enabled the quirks
+/// guessed from its code, and the ROM's own settings over all of them.
+fn configure(
+    emulator: &mut Emulator,
+    palette: &mut Palette,
+    config: &Config,
+    database: Option<&Database>,
+    rom: &[u8],
+    rom_config: &RomConfig,
+) {
+    emulator.set_speed(config.speed);
+    emulator.set_timing(config.timing);
+    emulator.set_stack_limit(config.stack_limit);
+    emulator.quirks = config.quirks;
+    emulator.latch_keys = config.latch_keys;
+    if config.protect_interpreter {
+        emulator.memory.protect_below(emulator.load_address());
+    }
+    *palette = config.palette();
+    match database.and_then(|database| database.lookup(rom)) {
+        Some(settings) => {
+            println!("Recognized {}", settings.title);
+            if let Some(speed) = settings.speed {
+                emulator.set_speed(speed);
+            }
+            emulator.quirks = settings.quirks;
+            palette.colors[0] = settings.background.unwrap_or(palette.colors[0]);
+            palette.colors[1] = settings.foreground.unwrap_or(palette.colors[1]);
+        }
+        None if config.guess_quirks => {
+            let load_address = emulator.load_address();
+            let (quirks, reasons) = analyze::guess_quirks(rom, load_address, emulator.quirks);
+            for reason in reasons {
+                println!("Guessed {}", reason);
+            }
+            emulator.quirks = quirks;
+        }
+        None => {}
+    }
+    rom_config.apply(emulator, palette);
+}
+
+/// Creates an emulator with the ROM at `path` loaded, returning it along
+/// with the ROM and its own settings.
+fn boot(path: &Path, config: &Config) -> Result<(Emulator, Vec<u8>, RomConfig), String> {
+    let rom = archive::read_rom(path)?;
+    let rom_config = config.rom_config(path, &rom)?;
+    let load_address = rom_config.load_address.unwrap_or(config.load_address);
+    let mut emulator = Emulator::new();
+    if let Some(path) = &config.font {
+        let font = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        emulator
+            .load_font(&font)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    emulator
+        .load_rom_at(&rom, load_address)
+        .map_err(|e| e.to_string())?;
+    Ok((emulator, rom, rom_config))
+}
+
+/// Shows the launcher until a ROM is picked and boots, or returns `None`
+/// when the user quits instead.
+fn launch(
+    canvas: &mut Canvas<Window>,
+    event_pump: &mut EventPump,
+    palette: &Palette,
+    config: &Config,
+) -> Option<(Emulator, Vec<u8>, RomConfig)> {
     loop {
-        for _ in event_pump.poll_iter() {
-            // Do something
+        let path = launcher::run(canvas, event_pump, palette, &config.rom_dir)?;
+        match boot(&path, config) {
+            Ok(game) => {
+                println!("Loaded {}", path.display());
+                return Some(game);
+            }
+            Err(e) => {
+                let message = format!("Failed to load {}: {}", path.display(), e);
+                show_error(canvas.window(), &message);
+            }
+        }
+    }
+}
+
+/// Applies input which affects emulation, live or from a replay.
+fn apply_input(
+    emulator: &mut Emulator,
+    input: Input,
+    osd: &mut Osd,
+    events: Option<&mut EventLog>,
+) {
+    match input {
+        Input::Key { key, pressed } => {
+            if let Some(events) = events {
+                events.key(key, pressed);
+            }
+            emulator.set_key(key, pressed);
+        }
+        Input::Speed(speed) => {
+            emulator.set_speed(speed);
+            osd.show(format!("Speed: {} IPS", emulator.speed()));
         }
-        if emulator.execute_current() {
-            // emulator.debug_display();
-            draw_canvas(&mut canvas, &emulator.display);
+        Input::Reset => {
+            emulator.reset();
+            // Reseeded so that replays stay deterministic across resets.
+            emulator.reseed(emulator.seed());
+            osd.show("Reset");
         }
-        std::thread::sleep(std::time::Duration::from_millis(10));
     }
 }
 
-fn create_canvas(sdl_context: &Sdl, width: u32, height: u32) -> Result<Canvas<Window>, String> {
+/// Reports what's suspicious about a newly loaded ROM on stderr and in a
+/// dialog.
+fn check_rom(window: &Window, rom: &[u8], load_address: usize) {
+    let diagnostics = analyze::analyze(rom, load_address);
+    if diagnostics.is_empty() {
+        return;
+    }
+    let mut lines: Vec<String> = diagnostics
+        .iter()
+        .take(MAX_WARNINGS)
+        .map(|diagnostic| format!("Warning: {}", diagnostic))
+        .collect();
+    if diagnostics.len() > MAX_WARNINGS {
+        lines.push(format!("and {} more", diagnostics.len() - MAX_WARNINGS));
+    }
+    let message = lines.join("\n");
+    eprintln!("{}", message);
+    let _ = show_simple_message_box(MessageBoxFlag::WARNING, "CHIP-8 warning", &message, window);
+}
+
+/// Reports an error on stderr and in a dialog.
+fn show_error(window: &Window, message: &str) {
+    eprintln!("{}", message);
+    let _ = show_simple_message_box(MessageBoxFlag::ERROR, "CHIP-8 error", message, window);
+}
+
+fn stop_recording(recorder: Recorder, frame: u64, osd: &mut Osd) {
+    match recorder.stop(frame) {
+        Ok(path) => osd.show(format!("Saved recording to {}", path.display())),
+        Err(e) => eprintln!("Failed to save recording: {}", e),
+    }
+}
+
+fn create_canvas(
+    sdl_context: &Sdl,
+    width: u32,
+    height: u32,
+    vsync: bool,
+) -> Result<Canvas<Window>, String> {
     let video_subsystem = sdl_context.video()?;
 
     let window = video_subsystem
-        .window("CHIP-8 emulator!", width, height)
+        .window(TITLE, width, height)
         .position_centered()
+        .resizable()
         .opengl()
+        // Render at the display's full resolution on Retina and scaled
+        // Wayland outputs, which would otherwise upscale a blurry image.
+        .allow_highdpi()
         .build()
         .map_err(|e| e.to_string())?;
 
-    let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+    // Scale the display texture without smoothing so pixels stay sharp.
+    sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "nearest");
+    // The debug overlay paints with OpenGL into the renderer's context.
+    sdl2::hint::set("SDL_RENDER_DRIVER", "opengl");
+    let builder = window.into_canvas();
+    let builder = if vsync {
+        builder.present_vsync()
+    } else {
+        builder
+    };
+    let mut canvas = builder.build().map_err(|e| e.to_string())?;
+    canvas
+        .window_mut()
+        .set_minimum_size(WIDTH as u32, HEIGHT as u32)
+        .map_err(|e| e.to_string())?;
     Ok(canvas)
 }
 
-fn draw_canvas(canvas: &mut Canvas<Window>, pixels: &[[bool; WIDTH]; HEIGHT]) {
-    canvas.set_draw_color(Color::RGB(0, 0, 0));
-    canvas.clear();
-    canvas.set_draw_color(Color::RGB(255, 255, 255));
-    for (i, row) in pixels.iter().enumerate() {
-        for (j, col) in row.iter().enumerate() {
-            if *col {
-                let rect = Rect::new(
-                    (BLOCK_SIZE * j as u32) as i32,
-                    (BLOCK_SIZE * i as u32) as i32,
-                    BLOCK_SIZE,
-                    BLOCK_SIZE,
-                );
-                canvas.fill_rect(rect).unwrap();
-            }
-        }
+/// Switches the window between windowed and fullscreen desktop mode.
+fn toggle_fullscreen(window: &mut Window) {
+    let mode = match window.fullscreen_state() {
+        FullscreenType::Off => FullscreenType::Desktop,
+        _ => FullscreenType::Off,
+    };
+    if let Err(e) = window.set_fullscreen(mode) {
+        eprintln!("Failed to toggle fullscreen: {}", e);
     }
-    canvas.present();
 }
@@ -0,0 +1,74 @@
+use core::ops::{Deref, DerefMut};
+
+use crate::error::Chip8Error;
+use crate::MEMORY_SIZE;
+
+/// The machine's memory. Instructions access it through [`Memory::read`] and
+/// [`Memory::write`], which bounds-check every access and can keep programs
+/// from overwriting the interpreter's area. Loaders and debuggers index it
+/// directly as a slice.
+#[derive(Clone, Copy)]
+pub struct Memory {
+    bytes: [u8; MEMORY_SIZE],
+    /// Writes through [`Memory::write`] below this address fail.
+    protected: usize,
+}
+
+impl Memory {
+    pub fn new() -> Memory {
+        Memory {
+            bytes: [0; MEMORY_SIZE],
+            protected: 0,
+        }
+    }
+
+    /// Makes instructions fail to write below `address`, or with 0 lets
+    /// them write anywhere.
+    pub fn protect_below(&mut self, address: usize) {
+        self.protected = address;
+    }
+
+    /// Returns the address writes are protected below.
+    pub fn protected(&self) -> usize {
+        self.protected
+    }
+
+    /// Returns `len` bytes from `address`.
+    pub fn read(&self, address: usize, len: usize) -> Result<&[u8], Chip8Error> {
+        address
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(address..end))
+            .ok_or(Chip8Error::MemoryOutOfBounds { address })
+    }
+
+    /// Returns `len` bytes from `address` for writing.
+    pub fn write(&mut self, address: usize, len: usize) -> Result<&mut [u8], Chip8Error> {
+        if address < self.protected {
+            return Err(Chip8Error::WriteProtected { address });
+        }
+        address
+            .checked_add(len)
+            .and_then(|end| self.bytes.get_mut(address..end))
+            .ok_or(Chip8Error::MemoryOutOfBounds { address })
+    }
+}
+
+impl Default for Memory {
+    fn default() -> Memory {
+        Memory::new()
+    }
+}
+
+impl Deref for Memory {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl DerefMut for Memory {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use chip8::palette::{Palette, Rgb};
+use clap::ValueEnum;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+use serde::Deserialize;
+
+use crate::text::{draw_text, GLYPH_WIDTH, LINE_HEIGHT};
+
+/// How long each message stays on screen.
+const DURATION: Duration = Duration::from_millis(1500);
+/// Messages shown at once, the oldest ones making way for new ones.
+const MAX_MESSAGES: usize = 4;
+/// Speaker the icon beep indicator shows, most significant bit left.
+const SPEAKER: [u8; 7] = [
+    0b0001001, 0b0011010, 0b1111001, 0b1111011, 0b1111001, 0b0011010, 0b0001001,
+];
+
+/// How the buzzer is shown on screen while it sounds, for players who can't
+/// hear it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum VisualBeep {
+    #[default]
+    Off,
+    /// A frame around the window.
+    Border,
+    /// A speaker in the top right corner.
+    Icon,
+}
+
+/// Short messages about emulator events shown over the game for a moment,
+/// such as "Paused" or the new speed.
+pub struct Osd {
+    /// Messages along with when they stop being shown, oldest first.
+    messages: VecDeque<(String, Instant)>,
+    /// Set when a message was added or dropped since the last update.
+    changed: bool,
+}
+
+impl Osd {
+    pub fn new() -> Osd {
+        Osd {
+            messages: VecDeque::new(),
+            changed: false,
+        }
+    }
+
+    /// Prints `message` and shows it under the ones already shown.
+    pub fn show(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        println!("{}", message);
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages
+            .push_back((message, Instant::now() + DURATION));
+        self.changed = true;
+    }
+
+    /// Drops the messages which were shown long enough. Returns whether the
+    /// messages have to be drawn, or erased after the last one was dropped.
+    pub fn update(&mut self) -> bool {
+        let now = Instant::now();
+        let count = self.messages.len();
+        self.messages.retain(|&(_, until)| until > now);
+        let changed = std::mem::take(&mut self.changed) || self.messages.len() != count;
+        changed || !self.messages.is_empty()
+    }
+
+    /// Draws the messages in the top left corner over whatever is on the
+    /// canvas, without presenting.
+    pub fn draw(&self, canvas: &mut Canvas<Window>, palette: &Palette) {
+        let (width, height) = canvas.output_size().unwrap();
+        // Twice the launcher's font size, to be readable over the game.
+        let px = (height as i32 / 80).max(1);
+        let columns = (width as i32 / (GLYPH_WIDTH * px) - 2).max(0) as usize;
+        for (i, (message, _)) in self.messages.iter().enumerate() {
+            let y = px + i as i32 * LINE_HEIGHT * px;
+            let length = message.chars().count().min(columns) as i32;
+            let Rgb(r, g, b) = palette.background();
+            canvas.set_blend_mode(BlendMode::Blend);
+            canvas.set_draw_color(Color::RGBA(r, g, b, 0xD0));
+            let background = Rect::new(
+                0,
+                y - px,
+                ((length + 2) * GLYPH_WIDTH * px) as u32,
+                (LINE_HEIGHT * px) as u32,
+            );
+            canvas.fill_rect(background).unwrap();
+            canvas.set_blend_mode(BlendMode::None);
+            let Rgb(r, g, b) = palette.foreground();
+            canvas.set_draw_color(Color::RGB(r, g, b));
+            draw_text(canvas, GLYPH_WIDTH * px, y, px, message, columns);
+        }
+    }
+}
+
+/// Draws `style`'s indicator of the buzzer sounding over whatever is on the
+/// canvas, without presenting.
+pub fn draw_beep(canvas: &mut Canvas<Window>, style: VisualBeep, palette: &Palette) {
+    let (width, height) = canvas.output_size().unwrap();
+    let px = (height as i32 / 80).max(1);
+    let Rgb(r, g, b) = palette.foreground();
+    canvas.set_draw_color(Color::RGB(r, g, b));
+    match style {
+        VisualBeep::Off => {}
+        VisualBeep::Border => {
+            let (width, height) = (width as i32, height as i32);
+            for rect in [
+                Rect::new(0, 0, width as u32, px as u32),
+                Rect::new(0, height - px, width as u32, px as u32),
+                Rect::new(0, 0, px as u32, height as u32),
+                Rect::new(width - px, 0, px as u32, height as u32),
+            ] {
+                canvas.fill_rect(rect).unwrap();
+            }
+        }
+        VisualBeep::Icon => {
+            let left = width as i32 - 9 * px;
+            for (row, bits) in SPEAKER.iter().enumerate() {
+                for col in 0..7 {
+                    if bits & (0b1000000 >> col) != 0 {
+                        let y = px + row as i32 * px;
+                        let rect = Rect::new(left + col * px, y, px as u32, px as u32);
+                        canvas.fill_rect(rect).unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::time::Instant;
+
+use chip8::disasm;
+use chip8::palette::{Palette, Rgb};
+use chip8::Emulator;
+use egui_sdl2_gl::egui::{self, RichText};
+use egui_sdl2_gl::painter::Painter;
+use egui_sdl2_gl::{DpiScaling, EguiStateHandler, ShaderVersion};
+use sdl2::event::Event;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+
+use crate::keymap::Keymap;
+use crate::text::{draw_text, GLYPH_WIDTH, LINE_HEIGHT};
+
+/// Instructions shown before and after the one at PC.
+const DISASM_CONTEXT: usize = 3;
+/// Width of a key of the keypad overlay, in characters.
+const KEY_COLUMNS: i32 = 6;
+/// Column the second column of the overlay starts at, in characters.
+const RIGHT_COLUMN: i32 = 30;
+/// Keypad keys in the layout of the COSMAC VIP keypad.
+const KEYPAD: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Pause,
+    Step,
+}
+
+/// Debug overlay drawn over the display with egui, showing the registers,
+/// the code around PC, the stack and the keypad, with buttons to pause and
+/// step.
+pub struct Overlay {
+    pub visible: bool,
+    context: egui::Context,
+    painter: Painter,
+    state: EguiStateHandler,
+    start: Instant,
+}
+
+impl Overlay {
+    /// Creates the overlay for `window`, whose canvas must render with
+    /// OpenGL, as egui paints into the same context.
+    pub fn new(window: &Window) -> Overlay {
+        let (painter, state) =
+            egui_sdl2_gl::with_sdl2(window, ShaderVersion::Adaptive, DpiScaling::Default);
+        Overlay {
+            visible: false,
+            context: egui::Context::default(),
+            painter,
+            state,
+            start: Instant::now(),
+        }
+    }
+
+    /// Passes `event` on to egui. Input is only collected while the overlay
+    /// is visible, but window events always are, to keep track of its size.
+    pub fn handle_event(&mut self, window: &Window, event: &Event) {
+        if self.visible || matches!(event, Event::Window { .. }) {
+            self.state
+                .process_input(window, event.clone(), &mut self.painter);
+        }
+    }
+
+    /// Draws the overlay over whatever is on the canvas, without presenting.
+    /// Returns the button clicked since the last frame, if any.
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        emulator: &Emulator,
+        paused: bool,
+    ) -> Option<Button> {
+        if !self.visible {
+            return None;
+        }
+        self.state.input.time = Some(self.start.elapsed().as_secs_f64());
+        self.context.begin_frame(self.state.input.take());
+        let mut clicked = None;
+        egui::Window::new("Debug")
+            .resizable(false)
+            .show(&self.context, |ui| {
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        for line in registers(emulator) {
+                            ui.monospace(line);
+                        }
+                        ui.separator();
+                        for line in code(emulator) {
+                            ui.monospace(line);
+                        }
+                    });
+                    ui.separator();
+                    ui.vertical(|ui| {
+                        ui.strong("Stack");
+                        if emulator.stack.is_empty() {
+                            ui.monospace("empty");
+                        }
+                        for addr in emulator.stack.iter().rev().take(4) {
+                            ui.monospace(format!("{:#05X}", addr));
+                        }
+                        ui.separator();
+                        ui.strong("Keypad");
+                        egui::Grid::new("keypad").show(ui, |ui| {
+                            for row in KEYPAD {
+                                for key in row {
+                                    let label = RichText::new(format!("{:X}", key)).monospace();
+                                    ui.selectable_label(emulator.keys[key as usize], label);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button(if paused { "Run" } else { "Pause" }).clicked() {
+                        clicked = Some(Button::Pause);
+                    }
+                    if ui.button("Step").clicked() {
+                        clicked = Some(Button::Step);
+                    }
+                });
+            });
+        let output = self.context.end_frame();
+        self.state
+            .process_output(canvas.window(), &output.platform_output);
+        let jobs = self.context.tessellate(output.shapes);
+        // SDL batches what it draws, so it must be flushed before painting
+        // over it directly.
+        unsafe {
+            sdl2::sys::SDL_RenderFlush(canvas.raw());
+        }
+        self.painter.paint_jobs(None, output.textures_delta, jobs);
+        clicked
+    }
+}
+
+/// Draws the keypad in the bottom right corner over whatever is on the
+/// canvas, without presenting, with the keys held highlighted and each
+/// labeled with the keyboard key it's bound to.
+pub fn draw_keypad(
+    canvas: &mut Canvas<Window>,
+    emulator: &Emulator,
+    keymap: &Keymap,
+    palette: &Palette,
+) {
+    let (width, height) = canvas.output_size().unwrap();
+    // Same font size as the launcher.
+    let px = (height as i32 / 160).max(1);
+    let (key_width, key_height) = (KEY_COLUMNS * GLYPH_WIDTH * px, 2 * LINE_HEIGHT * px + px);
+    let left = width as i32 - 4 * key_width - GLYPH_WIDTH * px;
+    let top = height as i32 - 4 * key_height - GLYPH_WIDTH * px;
+    let Rgb(r, g, b) = palette.background();
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(r, g, b, 0xD0));
+    let background = Rect::new(left, top, 4 * key_width as u32, 4 * key_height as u32);
+    canvas.fill_rect(background).unwrap();
+    canvas.set_blend_mode(BlendMode::None);
+    for (i, row) in KEYPAD.iter().enumerate() {
+        for (j, &key) in row.iter().enumerate() {
+            let x = left + j as i32 * key_width;
+            let y = top + i as i32 * key_height;
+            set_color(canvas, palette.foreground());
+            if emulator.keys[key as usize] {
+                let cell = Rect::new(x, y, key_width as u32, key_height as u32);
+                canvas.fill_rect(cell).unwrap();
+                set_color(canvas, palette.background());
+            }
+            let columns = KEY_COLUMNS as usize - 1;
+            let name = keymap.key_name(key).unwrap_or_default();
+            draw_text(canvas, x + px, y + px, px, &format!("{:X}", key), columns);
+            draw_text(
+                canvas,
+                x + px,
+                y + px + LINE_HEIGHT * px,
+                px,
+                &name,
+                columns,
+            );
+        }
+    }
+}
+
+fn set_color(canvas: &mut Canvas<Window>, Rgb(r, g, b): Rgb) {
+    canvas.set_draw_color(Color::RGB(r, g, b));
+}
+
+/// Draws the registers and code in a left column, and the stack and keypad
+/// in a right one, at `px` canvas pixels per font pixel and cut off after
+/// `columns` characters. Returns the number of lines in the left column.
+pub fn draw_state(
+    canvas: &mut Canvas<Window>,
+    emulator: &Emulator,
+    palette: &Palette,
+    px: i32,
+    columns: usize,
+) -> usize {
+    let left = GLYPH_WIDTH * px;
+    let right = left + RIGHT_COLUMN * GLYPH_WIDTH * px;
+    let line = |i: usize| px + i as i32 * LINE_HEIGHT * px;
+    set_color(canvas, palette.foreground());
+    let mut lines = registers(emulator);
+    lines.push(String::new());
+    lines.extend(code(emulator));
+    for (i, text) in lines.iter().enumerate() {
+        draw_text(canvas, left, line(i), px, text, columns);
+    }
+
+    let right_columns = columns.saturating_sub(RIGHT_COLUMN as usize + 1);
+    draw_text(canvas, right, line(0), px, "STACK", right_columns);
+    if emulator.stack.is_empty() {
+        draw_text(canvas, right, line(1), px, "EMPTY", right_columns);
+    }
+    for (i, addr) in emulator.stack.iter().rev().take(4).enumerate() {
+        let text = format!("{:#05X}", addr);
+        draw_text(canvas, right, line(i + 1), px, &text, right_columns);
+    }
+    draw_text(canvas, right, line(6), px, "KEYPAD", right_columns);
+    for (i, row) in KEYPAD.iter().enumerate() {
+        for (j, &key) in row.iter().enumerate() {
+            let x = right + 2 * j as i32 * GLYPH_WIDTH * px;
+            let y = line(i + 7);
+            if emulator.keys[key as usize] {
+                let cell = Rect::new(x - px, y - px, (5 * px) as u32, (7 * px) as u32);
+                canvas.fill_rect(cell).unwrap();
+                set_color(canvas, palette.background());
+            }
+            draw_text(canvas, x, y, px, &format!("{:X}", key), 1);
+            set_color(canvas, palette.foreground());
+        }
+    }
+    lines.len()
+}
+
+fn registers(emulator: &Emulator) -> Vec<String> {
+    let mut lines: Vec<String> = emulator
+        .var_registers
+        .chunks(4)
+        .enumerate()
+        .map(|(row, values)| {
+            let registers = values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| format!("V{:X} {:02X}", row * 4 + i, value));
+            registers.collect::<Vec<_>>().join("  ")
+        })
+        .collect();
+    lines.push(format!(
+        "I {:#05X}  PC {:#05X}",
+        emulator.index_register, emulator.pc
+    ));
+    lines.push(format!(
+        "SP {}  DT {:02X}  ST {:02X}",
+        emulator.stack.len(),
+        emulator.delay_timer,
+        emulator.sound_timer
+    ));
+    lines
+}
+
+/// Disassembles the instructions around PC, marking the one at PC.
+fn code(emulator: &Emulator) -> Vec<String> {
+    let pc = emulator.pc;
+    let start = pc.saturating_sub(2 * DISASM_CONTEXT);
+    let end = (pc + 2 * DISASM_CONTEXT + 2).min(emulator.memory.len());
+    let Some(bytes) = emulator.memory.get(start..end) else {
+        return Vec::new();
+    };
+    disasm::disassemble(bytes, start)
+        .map(|line| {
+            let marker = if line.address == pc { ">" } else { " " };
+            format!("{} {}", marker, line)
+        })
+        .collect()
+}
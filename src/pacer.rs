@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+
+use chip8::FRAME_RATE;
+
+/// Most frames emulated for one refresh. Longer gaps, such as while the
+/// window is dragged, are skipped instead of caught up on.
+const MAX_FRAMES: u32 = 4;
+
+/// Paces emulation to the display's refresh when presenting waits for vsync.
+/// The time each refresh took is measured, and the 60Hz frames which fell
+/// due in it are emulated, so that instructions and timers keep real time
+/// on displays of any refresh rate.
+pub struct Pacer {
+    last: Instant,
+    /// Time passed which is still short of a whole frame.
+    lag: Duration,
+}
+
+impl Pacer {
+    pub fn new() -> Pacer {
+        Pacer {
+            last: Instant::now(),
+            lag: Duration::ZERO,
+        }
+    }
+
+    /// Returns the number of frames to emulate for the refresh since the
+    /// last call.
+    pub fn due(&mut self) -> u32 {
+        let frame_duration = Duration::from_secs(1) / FRAME_RATE;
+        let now = Instant::now();
+        self.lag += now - self.last;
+        self.last = now;
+        let frames = (self.lag.as_nanos() / frame_duration.as_nanos()) as u32;
+        if frames > MAX_FRAMES {
+            self.lag = Duration::ZERO;
+            return 1;
+        }
+        self.lag -= frame_duration * frames;
+        frames
+    }
+
+    /// Forgets the time passed, after emulation was held up such as by the
+    /// launcher.
+    pub fn reset(&mut self) {
+        *self = Pacer::new();
+    }
+}
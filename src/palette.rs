@@ -0,0 +1,68 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// A color written as `#RRGGBB`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Rgb, String> {
+        let error = || format!("invalid color `{}`, expected #RRGGBB", s);
+        let hex = s.strip_prefix('#').ok_or_else(error)?;
+        if hex.len() != 6 {
+            return Err(error());
+        }
+        let rgb = u32::from_str_radix(hex, 16).map_err(|_| error())?;
+        Ok(Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+    }
+}
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Rgb, String> {
+        s.parse()
+    }
+}
+
+impl Rgb {
+    /// Blends towards `other` by `amount`, from 0 for `self` to 1 for `other`.
+    pub fn mix(self, other: Rgb, amount: f32) -> Rgb {
+        let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+        Rgb(
+            channel(self.0, other.0),
+            channel(self.1, other.1),
+            channel(self.2, other.2),
+        )
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.0, self.1, self.2)
+    }
+}
+
+/// Colors used to draw the display, indexed by the planes a pixel is set
+/// on: 0 for none, 1 for the first plane, 2 for the second plane and 3 for
+/// both. Only the first two are used until multi-plane variants are
+/// supported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [Rgb; 4],
+}
+
+impl Palette {
+    pub fn background(&self) -> Rgb {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> Rgb {
+        self.colors[1]
+    }
+}
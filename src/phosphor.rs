@@ -0,0 +1,58 @@
+use chip8::{HIRES_HEIGHT, WIDTH};
+
+/// Fraction of its brightness an erased pixel keeps each frame.
+const DECAY: f32 = 0.6;
+/// Brightness below which a fading pixel is treated as off.
+const CUTOFF: f32 = 0.05;
+
+/// Per-pixel brightness simulating the persistence of a CRT's phosphor, so
+/// that pixels erased by XOR drawing fade out over a few frames instead of
+/// flickering.
+pub struct Phosphor {
+    intensity: [[f32; WIDTH]; HIRES_HEIGHT],
+    /// Rows whose brightness changed in the last update.
+    dirty: [bool; HIRES_HEIGHT],
+    enabled: bool,
+}
+
+impl Phosphor {
+    /// When not `enabled`, pixels turn off as soon as they're erased.
+    pub fn new(enabled: bool) -> Phosphor {
+        Phosphor {
+            intensity: [[0.0; WIDTH]; HIRES_HEIGHT],
+            dirty: [true; HIRES_HEIGHT],
+            enabled,
+        }
+    }
+
+    /// Advances by a frame, lighting the pixels set in `display` and fading
+    /// the others.
+    pub fn update(&mut self, display: &[[bool; WIDTH]; HIRES_HEIGHT]) {
+        for ((intensities, pixels), dirty) in
+            self.intensity.iter_mut().zip(display).zip(&mut self.dirty)
+        {
+            *dirty = false;
+            for (intensity, &pixel) in intensities.iter_mut().zip(pixels) {
+                let new = if pixel {
+                    1.0
+                } else if self.enabled && *intensity * DECAY > CUTOFF {
+                    *intensity * DECAY
+                } else {
+                    0.0
+                };
+                *dirty |= new != *intensity;
+                *intensity = new;
+            }
+        }
+    }
+
+    /// Returns which rows changed brightness in the last update.
+    pub fn dirty_rows(&self) -> &[bool; HIRES_HEIGHT] {
+        &self.dirty
+    }
+
+    /// Returns the brightness of each pixel, from 0 for off to 1 for set.
+    pub fn intensity(&self) -> &[[f32; WIDTH]; HIRES_HEIGHT] {
+        &self.intensity
+    }
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::launcher;
+
+/// ROMs to cycle through with the next and previous ROM keys, such as for a
+/// kiosk showing one game after another.
+pub struct Playlist {
+    roms: Vec<PathBuf>,
+    current: usize,
+}
+
+impl Playlist {
+    /// Lists the ROMs in `dir`, or in the ZIP archive `dir`, as the launcher
+    /// does.
+    pub fn new(dir: &Path) -> Result<Playlist, String> {
+        let roms = launcher::find_roms(dir);
+        if roms.is_empty() {
+            return Err("no .ch8 ROMs in it".to_string());
+        }
+        Ok(Playlist { roms, current: 0 })
+    }
+
+    pub fn current(&self) -> &Path {
+        &self.roms[self.current]
+    }
+
+    /// Moves on to the next ROM, or back to the first after the last.
+    pub fn next(&mut self) -> &Path {
+        self.current = (self.current + 1) % self.roms.len();
+        self.current()
+    }
+
+    /// Moves back to the previous ROM, or on to the last from the first.
+    pub fn previous(&mut self) -> &Path {
+        self.current = (self.current + self.roms.len() - 1) % self.roms.len();
+        self.current()
+    }
+}
+
+/// The current ROM's file name and its position, like `pong.ch8 (3/12)`.
+impl fmt::Display for Playlist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.current().file_name().unwrap_or_default();
+        write!(
+            f,
+            "{} ({}/{})",
+            name.to_string_lossy(),
+            self.current + 1,
+            self.roms.len()
+        )
+    }
+}
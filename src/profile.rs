@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::ops::Range;
+
+use chip8::{decode, disasm, Instruction};
+
+/// Number of entries shown in each section of the report.
+const TOP: usize = 10;
+/// Bytes per line of the coverage map.
+const MAP_WIDTH: usize = 64;
+/// Coverage of a byte which was executed as part of an instruction.
+const CODE: u8 = 1;
+/// Coverage of a byte which was drawn as part of a sprite.
+const SPRITE: u8 = 2;
+
+/// Counts executed instructions per address and per opcode, and how often
+/// each backward jump is taken to find the hottest loops. It also notes
+/// which bytes were executed and which were drawn as sprites, to tell code
+/// from data.
+#[derive(Default)]
+pub struct Profiler {
+    addresses: HashMap<usize, (u16, u64)>,
+    /// `CODE` and `SPRITE` bits by address.
+    coverage: Vec<u8>,
+    opcodes: HashMap<&'static str, u64>,
+    /// Iterations per loop, keyed by its first and last address.
+    loops: HashMap<(usize, usize), u64>,
+    total: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    /// Records `opcode` executing at `pc`, after which execution continued
+    /// at `next`, and reading the memory in `read`, if any.
+    pub fn record(&mut self, pc: usize, opcode: u16, next: usize, read: Option<Range<usize>>) {
+        self.mark(pc..pc + 2, CODE);
+        if let Some(read) = read.filter(|_| opcode >> 12 == 0xD) {
+            self.mark(read, SPRITE);
+        }
+        self.addresses.entry(pc).or_insert((opcode, 0)).1 += 1;
+        *self.opcodes.entry(pattern(opcode)).or_insert(0) += 1;
+        if matches!(opcode >> 12, 0x1 | 0xB) && next <= pc {
+            *self.loops.entry((next, pc)).or_insert(0) += 1;
+        }
+        self.total += 1;
+    }
+
+    fn mark(&mut self, range: Range<usize>, kind: u8) {
+        if self.coverage.len() < range.end {
+            self.coverage.resize(range.end, 0);
+        }
+        for coverage in &mut self.coverage[range] {
+            *coverage |= kind;
+        }
+    }
+
+    /// Formats which bytes of `rom`, loaded at `load_address`, were executed
+    /// (`C`) or drawn as sprites (`S`), as a map of the whole ROM followed
+    /// by its disassembly annotated the same way.
+    pub fn coverage(&self, rom: &[u8], load_address: usize) -> String {
+        let marker = |address: usize| match self.coverage.get(address).copied().unwrap_or(0) {
+            0 => '.',
+            CODE => 'C',
+            SPRITE => 'S',
+            _ => 'B',
+        };
+        let covered = |kind: u8| {
+            let addresses = load_address..load_address + rom.len();
+            addresses
+                .filter(|&address| self.coverage.get(address).is_some_and(|c| c & kind != 0))
+                .count()
+        };
+        let mut s = format!(
+            "{} of {} bytes executed, {} drawn as sprites\n",
+            covered(CODE),
+            rom.len(),
+            covered(SPRITE)
+        );
+        s += "C executed, S drawn as a sprite, B both, . neither\n\n";
+        for start in (0..rom.len()).step_by(MAP_WIDTH) {
+            let end = (start + MAP_WIDTH).min(rom.len());
+            let row: String = (start..end).map(|i| marker(load_address + i)).collect();
+            writeln!(s, "{:#05X}  {}", load_address + start, row).unwrap();
+        }
+        s += "\n";
+        for line in disasm::disassemble(rom, load_address) {
+            let (a, b) = (marker(line.address), marker(line.address + 1));
+            writeln!(s, "{}{}  {}", a, b, line).unwrap();
+        }
+        s
+    }
+
+    /// Formats the hottest addresses and loops, and a histogram of all
+    /// executed opcodes.
+    pub fn report(&self) -> String {
+        let percent = |count: u64| 100.0 * count as f64 / self.total.max(1) as f64;
+        let mut s = format!("Executed {} instructions\n", self.total);
+
+        s += "\nHottest addresses:\n";
+        for (&address, &(opcode, count)) in top(&self.addresses, |&(_, count)| count) {
+            let line = disasm::Line { address, opcode };
+            writeln!(s, "{:>12} {:>5.1}%  {}", count, percent(count), line).unwrap();
+        }
+
+        s += "\nHottest loops:\n";
+        if self.loops.is_empty() {
+            s += "  none\n";
+        }
+        for (&(start, end), &count) in top(&self.loops, |&count| count) {
+            writeln!(
+                s,
+                "{:>12} iterations  {:#05X}-{:#05X}",
+                count,
+                start,
+                end + 1
+            )
+            .unwrap();
+        }
+
+        s += "\nOpcodes:\n";
+        let mut opcodes: Vec<_> = self.opcodes.iter().collect();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let max = opcodes.first().map_or(1, |(_, &count)| count);
+        for (pattern, &count) in opcodes {
+            let bar = "#".repeat((40 * count).div_ceil(max) as usize);
+            writeln!(
+                s,
+                "  {}  {:>12} {:>5.1}%  {}",
+                pattern,
+                count,
+                percent(count),
+                bar
+            )
+            .unwrap();
+        }
+        s
+    }
+}
+
+/// Returns the `TOP` entries of `map` with the highest counts.
+fn top<K: Ord, V>(map: &HashMap<K, V>, count: impl Fn(&V) -> u64) -> Vec<(&K, &V)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|a, b| count(b.1).cmp(&count(a.1)).then(a.0.cmp(b.0)));
+    entries.truncate(TOP);
+    entries
+}
+
+/// Returns the opcode pattern `op` belongs to, such as `6XNN`.
+fn pattern(op: u16) -> &'static str {
+    let Some(instruction) = decode(op) else {
+        return "????";
+    };
+    match instruction {
+        Instruction::Cls => "00E0",
+        Instruction::Sys(_) => "0NNN",
+        Instruction::Ret => "00EE",
+        Instruction::Jump(_) => "1NNN",
+        Instruction::Call(_) => "2NNN",
+        Instruction::SkipEqByte { .. } => "3XNN",
+        Instruction::SkipNeByte { .. } => "4XNN",
+        Instruction::SkipEq { .. } => "5XY0",
+        Instruction::LoadByte { .. } => "6XNN",
+        Instruction::AddByte { .. } => "7XNN",
+        Instruction::Load { .. } => "8XY0",
+        Instruction::Or { .. } => "8XY1",
+        Instruction::And { .. } => "8XY2",
+        Instruction::Xor { .. } => "8XY3",
+        Instruction::Add { .. } => "8XY4",
+        Instruction::Sub { .. } => "8XY5",
+        Instruction::ShiftRight { .. } => "8XY6",
+        Instruction::SubN { .. } => "8XY7",
+        Instruction::ShiftLeft { .. } => "8XYE",
+        Instruction::SkipNe { .. } => "9XY0",
+        Instruction::LoadI(_) => "ANNN",
+        Instruction::JumpOffset { .. } => "BNNN",
+        Instruction::Random { .. } => "CXNN",
+        Instruction::Draw { .. } => "DXYN",
+        Instruction::SkipKey { .. } => "EX9E",
+        Instruction::SkipNotKey { .. } => "EXA1",
+        Instruction::LoadDelay { .. } => "FX07",
+        Instruction::WaitKey { .. } => "FX0A",
+        Instruction::SetDelay { .. } => "FX15",
+        Instruction::SetSound { .. } => "FX18",
+        Instruction::AddI { .. } => "FX1E",
+        Instruction::Font { .. } => "FX29",
+        Instruction::BigFont { .. } => "FX30",
+        Instruction::Bcd { .. } => "FX33",
+        Instruction::Store { .. } => "FX55",
+        Instruction::Restore { .. } => "FX65",
+        Instruction::SaveFlags { .. } => "FX75",
+        Instruction::LoadFlags { .. } => "FX85",
+    }
+}
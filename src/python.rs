@@ -0,0 +1,117 @@
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::{Chip8Error, Emulator, FRAME_RATE, HEIGHT, WIDTH};
+
+fn error(e: Chip8Error) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+/// A CHIP-8 machine, for driving the interpreter from Python.
+#[pyclass(name = "Emulator")]
+struct PyEmulator {
+    emulator: Emulator,
+}
+
+#[pymethods]
+impl PyEmulator {
+    /// Creates an emulator whose CXNN results are determined by `seed`, or
+    /// random ones when it's omitted.
+    #[new]
+    #[pyo3(signature = (seed = None))]
+    fn new(seed: Option<u64>) -> PyEmulator {
+        let emulator = match seed {
+            Some(seed) => Emulator::with_seed(seed),
+            None => Emulator::new(),
+        };
+        PyEmulator { emulator }
+    }
+
+    /// Loads `rom` at 0x200.
+    fn load_rom(&mut self, rom: &[u8]) -> PyResult<()> {
+        self.emulator.load_rom(rom).map_err(error)?;
+        Ok(())
+    }
+
+    /// Restarts the loaded ROM, undoing its writes to memory.
+    fn reset(&mut self) {
+        self.emulator.reset();
+    }
+
+    /// Forgets the ROM, leaving the machine as `Emulator()` creates it.
+    fn hard_reset(&mut self) {
+        self.emulator.hard_reset();
+    }
+
+    /// Executes the instruction at PC, returning whether the display
+    /// changed.
+    fn step(&mut self) -> PyResult<bool> {
+        self.emulator.step().map_err(error)
+    }
+
+    /// Runs one frame with the keys currently held, returning whether the
+    /// display changed and whether the buzzer should sound.
+    fn run_frame(&mut self) -> PyResult<(bool, bool)> {
+        let keys = self.emulator.keys;
+        let frame = self.emulator.run_frame(&keys).map_err(error)?;
+        Ok((frame.display_changed, frame.sound))
+    }
+
+    fn set_key(&mut self, key: u8, pressed: bool) -> PyResult<()> {
+        if key >= 16 {
+            return Err(PyValueError::new_err(format!("invalid key {}", key)));
+        }
+        self.emulator.set_key(key, pressed);
+        Ok(())
+    }
+
+    /// Returns the display as `WIDTH * height` bytes, row by row, 1 for set
+    /// pixels. `numpy.frombuffer(data, numpy.uint8).reshape(height, WIDTH)`
+    /// turns it into an array.
+    fn framebuffer<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let pixels: Vec<u8> = self.emulator.display[..self.emulator.height()]
+            .iter()
+            .flatten()
+            .map(|&pixel| pixel as u8)
+            .collect();
+        PyBytes::new_bound(py, &pixels)
+    }
+
+    /// Rows of the display in use, `HEIGHT`, or 64 for hires ROMs.
+    #[getter]
+    fn height(&self) -> usize {
+        self.emulator.height()
+    }
+
+    #[getter]
+    fn pc(&self) -> usize {
+        self.emulator.pc
+    }
+
+    #[getter]
+    fn index(&self) -> usize {
+        self.emulator.index_register
+    }
+
+    /// V0 to VF.
+    #[getter]
+    fn registers(&self) -> [u8; 16] {
+        self.emulator.var_registers
+    }
+
+    #[getter]
+    fn sound_timer(&self) -> u8 {
+        self.emulator.sound_timer
+    }
+}
+
+/// The CHIP-8 interpreter core, built with the `python` feature.
+#[pymodule]
+fn chip8(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyEmulator>()?;
+    m.add("WIDTH", WIDTH)?;
+    m.add("HEIGHT", HEIGHT)?;
+    m.add("FRAME_RATE", FRAME_RATE)?;
+    Ok(())
+}
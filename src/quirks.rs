@@ -0,0 +1,144 @@
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// Behaviors which differ between CHIP-8 interpreters, that ROMs written
+/// for a particular one may rely on. All are off by default, which matches
+/// modern interpreters like Octo.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[serde(default, deny_unknown_fields)]
+pub struct Quirks {
+    /// DXYN waits for the next 60Hz tick after drawing, as the COSMAC VIP
+    /// waited for the vertical blank, limiting drawing to once per frame.
+    pub display_wait: bool,
+    /// 8XY1, 8XY2 and 8XY3 reset VF to 0.
+    pub vf_reset: bool,
+    /// FX55 and FX65 leave I pointing past the last register stored or
+    /// loaded.
+    pub memory_increment: bool,
+    /// 8XY6 and 8XYE shift VY into VX, instead of shifting VX in place.
+    pub shift_vy: bool,
+    /// BNNN jumps to NNN plus VX, where X is the highest nibble of NNN,
+    /// instead of plus V0.
+    pub jump_vx: bool,
+    /// FX0A registers a key as soon as it's pressed, instead of once it's
+    /// released like on the COSMAC VIP.
+    pub key_press: bool,
+    /// DXYN wraps the parts of sprites crossing the screen edges around to
+    /// the opposite edge, instead of clipping them.
+    pub wrap: bool,
+}
+
+impl Quirks {
+    /// Names of the quirks, as in configuration files.
+    pub const NAMES: [&'static str; 7] = [
+        "display_wait",
+        "vf_reset",
+        "memory_increment",
+        "shift_vy",
+        "jump_vx",
+        "key_press",
+        "wrap",
+    ];
+
+    /// Returns whether the quirk named `name` is on, spelled as for
+    /// [`Quirks::get_mut`].
+    pub fn get(&self, name: &str) -> Option<bool> {
+        let mut quirks = *self;
+        quirks.get_mut(name).copied()
+    }
+
+    /// Returns the quirk named `name`, spelled as in [`Quirks::NAMES`] or
+    /// with dashes like the command line flags.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut bool> {
+        let name = name.bytes().map(|b| if b == b'-' { b'_' } else { b });
+        let index = Self::NAMES
+            .iter()
+            .position(|n| n.bytes().eq(name.clone()))?;
+        Some(match index {
+            0 => &mut self.display_wait,
+            1 => &mut self.vf_reset,
+            2 => &mut self.memory_increment,
+            3 => &mut self.shift_vy,
+            4 => &mut self.jump_vx,
+            5 => &mut self.key_press,
+            _ => &mut self.wrap,
+        })
+    }
+}
+
+/// Interpreters whose quirks and typical speed can be selected together.
+///
+/// Only the quirks and speed differ: the SUPER-CHIP and XO-CHIP display,
+/// memory and instruction extensions are not emulated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    /// The original interpreter on the COSMAC VIP.
+    Chip8,
+    /// CHIP-48 on the HP-48 calculators.
+    Chip48,
+    /// SUPER-CHIP 1.1 on the HP-48 calculators.
+    Schip,
+    /// XO-CHIP, as implemented by Octo.
+    XoChip,
+}
+
+impl Platform {
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks {
+                display_wait: true,
+                vf_reset: true,
+                memory_increment: true,
+                shift_vy: true,
+                jump_vx: false,
+                key_press: false,
+                wrap: false,
+            },
+            // CHIP-48 incremented I by X rather than X + 1 in FX55 and
+            // FX65, which is closest to leaving it unchanged.
+            Platform::Chip48 | Platform::Schip => Quirks {
+                jump_vx: true,
+                ..Quirks::default()
+            },
+            Platform::XoChip => Quirks {
+                memory_increment: true,
+                shift_vy: true,
+                wrap: true,
+                ..Quirks::default()
+            },
+        }
+    }
+
+    /// Speed in instructions per second ROMs for the platform usually
+    /// expect.
+    pub fn speed(self) -> u32 {
+        match self {
+            Platform::Chip8 => 700,
+            Platform::Chip48 => 1000,
+            Platform::Schip => 1800,
+            Platform::XoChip => 6000,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Platform, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "chip8" => Ok(Platform::Chip8),
+            "chip48" => Ok(Platform::Chip48),
+            "schip" => Ok(Platform::Schip),
+            "xochip" => Ok(Platform::XoChip),
+            _ => Err(format!(
+                "unknown platform `{}`, expected chip8, chip48, schip or xochip",
+                s
+            )),
+        }
+    }
+}
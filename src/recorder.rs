@@ -0,0 +1,113 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::thread::JoinHandle;
+
+use chip8::palette::Palette;
+use chip8::{FRAME_RATE, HIRES_HEIGHT, WIDTH};
+
+enum Message {
+    Frame { pixels: Vec<u8>, frame: u64 },
+    Stop { frame: u64 },
+}
+
+/// Records the display to an animated GIF. Frames are only captured when
+/// the display changes and are encoded on a background thread, each shown
+/// until the frame number of the next one.
+pub struct Recorder {
+    path: PathBuf,
+    scale: usize,
+    /// Rows of the display recorded.
+    rows: usize,
+    sender: Sender<Message>,
+    encoder: JoinHandle<Result<(), gif::EncodingError>>,
+}
+
+impl Recorder {
+    pub fn start(
+        path: PathBuf,
+        palette: &Palette,
+        scale: u32,
+        rows: usize,
+    ) -> Result<Recorder, String> {
+        let scale = scale as usize;
+        let width = (WIDTH * scale) as u16;
+        let height = (rows * scale) as u16;
+        let colors: Vec<u8> = palette
+            .colors
+            .iter()
+            .flat_map(|rgb| [rgb.0, rgb.1, rgb.2])
+            .collect();
+        let file = File::create(&path).map_err(|e| e.to_string())?;
+        let mut encoder = gif::Encoder::new(BufWriter::new(file), width, height, &colors)
+            .map_err(|e| e.to_string())?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(|e| e.to_string())?;
+
+        let (sender, receiver) = mpsc::channel();
+        let encoder = std::thread::spawn(move || {
+            let mut pending: Option<(Vec<u8>, u64)> = None;
+            for message in receiver {
+                let (next, end) = match message {
+                    Message::Frame { pixels, frame } => (Some((pixels, frame)), frame),
+                    Message::Stop { frame } => (None, frame),
+                };
+                if let Some((pixels, start)) = pending {
+                    let mut frame = gif::Frame {
+                        width,
+                        height,
+                        buffer: Cow::Owned(pixels),
+                        ..gif::Frame::default()
+                    };
+                    // GIF delays are in hundredths of a second. Converting the
+                    // absolute frame numbers avoids accumulating rounding errors.
+                    let centiseconds = |frame: u64| frame * 100 / FRAME_RATE as u64;
+                    frame.delay = (centiseconds(end) - centiseconds(start)).max(1) as u16;
+                    encoder.write_frame(&frame)?;
+                }
+                pending = next;
+                if pending.is_none() {
+                    break;
+                }
+            }
+            Ok(())
+        });
+        Ok(Recorder {
+            path,
+            scale,
+            rows,
+            sender,
+            encoder,
+        })
+    }
+
+    /// Captures the display shown from frame number `frame` onwards.
+    pub fn capture(&self, display: &[[bool; WIDTH]; HIRES_HEIGHT], frame: u64) {
+        let mut pixels = Vec::with_capacity(WIDTH * self.rows * self.scale * self.scale);
+        for row in &display[..self.rows] {
+            let start = pixels.len();
+            for &pixel in row {
+                pixels.extend(std::iter::repeat_n(pixel as u8, self.scale));
+            }
+            for _ in 1..self.scale {
+                pixels.extend_from_within(start..);
+            }
+        }
+        // A send error means the encoder failed, which is reported by stop.
+        let _ = self.sender.send(Message::Frame { pixels, frame });
+    }
+
+    /// Finishes the recording at frame number `frame`, waiting for the
+    /// remaining frames to be encoded.
+    pub fn stop(self, frame: u64) -> Result<PathBuf, String> {
+        let _ = self.sender.send(Message::Stop { frame });
+        match self.encoder.join() {
+            Ok(Ok(())) => Ok(self.path),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err("encoder thread panicked".to_string()),
+        }
+    }
+}
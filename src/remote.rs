@@ -0,0 +1,139 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+
+use chip8::{Emulator, WIDTH};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// A command from a remote control client, one JSON object a line like
+/// `{"command": "press", "key": 5}`.
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Request {
+    Press { key: u8 },
+    Release { key: u8 },
+    Pause,
+    Resume,
+    Reset,
+    Load { path: PathBuf },
+    Registers,
+    Framebuffer,
+}
+
+/// A server accepting one remote control client at a time over TCP, for
+/// automation such as bots and tests. Every request is answered with a line
+/// of JSON, `{"ok": true, ...}` with the values asked for, or
+/// `{"ok": false, "error": "..."}`.
+pub struct RemoteServer {
+    listener: TcpListener,
+    client: Option<TcpStream>,
+    buffer: Vec<u8>,
+}
+
+impl RemoteServer {
+    pub fn bind(port: u16) -> std::io::Result<RemoteServer> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        println!("Listening for remote control on port {}", port);
+        Ok(RemoteServer {
+            listener,
+            client: None,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Returns the next request the client sent, after accepting a pending
+    /// connection when none is connected. Malformed requests are answered
+    /// here, and every request returned must be answered with
+    /// [`RemoteServer::reply`] before polling again.
+    pub fn poll(&mut self) -> Option<Request> {
+        if self.client.is_none() {
+            let (stream, addr) = self.listener.accept().ok()?;
+            stream.set_nonblocking(true).ok()?;
+            println!("Remote control connected from {}", addr);
+            self.client = Some(stream);
+        }
+        self.receive();
+        loop {
+            let end = self.buffer.iter().position(|&b| b == b'\n')?;
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(request) => return Some(request),
+                Err(e) => self.reply(Err(e.to_string())),
+            }
+        }
+    }
+
+    /// Reads whatever the client has sent, dropping it when it disconnects.
+    fn receive(&mut self) {
+        let Some(client) = &mut self.client else {
+            return;
+        };
+        let mut bytes = [0; 1024];
+        loop {
+            match client.read(&mut bytes) {
+                Ok(0) => break self.disconnect(),
+                Ok(len) => self.buffer.extend_from_slice(&bytes[..len]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => break self.disconnect(),
+            }
+        }
+    }
+
+    fn disconnect(&mut self) {
+        println!("Remote control disconnected");
+        self.client = None;
+        self.buffer.clear();
+    }
+
+    /// Answers the last request with the values in the object `result`, or
+    /// with an error.
+    pub fn reply(&mut self, result: Result<Value, String>) {
+        let reply = match result {
+            Ok(Value::Object(mut values)) => {
+                values.insert("ok".to_string(), Value::Bool(true));
+                Value::Object(values)
+            }
+            Ok(_) => json!({ "ok": true }),
+            Err(e) => json!({ "ok": false, "error": e }),
+        };
+        let Some(client) = &mut self.client else {
+            return;
+        };
+        // Replies are small, so the socket's buffer takes them whole.
+        if writeln!(client, "{}", reply).is_err() {
+            self.disconnect();
+        }
+    }
+}
+
+/// The registers, stack and timers, as the reply to `registers`.
+pub fn registers(emulator: &Emulator) -> Value {
+    json!({
+        "pc": emulator.pc,
+        "i": emulator.index_register,
+        "v": emulator.var_registers,
+        "stack": &emulator.stack[..],
+        "dt": emulator.delay_timer,
+        "st": emulator.sound_timer,
+    })
+}
+
+/// The rows of the display in use, as strings of `0` and `1` with a
+/// character for each pixel, as the reply to `framebuffer`.
+pub fn framebuffer(emulator: &Emulator) -> Value {
+    let rows: Vec<String> = emulator.display[..emulator.height()]
+        .iter()
+        .map(|row| row.iter().map(|&on| if on { '1' } else { '0' }).collect())
+        .collect();
+    json!({
+        "width": WIDTH,
+        "height": emulator.height(),
+        "rows": rows,
+    })
+}
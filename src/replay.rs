@@ -0,0 +1,125 @@
+use std::fmt::Write;
+use std::path::Path;
+
+const HEADER: &str = "chip8-replay 1";
+
+/// Input that affects emulation, so must be reproduced during playback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    Key { key: u8, pressed: bool },
+    Speed(u32),
+    Reset,
+}
+
+/// Inputs timestamped with the emulated frame they were applied before,
+/// together with the RNG seed and speed needed to reproduce a run exactly.
+///
+/// Replays are stored as text, one input per line:
+///
+/// ```text
+/// chip8-replay 1
+/// seed 1234567890
+/// speed 700
+/// 120 down 5
+/// 131 up 5
+/// 200 speed 800
+/// 250 reset
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: u64,
+    pub speed: u32,
+    pub inputs: Vec<(u64, Input)>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, speed: u32) -> Replay {
+        Replay {
+            seed,
+            speed,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+        if lines.next().map(|(_, line)| line) != Some(HEADER) {
+            return Err("not a replay file".to_string());
+        }
+        let mut seed = None;
+        let mut speed = None;
+        let mut inputs = Vec::new();
+        for (line_number, line) in lines {
+            let error = || format!("line {}: invalid entry `{}`", line_number, line);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let number = |i: usize| words.get(i).and_then(|w| w.parse().ok()).ok_or_else(error);
+            match words.as_slice() {
+                [] => {}
+                ["seed", _] => seed = Some(number(1)?),
+                ["speed", _] => speed = Some(number(1)? as u32),
+                [_, "down" | "up", _] => {
+                    let key = number(2)?;
+                    if key > 0xF {
+                        return Err(error());
+                    }
+                    let input = Input::Key {
+                        key: key as u8,
+                        pressed: words[1] == "down",
+                    };
+                    inputs.push((number(0)?, input));
+                }
+                [_, "speed", _] => inputs.push((number(0)?, Input::Speed(number(2)? as u32))),
+                [_, "reset"] => inputs.push((number(0)?, Input::Reset)),
+                _ => return Err(error()),
+            }
+        }
+        Ok(Replay {
+            seed: seed.ok_or("missing seed")?,
+            speed: speed.ok_or("missing speed")?,
+            inputs,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut text = format!("{}\nseed {}\nspeed {}\n", HEADER, self.seed, self.speed);
+        for (frame, input) in &self.inputs {
+            match input {
+                Input::Key { key, pressed } => {
+                    let state = if *pressed { "down" } else { "up" };
+                    writeln!(text, "{} {} {}", frame, state, key).unwrap();
+                }
+                Input::Speed(speed) => writeln!(text, "{} speed {}", frame, speed).unwrap(),
+                Input::Reset => writeln!(text, "{} reset", frame).unwrap(),
+            }
+        }
+        std::fs::write(path, text)
+    }
+}
+
+/// Feeds the inputs of a replay back in order.
+pub struct Player {
+    replay: Replay,
+    next: usize,
+}
+
+impl Player {
+    pub fn new(replay: Replay) -> Player {
+        Player { replay, next: 0 }
+    }
+
+    /// Returns the next input to apply before emulated frame `frame`.
+    pub fn next_input(&mut self, frame: u64) -> Option<Input> {
+        match self.replay.inputs.get(self.next) {
+            Some(&(at, input)) if at <= frame => {
+                self.next += 1;
+                Some(input)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.replay.inputs.len()
+    }
+}
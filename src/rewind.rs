@@ -0,0 +1,39 @@
+use std::collections::VecDeque;
+
+use chip8::Snapshot;
+
+/// Ring buffer of the most recent frame snapshots, bounded by a memory budget.
+pub struct Rewind {
+    snapshots: VecDeque<Snapshot>,
+    capacity: usize,
+}
+
+impl Rewind {
+    /// Creates a buffer holding as many snapshots as fit in `budget` bytes.
+    pub fn new(budget: usize) -> Rewind {
+        Rewind {
+            snapshots: VecDeque::new(),
+            capacity: budget / std::mem::size_of::<Snapshot>(),
+        }
+    }
+
+    /// Records a snapshot, dropping the oldest one when the buffer is full.
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// Removes and returns the most recent snapshot.
+    pub fn pop(&mut self) -> Option<Snapshot> {
+        self.snapshots.pop_back()
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
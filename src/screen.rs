@@ -0,0 +1,223 @@
+use chip8::palette::{Palette, Rgb};
+use chip8::{HIRES_HEIGHT, WIDTH};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+use serde::Deserialize;
+
+/// Brightness of the darkened rows between scanlines.
+const SCANLINE_BRIGHTNESS: f32 = 0.6;
+/// Brightness of the lines between pixels with the pixel grid.
+const GRID_BRIGHTNESS: f32 = 0.8;
+/// Strength of the barrel distortion with curvature.
+const CURVATURE: f32 = 0.08;
+
+/// CRT style effects, set in the `[crt]` table of the configuration.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Crt {
+    /// Darken every other row of window pixels.
+    pub scanlines: bool,
+    /// Darken the edges of each CHIP-8 pixel.
+    pub grid: bool,
+    /// Bulge the display out like a curved tube.
+    pub curvature: bool,
+}
+
+impl Crt {
+    fn enabled(&self) -> bool {
+        self.scanlines || self.grid || self.curvature
+    }
+}
+
+/// Area of the window the display is drawn in, centered and scaled by the
+/// largest integer factor that fits so pixels stay square and sharp.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub scale: u32,
+    /// Rows of the display shown, which depends on the ROM.
+    pub rows: usize,
+}
+
+impl Viewport {
+    pub fn fit((width, height): (u32, u32), rows: usize) -> Viewport {
+        let scale = (width / WIDTH as u32).min(height / rows as u32).max(1);
+        Viewport {
+            x: (width as i32 - (WIDTH as u32 * scale) as i32) / 2,
+            y: (height as i32 - (rows as u32 * scale) as i32) / 2,
+            scale,
+            rows,
+        }
+    }
+}
+
+/// Draws the display through a streaming texture. Without CRT effects the
+/// texture holds one texel per CHIP-8 pixel and is scaled up when copied;
+/// with them it's rendered at the viewport's size so the effects can work
+/// on window pixels. Only the rows that changed since the last draw are
+/// rendered and uploaded again.
+pub struct Screen<'a> {
+    texture_creator: &'a TextureCreator<WindowContext>,
+    texture: Option<(Texture<'a>, u32, u32)>,
+    pixels: Vec<u8>,
+    crt: Crt,
+    /// Viewport and palette of the last draw, or `None` when everything has
+    /// to be drawn again.
+    drawn: Option<(Viewport, Palette)>,
+}
+
+impl<'a> Screen<'a> {
+    pub fn new(texture_creator: &'a TextureCreator<WindowContext>, crt: Crt) -> Screen<'a> {
+        Screen {
+            texture_creator,
+            texture: None,
+            pixels: Vec::new(),
+            crt,
+            drawn: None,
+        }
+    }
+
+    /// Makes the next draw redraw everything, for when something else was
+    /// drawn over the canvas.
+    pub fn invalidate(&mut self) {
+        self.drawn = None;
+    }
+
+    /// Clears the canvas and draws `intensity` into `viewport`, without
+    /// presenting, re-rendering the `dirty` rows. Returns false without
+    /// touching the canvas when nothing changed since the last draw.
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        intensity: &[[f32; WIDTH]; HIRES_HEIGHT],
+        dirty: &[bool; HIRES_HEIGHT],
+        viewport: Viewport,
+        palette: &Palette,
+    ) -> bool {
+        let scale = if self.crt.enabled() {
+            viewport.scale
+        } else {
+            1
+        };
+        let (width, height) = (WIDTH as u32 * scale, viewport.rows as u32 * scale);
+        let resized = !matches!(self.texture, Some((_, w, h)) if (w, h) == (width, height));
+        let full = resized || !matches!(self.drawn, Some((_, drawn)) if drawn == *palette);
+        let moved = !matches!(self.drawn, Some((drawn, _)) if drawn == viewport);
+        if !full && !moved && !dirty.contains(&true) {
+            return false;
+        }
+        let (intensity, dirty) = (&intensity[..viewport.rows], &dirty[..viewport.rows]);
+        let dirty = if full {
+            &[true; HIRES_HEIGHT][..viewport.rows]
+        } else {
+            dirty
+        };
+        self.drawn = Some((viewport, *palette));
+
+        self.pixels.resize((width * height * 4) as usize, 0);
+        if self.crt.enabled() {
+            self.render_crt(intensity, dirty, scale as usize, palette);
+        } else {
+            for ((out, amounts), _) in self
+                .pixels
+                .chunks_exact_mut(WIDTH * 4)
+                .zip(intensity)
+                .zip(dirty)
+                .filter(|(_, &dirty)| dirty)
+            {
+                for (out, &amount) in out.chunks_exact_mut(4).zip(amounts) {
+                    let Rgb(r, g, b) = palette.background().mix(palette.foreground(), amount);
+                    out.copy_from_slice(&[r, g, b, 0xFF]);
+                }
+            }
+        }
+
+        if resized {
+            let texture = self
+                .texture_creator
+                .create_texture_streaming(PixelFormatEnum::RGBA32, width, height)
+                .unwrap();
+            self.texture = Some((texture, width, height));
+        }
+        let (texture, _, _) = self.texture.as_mut().unwrap();
+        // Upload the band of rows from the first to the last dirty one. With
+        // curvature a row's pixels spread over other rows, so upload it all.
+        let first = dirty.iter().position(|&dirty| dirty);
+        let last = dirty.iter().rposition(|&dirty| dirty);
+        if let (Some(first), Some(last)) = (first, last) {
+            let (first, last) = if self.crt.curvature {
+                (0, viewport.rows - 1)
+            } else {
+                (first, last)
+            };
+            let pitch = width as usize * 4;
+            let (top, rows) = (first as u32 * scale, (last - first + 1) as u32 * scale);
+            let band = Rect::new(0, top as i32, width, rows);
+            let start = top as usize * pitch;
+            texture
+                .update(
+                    band,
+                    &self.pixels[start..start + rows as usize * pitch],
+                    pitch,
+                )
+                .unwrap();
+        }
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        canvas.clear();
+        let display = Rect::new(
+            viewport.x,
+            viewport.y,
+            WIDTH as u32 * viewport.scale,
+            viewport.rows as u32 * viewport.scale,
+        );
+        canvas.copy(texture, None, display).unwrap();
+        true
+    }
+
+    /// Renders the display at `scale` window pixels per CHIP-8 pixel with
+    /// the enabled effects applied, skipping the pixels showing rows that
+    /// aren't `dirty`.
+    fn render_crt(
+        &mut self,
+        intensity: &[[f32; WIDTH]],
+        dirty: &[bool],
+        scale: usize,
+        palette: &Palette,
+    ) {
+        let (width, height) = (WIDTH * scale, intensity.len() * scale);
+        let black = Rgb(0, 0, 0);
+        for (i, out) in self.pixels.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (i % width, i / width);
+            let (mut u, mut v) = (x as f32 + 0.5, y as f32 + 0.5);
+            if self.crt.curvature {
+                // Distort from the center, in -1 to 1 units.
+                let nx = 2.0 * u / width as f32 - 1.0;
+                let ny = 2.0 * v / height as f32 - 1.0;
+                let distortion = 1.0 + CURVATURE * (nx * nx + ny * ny);
+                u = (nx * distortion + 1.0) * width as f32 / 2.0;
+                v = (ny * distortion + 1.0) * height as f32 / 2.0;
+                if u < 0.0 || v < 0.0 || u >= width as f32 || v >= height as f32 {
+                    out.copy_from_slice(&[0, 0, 0, 0xFF]);
+                    continue;
+                }
+            }
+            let (u, v) = (u as usize, v as usize);
+            if !dirty[v / scale] {
+                continue;
+            }
+            let amount = intensity[v / scale][u / scale];
+            let mut color = palette.background().mix(palette.foreground(), amount);
+            if self.crt.grid && scale >= 4 && (u % scale == scale - 1 || v % scale == scale - 1) {
+                color = black.mix(color, GRID_BRIGHTNESS);
+            }
+            if self.crt.scanlines && scale >= 2 && y % 2 == 1 {
+                color = black.mix(color, SCANLINE_BRIGHTNESS);
+            }
+            let Rgb(r, g, b) = color;
+            out.copy_from_slice(&[r, g, b, 0xFF]);
+        }
+    }
+}
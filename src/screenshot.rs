@@ -0,0 +1,46 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use chip8::WIDTH;
+
+/// Saves an RGBA framebuffer to a timestamped PNG in the working directory,
+/// scaling every pixel up to a `scale` by `scale` square.
+pub fn save(rgba: &[u8], scale: u32) -> io::Result<PathBuf> {
+    let path = timestamped_path("png");
+    write_png(&path, rgba, scale)?;
+    Ok(path)
+}
+
+/// Returns a file name in the working directory containing the current time.
+pub fn timestamped_path(extension: &str) -> PathBuf {
+    let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S%.3f");
+    PathBuf::from(format!("chip8_{}.{}", timestamp, extension))
+}
+
+/// Writes an RGBA framebuffer to `path` as a PNG, scaling every pixel up to
+/// a `scale` by `scale` square.
+pub fn write_png(path: &Path, rgba: &[u8], scale: u32) -> io::Result<()> {
+    let scale = scale as usize;
+    let mut data = Vec::with_capacity(rgba.len() * scale * scale);
+    for row in rgba.chunks_exact(WIDTH * 4) {
+        let start = data.len();
+        for pixel in row.chunks_exact(4) {
+            for _ in 0..scale {
+                data.extend_from_slice(pixel);
+            }
+        }
+        for _ in 1..scale {
+            data.extend_from_within(start..);
+        }
+    }
+
+    let file = BufWriter::new(File::create(path)?);
+    let height = rgba.len() / (WIDTH * 4);
+    let mut encoder = png::Encoder::new(file, (WIDTH * scale) as u32, (height * scale) as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    Ok(())
+}
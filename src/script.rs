@@ -0,0 +1,201 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use chip8::{Emulator, WIDTH};
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Scope, AST};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// A rhai script run alongside the emulator, for cheats, bots and checks.
+///
+/// Top level statements run once when the script is loaded. The script may
+/// then define hooks:
+///
+/// ```text
+/// fn on_frame(frame) { ... }          // after every emulated frame
+/// fn on_instruction(pc, op) { ... }   // before every instruction
+/// ```
+///
+/// The script reads and changes the emulator with `peek(addr)`,
+/// `poke(addr, value)`, `reg(x)`, `set_reg(x, value)`, `index()`,
+/// `set_index(addr)`, `pc()`, `set_pc(addr)`, `delay_timer()`,
+/// `set_delay_timer(value)`, `pixel(x, y)`, `press(key)` and
+/// `release(key)`. Throwing an error, like a failed check, stops the
+/// emulator and reports it.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    /// Emulator the API functions act on, swapped with the real one while
+    /// the script runs.
+    shared: Rc<RefCell<Emulator>>,
+    on_frame: bool,
+    on_instruction: bool,
+}
+
+impl Script {
+    /// Compiles the script at `path` and runs its top level statements.
+    pub fn load(path: &Path, emulator: &mut Emulator) -> Result<Script, String> {
+        let shared = Rc::new(RefCell::new(Emulator::new()));
+        let mut engine = Engine::new();
+        register_api(&mut engine, &shared);
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| e.to_string())?;
+        let has_hook = |name: &str, params: usize| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == params)
+        };
+        let mut script = Script {
+            on_frame: has_hook("on_frame", 1),
+            on_instruction: has_hook("on_instruction", 2),
+            engine,
+            ast,
+            scope: Scope::new(),
+            shared,
+        };
+        script.with_emulator(emulator, |script| {
+            script
+                .engine
+                .run_ast_with_scope(&mut script.scope, &script.ast)
+        })?;
+        Ok(script)
+    }
+
+    /// Calls `on_frame`, with the number of frames emulated so far.
+    pub fn on_frame(&mut self, emulator: &mut Emulator, frame: u64) -> Result<(), String> {
+        if !self.on_frame {
+            return Ok(());
+        }
+        self.call(emulator, "on_frame", (frame as i64,))
+    }
+
+    /// Calls `on_instruction` with the address and opcode of the instruction
+    /// about to be executed.
+    pub fn on_instruction(&mut self, emulator: &mut Emulator) -> Result<(), String> {
+        if !self.on_instruction {
+            return Ok(());
+        }
+        let Ok(opcode) = emulator.current_opcode() else {
+            return Ok(());
+        };
+        let args = (emulator.pc as i64, opcode as i64);
+        self.call(emulator, "on_instruction", args)
+    }
+
+    fn call(
+        &mut self,
+        emulator: &mut Emulator,
+        name: &str,
+        args: impl FuncArgs,
+    ) -> Result<(), String> {
+        self.with_emulator(emulator, |script| {
+            let options = CallFnOptions::new().eval_ast(false);
+            script
+                .engine
+                .call_fn_with_options::<Dynamic>(
+                    options,
+                    &mut script.scope,
+                    &script.ast,
+                    name,
+                    args,
+                )
+                .map(drop)
+        })
+    }
+
+    /// Runs `f` with `emulator` made available to the API functions.
+    fn with_emulator(
+        &mut self,
+        emulator: &mut Emulator,
+        f: impl FnOnce(&mut Script) -> ScriptResult<()>,
+    ) -> Result<(), String> {
+        std::mem::swap(emulator, &mut *self.shared.borrow_mut());
+        let result = f(self);
+        std::mem::swap(emulator, &mut *self.shared.borrow_mut());
+        result.map_err(|e| e.to_string())
+    }
+}
+
+fn register_api(engine: &mut Engine, shared: &Rc<RefCell<Emulator>>) {
+    let emulator = shared.clone();
+    engine.register_fn("peek", move |addr: i64| -> ScriptResult<i64> {
+        let emulator = emulator.borrow();
+        let value = emulator
+            .memory
+            .get(addr as usize)
+            .ok_or_else(|| out_of_range("address", addr))?;
+        Ok(*value as i64)
+    });
+    let emulator = shared.clone();
+    engine.register_fn("poke", move |addr: i64, value: i64| -> ScriptResult<()> {
+        let mut emulator = emulator.borrow_mut();
+        let byte = emulator
+            .memory
+            .get_mut(addr as usize)
+            .ok_or_else(|| out_of_range("address", addr))?;
+        *byte = value as u8;
+        Ok(())
+    });
+    let emulator = shared.clone();
+    engine.register_fn("reg", move |x: i64| -> ScriptResult<i64> {
+        let emulator = emulator.borrow();
+        let value = emulator
+            .var_registers
+            .get(x as usize)
+            .ok_or_else(|| out_of_range("register", x))?;
+        Ok(*value as i64)
+    });
+    let emulator = shared.clone();
+    engine.register_fn("set_reg", move |x: i64, value: i64| -> ScriptResult<()> {
+        let mut emulator = emulator.borrow_mut();
+        let register = emulator
+            .var_registers
+            .get_mut(x as usize)
+            .ok_or_else(|| out_of_range("register", x))?;
+        *register = value as u8;
+        Ok(())
+    });
+    let emulator = shared.clone();
+    engine.register_fn("index", move || emulator.borrow().index_register as i64);
+    let emulator = shared.clone();
+    engine.register_fn("set_index", move |addr: i64| {
+        emulator.borrow_mut().index_register = addr as usize & 0xFFFF;
+    });
+    let emulator = shared.clone();
+    engine.register_fn("pc", move || emulator.borrow().pc as i64);
+    let emulator = shared.clone();
+    engine.register_fn("set_pc", move |addr: i64| -> ScriptResult<()> {
+        let mut emulator = emulator.borrow_mut();
+        if addr < 0 || addr as usize >= emulator.memory.len() {
+            return Err(out_of_range("address", addr));
+        }
+        emulator.pc = addr as usize;
+        Ok(())
+    });
+    let emulator = shared.clone();
+    engine.register_fn("delay_timer", move || emulator.borrow().delay_timer as i64);
+    let emulator = shared.clone();
+    engine.register_fn("set_delay_timer", move |value: i64| {
+        emulator.borrow_mut().delay_timer = value as u8;
+    });
+    let emulator = shared.clone();
+    engine.register_fn("pixel", move |x: i64, y: i64| {
+        let emulator = emulator.borrow();
+        let y = y.rem_euclid(emulator.height() as i64) as usize;
+        emulator.display[y][x.rem_euclid(WIDTH as i64) as usize]
+    });
+    let emulator = shared.clone();
+    engine.register_fn("press", move |key: i64| {
+        emulator.borrow_mut().set_key(key as u8 & 0xF, true);
+    });
+    let emulator = shared.clone();
+    engine.register_fn("release", move |key: i64| {
+        emulator.borrow_mut().set_key(key as u8 & 0xF, false);
+    });
+}
+
+fn out_of_range(what: &str, value: i64) -> Box<EvalAltResult> {
+    format!("{} {:#X} out of range", what, value).into()
+}
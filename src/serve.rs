@@ -0,0 +1,411 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use sha1::{Digest, Sha1};
+
+use crate::palette::{Palette, Rgb};
+use crate::theme::Theme;
+use crate::{Emulator, Platform, Quirks, Timing, FRAME_RATE, WIDTH};
+
+/// The client page, served at `/`.
+const PAGE: &str = include_str!("serve.html");
+/// Appended to a client's key to accept its WebSocket handshake.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Longest a new connection may take to send its HTTP request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+/// Largest HTTP request accepted.
+const MAX_REQUEST: usize = 16 * 1024;
+/// Largest message accepted from a client, well above a key event's.
+const MAX_MESSAGE: usize = 1024;
+
+/// Flags setting up the emulator, shared by `chip8-serve` and the `chip8`
+/// subcommands which run ROMs.
+#[derive(clap::Args)]
+pub struct EmulatorOptions {
+    /// Speed in instructions per second [default: 700], adjustable while
+    /// running with PageUp and PageDown
+    #[arg(long)]
+    pub speed: Option<u32>,
+    /// How to count the instructions run in a frame: fixed by --speed, or
+    /// vip to charge each what it cost on the COSMAC VIP [default: fixed]
+    #[arg(long)]
+    pub timing: Option<Timing>,
+    /// Subroutine calls which can be nested before the ROM stops with a
+    /// stack overflow, from 1 to 16 [default: 16]
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=16))]
+    pub stack_limit: Option<u8>,
+    /// Interpreter to use the quirks and speed of, before applying the
+    /// individual quirk flags and --speed: chip8, chip48, schip or xochip
+    #[arg(long)]
+    pub platform: Option<Platform>,
+    /// Wait for the next frame after drawing, like the COSMAC VIP
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub display_wait: Option<bool>,
+    /// Reset VF to 0 in 8XY1, 8XY2 and 8XY3
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub vf_reset: Option<bool>,
+    /// Increment I past the registers stored or loaded by FX55 and FX65
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub memory_increment: Option<bool>,
+    /// Shift VY into VX in 8XY6 and 8XYE, instead of shifting VX in place
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub shift_vy: Option<bool>,
+    /// Jump to NNN plus VX in BNNN, instead of plus V0
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub jump_vx: Option<bool>,
+    /// Register the key in FX0A when pressed, instead of when released
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub key_press: Option<bool>,
+    /// Wrap sprites around the screen edges in DXYN, instead of clipping
+    /// them
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub wrap: Option<bool>,
+    /// Color theme [default: classic]
+    #[arg(long)]
+    pub theme: Option<Theme>,
+    /// Color of set pixels as #RRGGBB, overriding the theme
+    #[arg(long)]
+    pub foreground: Option<Rgb>,
+    /// Color of unset pixels as #RRGGBB, overriding the theme
+    #[arg(long)]
+    pub background: Option<Rgb>,
+    /// Seed for the random number generator, random when omitted
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+impl EmulatorOptions {
+    /// Applies the platform, then the speed and individual quirk flags, over
+    /// `speed` and `quirks`.
+    pub fn apply(&self, speed: &mut u32, quirks: &mut Quirks) {
+        if let Some(platform) = self.platform {
+            *quirks = platform.quirks();
+            *speed = platform.speed();
+        }
+        *speed = self.speed.unwrap_or(*speed);
+        quirks.display_wait = self.display_wait.unwrap_or(quirks.display_wait);
+        quirks.vf_reset = self.vf_reset.unwrap_or(quirks.vf_reset);
+        quirks.memory_increment = self.memory_increment.unwrap_or(quirks.memory_increment);
+        quirks.shift_vy = self.shift_vy.unwrap_or(quirks.shift_vy);
+        quirks.jump_vx = self.jump_vx.unwrap_or(quirks.jump_vx);
+        quirks.key_press = self.key_press.unwrap_or(quirks.key_press);
+        quirks.wrap = self.wrap.unwrap_or(quirks.wrap);
+    }
+
+    /// Returns the theme's palette, or `theme`'s when none was given, with
+    /// the color flags applied.
+    pub fn palette(&self, theme: Theme) -> Palette {
+        let mut palette = self.theme.unwrap_or(theme).palette();
+        palette.colors[0] = self.background.unwrap_or(palette.colors[0]);
+        palette.colors[1] = self.foreground.unwrap_or(palette.colors[1]);
+        palette
+    }
+}
+
+/// Flags for where to serve, shared by `chip8 serve` and `chip8-serve`.
+#[derive(clap::Args)]
+pub struct ServeOptions {
+    /// Address to listen on, 0.0.0.0 to let other machines connect
+    #[arg(long, default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    pub bind: IpAddr,
+    /// Port to serve the page and its WebSocket on
+    #[arg(long, default_value_t = 8080)]
+    pub port: u16,
+}
+
+impl ServeOptions {
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind, self.port)
+    }
+}
+
+/// A connection whose HTTP request hasn't all arrived yet.
+struct Pending {
+    stream: TcpStream,
+    request: Vec<u8>,
+    since: Instant,
+}
+
+/// A browser connected over WebSocket.
+struct Client {
+    stream: TcpStream,
+    /// Bytes received which don't make a whole frame yet.
+    buffer: Vec<u8>,
+}
+
+/// Runs the emulator in real time without a window, serving the page at
+/// `/` on `addr` and streaming the display to every browser viewing it over
+/// a WebSocket. Each browser's key presses are applied as they arrive. An
+/// emulator error stops execution, but the last frame is still served.
+pub fn serve(emulator: &mut Emulator, palette: &Palette, addr: SocketAddr) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    println!("Serving on http://{}/", addr);
+    let mut pending: Vec<Pending> = Vec::new();
+    let mut clients: Vec<Client> = Vec::new();
+    let mut keys = [false; 16];
+    let mut stopped = false;
+    let mut sound = false;
+    let mut generation = None;
+    let frame_duration = Duration::from_secs(1) / FRAME_RATE;
+    let mut next_frame = Instant::now();
+    loop {
+        while let Ok((stream, _)) = listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                pending.push(Pending {
+                    stream,
+                    request: Vec::new(),
+                    since: Instant::now(),
+                });
+            }
+        }
+        // Requests are read as they arrive, so a slow client doesn't hold
+        // up the emulator.
+        for mut connection in std::mem::take(&mut pending) {
+            match receive_request(&mut connection) {
+                Ok(false) if connection.since.elapsed() < REQUEST_TIMEOUT => {
+                    pending.push(connection)
+                }
+                Ok(false) | Err(_) => {}
+                Ok(true) => match accept(connection, palette) {
+                    Ok(Some(client)) => {
+                        clients.push(client);
+                        // Sent the current frame along with the next.
+                        generation = None;
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Failed to answer a request: {}", e),
+                },
+            }
+        }
+        clients.retain_mut(|client| receive(client, &mut keys).is_ok());
+
+        if !stopped {
+            match emulator.run_frame(&keys) {
+                Ok(result) => sound = result.sound,
+                Err(e) => {
+                    eprintln!("The emulator stopped: {}", e);
+                    stopped = true;
+                    sound = false;
+                }
+            }
+        }
+        let state = (emulator.display_generation(), sound);
+        if generation != Some(state) {
+            generation = Some(state);
+            let frame = encode_display(emulator, sound);
+            clients.retain_mut(|client| send(&mut client.stream, 0x2, &frame).is_ok());
+        }
+
+        next_frame += frame_duration;
+        let now = Instant::now();
+        if next_frame > now {
+            std::thread::sleep(next_frame - now);
+        } else {
+            next_frame = now;
+        }
+    }
+}
+
+/// Reads what has arrived of the HTTP request on a new connection, returning
+/// true once all of it has. Fails when the connection closes first or the
+/// request is too long.
+fn receive_request(connection: &mut Pending) -> io::Result<bool> {
+    let mut bytes = [0; 1024];
+    loop {
+        if header_end(&connection.request).is_some() {
+            return Ok(true);
+        }
+        if connection.request.len() > MAX_REQUEST {
+            return Err(ErrorKind::InvalidData.into());
+        }
+        match connection.stream.read(&mut bytes) {
+            Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+            Ok(len) => connection.request.extend_from_slice(&bytes[..len]),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(false),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Returns the length of the HTTP request head in `request`, up to and
+/// including the blank line ending it.
+fn header_end(request: &[u8]) -> Option<usize> {
+    request
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|i| i + 4)
+}
+
+/// Answers the HTTP request a connection sent, with the page, a 404, or by
+/// accepting a WebSocket and returning it as a client. Whatever the client
+/// sent after the request is kept as the start of its first frame.
+fn accept(connection: Pending, palette: &Palette) -> io::Result<Option<Client>> {
+    let Pending {
+        mut stream,
+        mut request,
+        ..
+    } = connection;
+    let end = header_end(&request).unwrap_or(request.len());
+    let buffer = request.split_off(end);
+    let request = String::from_utf8_lossy(&request);
+    let mut lines = request.lines();
+    let path = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or("");
+    let key = lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.eq_ignore_ascii_case("sec-websocket-key")
+            .then(|| value.trim().to_string())
+    });
+
+    let Some(key) = key else {
+        let response = if path == "/" {
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                PAGE.len(),
+                PAGE
+            )
+        } else {
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+        };
+        stream.write_all(response.as_bytes())?;
+        return Ok(None);
+    };
+    let accept = base64(&Sha1::digest(format!("{}{}", key, WEBSOCKET_GUID)));
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+         Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept
+    )?;
+    let colors = format!("palette {} {}", palette.colors[0], palette.colors[1]);
+    send(&mut stream, 0x1, colors.as_bytes())?;
+    Ok(Some(Client { stream, buffer }))
+}
+
+/// Reads what `client` has sent and applies the `down <key>` and
+/// `up <key>` messages in it to `keys`. Fails once the client is gone.
+fn receive(client: &mut Client, keys: &mut [bool; 16]) -> io::Result<()> {
+    let mut bytes = [0; 1024];
+    loop {
+        match client.stream.read(&mut bytes) {
+            Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+            Ok(len) => client.buffer.extend_from_slice(&bytes[..len]),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            Err(e) => return Err(e),
+        }
+    }
+    while let Some((opcode, message)) = next_message(&mut client.buffer)? {
+        match opcode {
+            0x1 => {
+                let message = String::from_utf8_lossy(&message);
+                let key = match message.split_once(' ') {
+                    Some(("down", key)) => key.parse().ok().map(|key: usize| (key, true)),
+                    Some(("up", key)) => key.parse().ok().map(|key: usize| (key, false)),
+                    _ => None,
+                };
+                if let Some((key, pressed)) = key.filter(|&(key, _)| key < keys.len()) {
+                    keys[key] = pressed;
+                }
+            }
+            0x8 => return Err(ErrorKind::ConnectionAborted.into()),
+            0x9 => send(&mut client.stream, 0xA, &message)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Removes the first whole WebSocket frame from `buffer`, returning its
+/// opcode and unmasked payload. Fragmented messages aren't supported, as
+/// the page never sends any.
+fn next_message(buffer: &mut Vec<u8>) -> io::Result<Option<(u8, Vec<u8>)>> {
+    let &[first, second, ..] = buffer.as_slice() else {
+        return Ok(None);
+    };
+    let (len, mut start) = match second & 0x7F {
+        126 if buffer.len() >= 4 => (u16::from_be_bytes([buffer[2], buffer[3]]) as usize, 4),
+        126 | 127 if buffer.len() < 4 => return Ok(None),
+        127 => return Err(ErrorKind::InvalidData.into()),
+        len => (len as usize, 2),
+    };
+    if len > MAX_MESSAGE {
+        return Err(ErrorKind::InvalidData.into());
+    }
+    // Clients always mask what they send.
+    let masked = second & 0x80 != 0;
+    if !masked {
+        return Err(ErrorKind::InvalidData.into());
+    }
+    if buffer.len() < start + 4 + len {
+        return Ok(None);
+    }
+    let mask = [
+        buffer[start],
+        buffer[start + 1],
+        buffer[start + 2],
+        buffer[start + 3],
+    ];
+    start += 4;
+    let payload = buffer[start..start + len]
+        .iter()
+        .zip(mask.iter().cycle())
+        .map(|(byte, mask)| byte ^ mask)
+        .collect();
+    buffer.drain(..start + len);
+    Ok(Some((first & 0xF, payload)))
+}
+
+/// Sends `payload` as a single unmasked WebSocket frame.
+fn send(stream: &mut TcpStream, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    // Frames are small, so the socket's buffer takes them whole.
+    stream.write_all(&frame)
+}
+
+/// Encodes the display for the page: its height and whether the buzzer
+/// sounds, then the pixels row by row, eight to a byte with the leftmost in
+/// the high bit.
+fn encode_display(emulator: &Emulator, sound: bool) -> Vec<u8> {
+    let rows = &emulator.display[..emulator.height()];
+    let mut frame = vec![0; 2 + rows.len() * WIDTH / 8];
+    frame[0] = rows.len() as u8;
+    frame[1] = sound as u8;
+    for (i, &on) in rows.iter().flatten().enumerate() {
+        if on {
+            frame[2 + i / 8] |= 0x80 >> (i % 8);
+        }
+    }
+    frame
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut s = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
@@ -0,0 +1,151 @@
+use std::fmt::Write;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chip8::Emulator;
+use sha1::{Digest, Sha1};
+
+/// Number of save state slots, bound to F1 to F10 by default.
+pub const SLOTS: u8 = 10;
+
+/// Save state slots of the running ROM, kept in the user's data directory
+/// under the ROM's SHA-1 hash like its RPL flags.
+pub struct States {
+    dir: Option<PathBuf>,
+}
+
+impl States {
+    pub fn new(rom: &[u8]) -> States {
+        let dir = dirs::data_dir().map(|dir| {
+            let hash = format!("{:x}", Sha1::digest(rom));
+            dir.join("chip8").join("states").join(hash)
+        });
+        States { dir }
+    }
+
+    fn path(&self, slot: u8) -> io::Result<PathBuf> {
+        match &self.dir {
+            Some(dir) => Ok(dir.join(format!("{}.state", slot))),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no user data directory",
+            )),
+        }
+    }
+
+    /// Saves the emulator's state to `slot`, replacing the one there.
+    pub fn save(&self, emulator: &Emulator, slot: u8) -> io::Result<()> {
+        let path = self.path(slot)?;
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, emulator.save_state())
+    }
+
+    /// Loads the state saved to `slot` into the emulator.
+    pub fn load(&self, emulator: &mut Emulator, slot: u8) -> Result<(), String> {
+        let state = match std::fs::read(self.path(slot).map_err(|e| e.to_string())?) {
+            Ok(state) => state,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err("nothing is saved there".to_string());
+            }
+            Err(e) => return Err(e.to_string()),
+        };
+        emulator.load_state(&state).map_err(|e| e.to_string())
+    }
+}
+
+/// Most changed bytes of a memory range shown by [`diff`].
+const DIFF_BYTES: usize = 8;
+
+/// Reads the save state at `path` into an emulator of its own.
+pub fn read(path: &Path) -> Result<Emulator, String> {
+    let state = std::fs::read(path).map_err(|e| e.to_string())?;
+    let mut emulator = Emulator::new();
+    emulator.load_state(&state).map_err(|e| e.to_string())?;
+    Ok(emulator)
+}
+
+/// Lists the registers, stack entries and memory ranges which differ from
+/// `old` to `new`, a line each, or says that nothing does.
+pub fn diff(old: &Emulator, new: &Emulator) -> String {
+    let mut s = String::new();
+    let mut changed = |name: &str, old: String, new: String| {
+        if old != new {
+            writeln!(s, "{:<4} {} -> {}", name, old, new).unwrap();
+        }
+    };
+    let address = |address: Option<&usize>| match address {
+        Some(address) => format!("{:#05X}", address),
+        None => "-".to_string(),
+    };
+    changed("PC", address(Some(&old.pc)), address(Some(&new.pc)));
+    changed(
+        "I",
+        address(Some(&old.index_register)),
+        address(Some(&new.index_register)),
+    );
+    for (i, (old, new)) in old.var_registers.iter().zip(&new.var_registers).enumerate() {
+        let name = format!("V{:X}", i);
+        changed(&name, format!("{:#04X}", old), format!("{:#04X}", new));
+    }
+    changed(
+        "DT",
+        old.delay_timer.to_string(),
+        new.delay_timer.to_string(),
+    );
+    changed(
+        "ST",
+        old.sound_timer.to_string(),
+        new.sound_timer.to_string(),
+    );
+    changed(
+        "SP",
+        old.stack.len().to_string(),
+        new.stack.len().to_string(),
+    );
+    for i in 0..old.stack.len().max(new.stack.len()) {
+        let name = format!("#{}", i);
+        changed(&name, address(old.stack.get(i)), address(new.stack.get(i)));
+    }
+
+    let mut start = None;
+    for i in 0..=old.memory.len() {
+        let differs = i < old.memory.len() && old.memory[i] != new.memory[i];
+        match (start, differs) {
+            (None, true) => start = Some(i),
+            (Some(from), false) => {
+                start = None;
+                let bytes = |memory: &[u8]| {
+                    let shown = &memory[from..i.min(from + DIFF_BYTES)];
+                    let mut bytes: Vec<String> =
+                        shown.iter().map(|byte| format!("{:02X}", byte)).collect();
+                    if i - from > DIFF_BYTES {
+                        bytes.push("...".to_string());
+                    }
+                    bytes.join(" ")
+                };
+                let name = if i - from == 1 {
+                    format!("{:#05X}", from)
+                } else {
+                    format!("{:#05X}..{:#05X}", from, i)
+                };
+                changed(&name, bytes(&old.memory), bytes(&new.memory));
+            }
+            _ => {}
+        }
+    }
+
+    let pixels = old
+        .display
+        .iter()
+        .flatten()
+        .zip(new.display.iter().flatten())
+        .filter(|(old, new)| old != new)
+        .count();
+    if pixels > 0 {
+        writeln!(s, "{} pixels of the display changed", pixels).unwrap();
+    }
+    if s.is_empty() {
+        s += "No differences\n";
+    }
+    s
+}
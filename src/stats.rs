@@ -0,0 +1,77 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use chip8::FRAME_RATE;
+
+/// How often the rates are recomputed.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Rates of rendered frames, executed instructions and timer ticks, averaged
+/// over the last second.
+pub struct Stats {
+    pub visible: bool,
+    start: Instant,
+    frames: u32,
+    instructions: u64,
+    ticks: u32,
+    fps: f64,
+    ips: f64,
+    tick_rate: f64,
+}
+
+impl Stats {
+    pub fn new(visible: bool) -> Stats {
+        Stats {
+            visible,
+            start: Instant::now(),
+            frames: 0,
+            instructions: 0,
+            ticks: 0,
+            fps: 0.0,
+            ips: 0.0,
+            tick_rate: 0.0,
+        }
+    }
+
+    pub fn record_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    pub fn record_instruction(&mut self) {
+        self.instructions += 1;
+    }
+
+    pub fn record_tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    /// Recomputes the rates once a second has passed since they last were,
+    /// returning whether they were.
+    pub fn update(&mut self) -> bool {
+        let elapsed = self.start.elapsed();
+        if elapsed < INTERVAL {
+            return false;
+        }
+        let seconds = elapsed.as_secs_f64();
+        self.fps = self.frames as f64 / seconds;
+        self.ips = self.instructions as f64 / seconds;
+        self.tick_rate = self.ticks as f64 / seconds;
+        self.start = Instant::now();
+        self.frames = 0;
+        self.instructions = 0;
+        self.ticks = 0;
+        true
+    }
+}
+
+impl fmt::Display for Stats {
+    /// Formats the rates, with how far the timers drift from 60 Hz.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let drift = 100.0 * (self.tick_rate / FRAME_RATE as f64 - 1.0);
+        write!(
+            f,
+            "{:.0} FPS, {:.0} IPS, timers {:.1} Hz ({:+.1}%)",
+            self.fps, self.ips, self.tick_rate, drift
+        )
+    }
+}
@@ -0,0 +1,51 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::palette::{Palette, Rgb};
+
+/// Built-in palettes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// White on black
+    #[default]
+    Classic,
+    /// Green phosphor monitor
+    Green,
+    /// Amber phosphor monitor
+    Amber,
+    /// Dark gray on off-white
+    Paper,
+}
+
+impl Theme {
+    pub fn palette(self) -> Palette {
+        let colors = match self {
+            Theme::Classic => [
+                Rgb(0x00, 0x00, 0x00),
+                Rgb(0xFF, 0xFF, 0xFF),
+                Rgb(0xAA, 0xAA, 0xAA),
+                Rgb(0x55, 0x55, 0x55),
+            ],
+            Theme::Green => [
+                Rgb(0x0A, 0x14, 0x0A),
+                Rgb(0x33, 0xFF, 0x33),
+                Rgb(0x1F, 0x99, 0x1F),
+                Rgb(0x99, 0xFF, 0x99),
+            ],
+            Theme::Amber => [
+                Rgb(0x14, 0x0C, 0x00),
+                Rgb(0xFF, 0xB0, 0x00),
+                Rgb(0x99, 0x69, 0x00),
+                Rgb(0xFF, 0xD8, 0x80),
+            ],
+            Theme::Paper => [
+                Rgb(0xF0, 0xEA, 0xD6),
+                Rgb(0x26, 0x26, 0x26),
+                Rgb(0x8C, 0x88, 0x7D),
+                Rgb(0x59, 0x57, 0x50),
+            ],
+        };
+        Palette { colors }
+    }
+}
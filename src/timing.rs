@@ -0,0 +1,91 @@
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::instruction::Instruction;
+
+/// Machine cycles of the COSMAC VIP in a 60Hz frame, of 8 clocks of its
+/// 1.76MHz CDP1802 each.
+const FRAME_CYCLES: i32 = 3668;
+/// Machine cycles of each frame taken by the display interrupt and its DMA,
+/// which the interpreter doesn't get to run in.
+const DISPLAY_CYCLES: i32 = 1832;
+/// Machine cycles the interpreter takes to fetch and decode an instruction.
+const FETCH_CYCLES: i32 = 18;
+
+/// Machine cycles left to the interpreter in each frame.
+pub(crate) const VIP_FRAME_CYCLES: i32 = FRAME_CYCLES - DISPLAY_CYCLES;
+/// Fewest machine cycles an instruction takes, that of 6XNN.
+pub(crate) const VIP_MIN_CYCLES: i32 = FETCH_CYCLES + 6;
+
+/// How the instructions run in a frame are counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[serde(rename_all = "lowercase")]
+pub enum Timing {
+    /// Every instruction takes as long, running as many a second as the
+    /// emulator's speed.
+    #[default]
+    Fixed,
+    /// Every instruction takes about as many machine cycles as it did in
+    /// the COSMAC VIP's interpreter, ignoring the speed, so that ROMs
+    /// relying on the VIP's pace run like they did on it.
+    Vip,
+}
+
+#[cfg(feature = "std")]
+impl FromStr for Timing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Timing, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "fixed" => Ok(Timing::Fixed),
+            "vip" => Ok(Timing::Vip),
+            _ => Err(format!("unknown timing `{}`, expected fixed or vip", s)),
+        }
+    }
+}
+
+/// Returns about how many machine cycles the COSMAC VIP's interpreter took
+/// to execute `instruction`, including fetching it. Time spent waiting
+/// for the display or a key is counted separately.
+pub(crate) fn vip_cycles(instruction: Instruction) -> i32 {
+    let cycles = match instruction {
+        Instruction::Cls => 24,
+        Instruction::Sys(_) => 0,
+        Instruction::Ret | Instruction::Jump(_) | Instruction::Call(_) => 23,
+        Instruction::SkipEqByte { .. } | Instruction::SkipNeByte { .. } => 12,
+        Instruction::SkipEq { .. } | Instruction::SkipNe { .. } => 16,
+        Instruction::LoadByte { .. } => 6,
+        Instruction::AddByte { .. } => 10,
+        Instruction::Load { .. }
+        | Instruction::Or { .. }
+        | Instruction::And { .. }
+        | Instruction::Xor { .. }
+        | Instruction::Add { .. }
+        | Instruction::Sub { .. }
+        | Instruction::ShiftRight { .. }
+        | Instruction::SubN { .. }
+        | Instruction::ShiftLeft { .. } => 44,
+        Instruction::LoadI(_) => 12,
+        Instruction::JumpOffset { .. } => 23,
+        Instruction::Random { .. } => 36,
+        // Each row is shifted into place and XORed a byte at a time.
+        Instruction::Draw { n, .. } => 26 + 68 * n as i32,
+        Instruction::SkipKey { .. } | Instruction::SkipNotKey { .. } => 16,
+        Instruction::LoadDelay { .. }
+        | Instruction::WaitKey { .. }
+        | Instruction::SetDelay { .. }
+        | Instruction::SetSound { .. } => 10,
+        Instruction::AddI { .. } => 19,
+        Instruction::Font { .. } | Instruction::BigFont { .. } => 20,
+        // The digits are found by repeated subtraction.
+        Instruction::Bcd { .. } => 204,
+        Instruction::Store { x }
+        | Instruction::Restore { x }
+        | Instruction::SaveFlags { x }
+        | Instruction::LoadFlags { x } => 14 + 14 * (x as i32 + 1),
+    };
+    FETCH_CYCLES + cycles
+}
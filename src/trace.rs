@@ -0,0 +1,77 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use chip8::disasm::{Labels, Line};
+use chip8::{Chip8Error, Emulator};
+
+/// Logs every executed instruction along with the registers it changed:
+///
+/// ```text
+/// 0x200: 6A02  LD VA, 0x02          VA=02
+/// 0x202: A22A  LD I, 0x22A          I=0x22A
+/// ```
+pub struct Tracer {
+    out: BufWriter<Box<dyn Write>>,
+    labels: Labels,
+}
+
+impl Tracer {
+    /// Creates a tracer writing to the file at `path`, or to stderr when
+    /// it's `None`, naming addresses with `labels`.
+    pub fn new(path: Option<&Path>, labels: Labels) -> io::Result<Tracer> {
+        let out: Box<dyn Write> = match path {
+            Some(path) => Box::new(File::create(path)?),
+            None => Box::new(io::stderr()),
+        };
+        Ok(Tracer {
+            out: BufWriter::new(out),
+            labels,
+        })
+    }
+
+    /// Executes the instruction at PC like [`Emulator::step`] and logs it.
+    pub fn step(&mut self, emulator: &mut Emulator) -> Result<bool, Chip8Error> {
+        let Ok(opcode) = emulator.current_opcode() else {
+            return emulator.step();
+        };
+        let line = Line {
+            address: emulator.pc,
+            opcode,
+        };
+        let registers = emulator.var_registers;
+        let index_register = emulator.index_register;
+        let depth = emulator.stack.len();
+        let timers = (emulator.delay_timer, emulator.sound_timer);
+
+        let result = emulator.step();
+
+        let mut changes = String::new();
+        for (i, (old, new)) in registers.iter().zip(emulator.var_registers).enumerate() {
+            if *old != new {
+                write!(changes, " V{:X}={:02X}", i, new).unwrap();
+            }
+        }
+        if emulator.index_register != index_register {
+            write!(changes, " I={:#05X}", emulator.index_register).unwrap();
+        }
+        if emulator.stack.len() != depth {
+            write!(changes, " SP={}", emulator.stack.len()).unwrap();
+        }
+        if (emulator.delay_timer, emulator.sound_timer) != timers {
+            write!(
+                changes,
+                " DT={} ST={}",
+                emulator.delay_timer, emulator.sound_timer
+            )
+            .unwrap();
+        }
+        if let Err(e) = &result {
+            write!(changes, " error: {}", e).unwrap();
+        }
+        // A failing trace shouldn't stop the emulator, so errors are ignored.
+        let _ = writeln!(self.out, "{:<32}{}", line.labeled(&self.labels), changes);
+        result
+    }
+}
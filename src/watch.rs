@@ -0,0 +1,44 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Watches a ROM file for changes. Its directory is watched rather than the
+/// file itself, so that the file is still seen after tools which replace it
+/// instead of writing it in place.
+pub struct RomWatcher {
+    path: PathBuf,
+    events: Receiver<notify::Result<Event>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl RomWatcher {
+    pub fn new(path: &Path) -> notify::Result<RomWatcher> {
+        let path = path.canonicalize()?;
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(
+            path.parent().unwrap_or(Path::new("/")),
+            RecursiveMode::NonRecursive,
+        )?;
+        Ok(RomWatcher {
+            path,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns whether the file was written or replaced since the last call.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        for event in self.events.try_iter().flatten() {
+            changed |= matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event.paths.contains(&self.path);
+        }
+        changed
+    }
+}
@@ -0,0 +1,54 @@
+//! Golden-image checks shared by the integration tests: runs a ROM without a
+//! window and compares the final screen, as text art, with a known-good one
+//! stored in `tests/expected/`. After checking a run's output is correct, run
+//! with `CHIP8_BLESS=1` to write it there.
+
+use std::path::PathBuf;
+
+use chip8::Emulator;
+
+/// Runs `rom` for `cycles` instructions, ticking the timers as if running in
+/// real time at the default speed.
+pub fn run(rom: &[u8], cycles: u64) -> Emulator {
+    let mut emulator = Emulator::with_seed(0);
+    emulator.load_rom(rom).unwrap();
+    let mut executed = 0;
+    while executed < cycles {
+        for _ in 0..emulator.frame_instructions() {
+            if emulator.is_waiting() || executed == cycles {
+                break;
+            }
+            emulator.step().unwrap();
+            executed += 1;
+        }
+        emulator.tick_timers();
+    }
+    emulator
+}
+
+/// Compares the screen with the known-good one for `name`, or writes it as
+/// the known-good one when blessing. Returns false when there's none to
+/// compare with.
+pub fn check_screen(name: &str, emulator: &Emulator) -> bool {
+    let screen = emulator.display_string();
+    let expected_path = PathBuf::from("tests/expected")
+        .join(name)
+        .with_extension("txt");
+    if std::env::var_os("CHIP8_BLESS").is_some() {
+        std::fs::create_dir_all(expected_path.parent().unwrap()).unwrap();
+        std::fs::write(&expected_path, &screen).unwrap();
+        return true;
+    }
+    let Ok(expected) = std::fs::read_to_string(&expected_path) else {
+        return false;
+    };
+    assert!(
+        screen == expected,
+        "{} screen differs from {} (hash {:016x}):\n{}",
+        name,
+        expected_path.display(),
+        emulator.display_hash(),
+        screen
+    );
+    true
+}
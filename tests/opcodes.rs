@@ -0,0 +1,787 @@
+use chip8::analyze::{analyze, guess_quirks, scan, Diagnostic};
+use chip8::disasm::{Labels, Pattern};
+use chip8::palette::{Palette, Rgb};
+use chip8::{
+    asm, decode, Chip8Error, Emulator, Instruction, Platform, Quirks, Timing, BIG_FONT_ADDRESS,
+    FONT_SIZE, HIRES_HEIGHT, PROGRAM_ADDRESS, STACK_SIZE,
+};
+
+/// Assembles `source` and runs it for `frames` frames, at the default
+/// speed of about 11 instructions per frame.
+fn run(source: &str, frames: usize) -> Emulator {
+    run_with(Quirks::default(), source, frames)
+}
+
+fn run_with(quirks: Quirks, source: &str, frames: usize) -> Emulator {
+    let rom = asm::assemble(source).unwrap();
+    let mut emulator = Emulator::with_seed(0);
+    emulator.quirks = quirks;
+    emulator.load_rom(&rom).unwrap();
+    for _ in 0..frames {
+        emulator.run_frame(&[false; 16]).unwrap();
+    }
+    emulator
+}
+
+#[test]
+fn add_and_subtract_set_vf() {
+    let emulator = run(
+        "
+        LD V0, 0xFF
+        LD V1, 2
+        ADD V0, V1
+        LD V4, VF
+        LD V2, 5
+        LD V3, 7
+        SUB V2, V3
+        LD V5, VF
+        LD V6, 7
+        SUBN V6, V3
+        halt: JP halt
+        ",
+        10,
+    );
+    let v = emulator.var_registers;
+    assert_eq!((v[0], v[4]), (0x01, 1));
+    assert_eq!((v[2], v[5]), (0xFE, 0));
+    assert_eq!((v[6], v[0xF]), (0x00, 1));
+}
+
+#[test]
+fn shifts_and_logic() {
+    let emulator = run(
+        "
+        LD V0, 0b10000001
+        SHR V0
+        LD V1, VF
+        LD V2, 0b10000001
+        SHL V2
+        LD V3, VF
+        LD V4, 0b1100
+        LD V5, 0b1010
+        LD V6, V4
+        OR V6, V5
+        LD V7, V4
+        AND V7, V5
+        LD V8, V4
+        XOR V8, V5
+        halt: JP halt
+        ",
+        10,
+    );
+    let v = emulator.var_registers;
+    assert_eq!((v[0], v[1]), (0b01000000, 1));
+    assert_eq!((v[2], v[3]), (0b00000010, 1));
+    assert_eq!((v[6], v[7], v[8]), (0b1110, 0b1000, 0b0110));
+}
+
+#[test]
+fn skips() {
+    let emulator = run(
+        "
+        LD V0, 1
+        LD V1, 1
+        SE V0, V1
+        LD V2, 0xFF
+        SNE V0, V1
+        LD V3, 0xFF
+        SE V0, 2
+        LD V4, 0xFF
+        SNE V0, 1
+        LD V5, 0xFF
+        halt: JP halt
+        ",
+        10,
+    );
+    let v = emulator.var_registers;
+    assert_eq!((v[2], v[3], v[4], v[5]), (0, 0xFF, 0xFF, 0xFF));
+}
+
+#[test]
+fn bcd_store_and_load() {
+    let emulator = run(
+        "
+        LD V0, 123
+        LD I, 0x300
+        LD B, V0
+        LD V2, [I]
+        LD I, 0x310
+        LD [I], V2
+        halt: JP halt
+        ",
+        10,
+    );
+    assert_eq!(&emulator.var_registers[..3], &[1, 2, 3]);
+    assert_eq!(&emulator.memory[0x310..0x314], &[1, 2, 3, 0]);
+}
+
+#[test]
+fn calls_and_jumps() {
+    let emulator = run(
+        "
+        CALL sub
+        LD V0, 4
+        JP V0, table
+        sub:
+        LD V1, 1
+        RET
+        table:
+        LD V2, 1
+        LD V2, 2
+        LD V3, 3
+        halt: JP halt
+        ",
+        10,
+    );
+    let v = emulator.var_registers;
+    assert_eq!((v[1], v[2], v[3]), (1, 0, 3));
+    assert!(emulator.stack.is_empty());
+}
+
+#[test]
+fn self_modifying_code_is_decoded_again() {
+    let emulator = run(
+        "
+        LD V3, 0
+        loop: ADD V3, 1
+        SE V3, 2
+        JP loop
+        ; Turn the loop into a jump to itself.
+        LD I, loop
+        LD V0, 0x12
+        LD V1, 0x02
+        LD [I], V1
+        JP loop
+        ",
+        10,
+    );
+    assert_eq!(emulator.var_registers[3], 2);
+    assert_eq!(emulator.pc, 0x202);
+}
+
+#[test]
+fn draws_font_with_xor_and_collision() {
+    let source = "
+        LD V0, 0xA
+        LD F, V0
+        LD V1, 0
+        DRW V1, V1, 5
+        LD V2, VF
+        halt: JP halt
+        ";
+    let emulator = run(source, 10);
+    let screen = emulator.display_string();
+    let rows: Vec<&str> = screen.lines().map(|row| &row[..4]).collect();
+    assert_eq!(
+        &rows[..6],
+        &["####", "#..#", "####", "#..#", "#..#", "...."]
+    );
+    assert_eq!(emulator.var_registers[2], 0);
+
+    let emulator = run(&source.replace("halt:", "DRW V1, V1, 5\nhalt:"), 10);
+    assert!(!emulator.display.iter().flatten().any(|&pixel| pixel));
+    assert_eq!(emulator.var_registers[0xF], 1);
+}
+
+#[test]
+fn fx30_points_at_the_big_font() {
+    let emulator = run(
+        "
+        LD V0, 8
+        LD HF, V0
+        LD V1, 0
+        DRW V1, V1, 10
+        halt: JP halt
+        ",
+        10,
+    );
+    assert_eq!(emulator.index_register, BIG_FONT_ADDRESS + 8 * 10);
+    let screen = emulator.display_string();
+    let rows: Vec<&str> = screen.lines().map(|row| &row[..8]).collect();
+    assert_eq!(
+        &rows[..4],
+        &["########", "########", "##....##", "##....##"]
+    );
+}
+
+#[test]
+fn display_hash_depends_on_display() {
+    let blank = run("halt: JP halt", 10);
+    let digit = run("LD V0, 0\nLD F, V0\nDRW V0, V0, 5\nhalt: JP halt", 10);
+    assert_eq!(blank.display_hash(), Emulator::new().display_hash());
+    assert_ne!(blank.display_hash(), digit.display_hash());
+}
+
+#[test]
+fn vip_quirks() {
+    let source = "
+        LD V0, 0x81
+        LD V1, 0x02
+        LD VF, 1
+        OR V0, V0
+        LD V2, VF
+        SHR V0, V1
+        LD I, 0x300
+        LD [I], V0
+        halt: JP halt
+    ";
+    let modern = run(source, 10);
+    let vip = run_with(Platform::Chip8.quirks(), source, 10);
+    let (m, v) = (modern.var_registers, vip.var_registers);
+    // 8XY1 resets VF.
+    assert_eq!((m[2], v[2]), (1, 0));
+    // 8XY6 shifts VY into VX.
+    assert_eq!((m[0], v[0]), (0x40, 0x01));
+    // FX55 increments I by X + 1.
+    assert_eq!((modern.index_register, vip.index_register), (0x300, 0x301));
+}
+
+#[test]
+fn jump_vx_quirk() {
+    let source = "
+        LD V2, 2
+        LD V0, 0
+        JP V0, 0x208
+        skipped: JP skipped
+        plus_v0: JP plus_v0
+        plus_v2: JP plus_v2
+    ";
+    let quirks = Quirks {
+        jump_vx: true,
+        ..Quirks::default()
+    };
+    assert_eq!(run(source, 1).pc, 0x208);
+    assert_eq!(run_with(quirks, source, 1).pc, 0x20A);
+}
+
+#[test]
+fn quirks_can_be_flipped_while_running() {
+    let mut emulator = run(
+        "
+        LD V1, 4
+        LD V0, 0x10
+        SHR V0, V1
+        halt: JP halt
+    ",
+        1,
+    );
+    assert_eq!(emulator.var_registers[0], 0x08);
+    *emulator.quirks.get_mut("shift-vy").unwrap() = true;
+    emulator.pc = PROGRAM_ADDRESS + 2;
+    emulator.run_frame(&[false; 16]).unwrap();
+    assert_eq!(emulator.var_registers[0], 0x02);
+    assert!(emulator.quirks.shift_vy);
+    assert!(emulator.quirks.get_mut("shift").is_none());
+}
+
+#[test]
+fn vip_timing_charges_instructions_their_cycles() {
+    let run_vip = |source: &str, speed: u32| {
+        let rom = asm::assemble(source).unwrap();
+        let mut emulator = Emulator::with_seed(0);
+        emulator.set_speed(speed);
+        emulator.set_timing(Timing::Vip);
+        emulator.load_rom(&rom).unwrap();
+        emulator.run_frame(&[false; 16]).unwrap();
+        emulator.var_registers[0]
+    };
+    let adds = "
+        loop: ADD V0, 1
+        JP loop
+    ";
+    let draws = "
+        loop: ADD V0, 1
+        DRW V1, V1, 15
+        JP loop
+    ";
+    // The speed is ignored, and cheap instructions run more often.
+    assert_eq!(run_vip(adds, 1), run_vip(adds, 100_000));
+    assert!(run_vip(adds, 700) > 20);
+    assert!(run_vip(draws, 700) < 3);
+}
+
+#[test]
+fn display_generation_counts_changes() {
+    let blank = run("halt: JP halt", 10);
+    let digit = run("LD V0, 0\nLD F, V0\nDRW V0, V0, 5\nCLS\nhalt: JP halt", 10);
+    assert_eq!(blank.display_generation(), 0);
+    assert_eq!(digit.display_generation(), 2);
+}
+
+#[test]
+fn framebuffer_rgba_uses_the_palette() {
+    let emulator = run("LD V0, 0\nLD F, V0\nDRW V0, V0, 5\nhalt: JP halt", 10);
+    let palette = Palette {
+        colors: [Rgb(1, 2, 3), Rgb(4, 5, 6), Rgb(0, 0, 0), Rgb(0, 0, 0)],
+    };
+    let rgba = emulator.framebuffer_rgba(&palette);
+    // The top row of the 0 glyph is 0xF0.
+    assert_eq!(rgba[..4 * 4], [4, 5, 6, 0xFF].repeat(4));
+    assert_eq!(rgba[4 * 4..5 * 4], [1, 2, 3, 0xFF]);
+}
+
+#[test]
+fn random_numbers_follow_the_seed() {
+    let rom = asm::assemble("RND V0, 0xFF\nRND V1, 0xFF\nhalt: JP halt").unwrap();
+    let registers = |seed| {
+        let mut emulator = Emulator::with_seed(seed);
+        emulator.load_rom(&rom).unwrap();
+        emulator.run_frame(&[false; 16]).unwrap();
+        emulator.var_registers
+    };
+    assert_eq!(registers(1), registers(1));
+}
+
+#[test]
+fn decode_and_display_instructions() {
+    assert_eq!(decode(0xD125), Some(Instruction::Draw { x: 1, y: 2, n: 5 }));
+    assert_eq!(
+        decode(0xB310),
+        Some(Instruction::JumpOffset { x: 3, nnn: 0x310 })
+    );
+    assert_eq!(decode(0x0123), Some(Instruction::Sys(0x123)));
+    assert_eq!(decode(0x5121), None);
+    assert_eq!(decode(0xF30A).unwrap().to_string(), "LD V3, K");
+    assert_eq!(decode(0x7A0F).unwrap().to_string(), "ADD VA, 0x0F");
+}
+
+#[test]
+fn errors() {
+    let mut emulator = Emulator::new();
+    emulator.load_rom(&asm::assemble("RET").unwrap()).unwrap();
+    assert_eq!(
+        emulator.step(),
+        Err(Chip8Error::StackUnderflow { address: 0x200 })
+    );
+
+    let mut emulator = Emulator::new();
+    emulator
+        .load_rom(&asm::assemble("loop: CALL loop").unwrap())
+        .unwrap();
+    for _ in 0..STACK_SIZE {
+        emulator.step().unwrap();
+    }
+    assert_eq!(
+        emulator.step(),
+        Err(Chip8Error::StackOverflow { address: 0x200 })
+    );
+
+    let mut emulator = Emulator::new();
+    emulator.set_stack_limit(12);
+    emulator
+        .load_rom(&asm::assemble("loop: CALL loop").unwrap())
+        .unwrap();
+    for _ in 0..12 {
+        emulator.step().unwrap();
+    }
+    assert_eq!(
+        emulator.step(),
+        Err(Chip8Error::StackOverflow { address: 0x200 })
+    );
+    assert_eq!(emulator.stack.len(), 12);
+
+    let mut emulator = Emulator::new();
+    emulator.load_rom(&[0xF0, 0xFF]).unwrap();
+    assert_eq!(
+        emulator.step(),
+        Err(Chip8Error::UnknownOpcode {
+            opcode: 0xF0FF,
+            address: 0x200
+        })
+    );
+
+    let mut emulator = Emulator::new();
+    assert_eq!(
+        emulator.load_rom(&[0; 4096]).err(),
+        Some(Chip8Error::RomTooLarge {
+            size: 4096,
+            max: 4096 - 0x200
+        })
+    );
+}
+
+#[test]
+fn memory_bounds_and_protection() {
+    let rom = asm::assemble("LD I, 0x100\nLD [I], V0\nDRW V0, V0, 1").unwrap();
+    let mut emulator = Emulator::new();
+    emulator.load_rom(&rom).unwrap();
+    emulator.memory.protect_below(PROGRAM_ADDRESS);
+    emulator.step().unwrap();
+    assert_eq!(
+        emulator.step(),
+        Err(Chip8Error::WriteProtected { address: 0x100 })
+    );
+
+    emulator.pc = 0x204;
+    emulator.index_register = usize::MAX;
+    assert_eq!(
+        emulator.step(),
+        Err(Chip8Error::MemoryOutOfBounds {
+            address: usize::MAX
+        })
+    );
+}
+
+#[test]
+fn load_rom_at_sets_pc() {
+    let mut emulator = Emulator::with_seed(0);
+    // 6042: V0 = 0x42, then jump to itself at 0x602.
+    emulator
+        .load_rom_at(&[0x60, 0x42, 0x16, 0x02], 0x600)
+        .unwrap();
+    assert_eq!(emulator.pc, 0x600);
+    assert_eq!(emulator.load_address(), 0x600);
+    emulator.run_frame(&[false; 16]).unwrap();
+    assert_eq!(emulator.var_registers[0], 0x42);
+    assert_eq!(emulator.pc, 0x602);
+
+    let mut emulator = Emulator::with_seed(0);
+    assert_eq!(
+        emulator.load_rom_at(&[0; 0x201], 0xE00).err(),
+        Some(Chip8Error::RomTooLarge {
+            size: 0x201,
+            max: 0x200
+        })
+    );
+    assert_eq!(
+        emulator.load_rom_at(&[0x00], 0x1000).err(),
+        Some(Chip8Error::MemoryOutOfBounds { address: 0x1000 })
+    );
+}
+
+#[test]
+fn load_font_replaces_digits() {
+    let mut font = [0; FONT_SIZE];
+    // A digit A of a single row, 4 pixels wide.
+    font[0xA * 5] = 0xF0;
+    let rom = asm::assemble("LD V0, 0xA\nLD F, V0\nDRW V0, V0, 5\nhalt: JP halt").unwrap();
+    let mut emulator = Emulator::with_seed(0);
+    emulator.load_font(&font).unwrap().load_rom(&rom).unwrap();
+    emulator.run_frame(&[false; 16]).unwrap();
+    assert_eq!(emulator.font(), &font);
+    let lit: Vec<bool> = emulator.display.iter().flatten().copied().collect();
+    assert_eq!(lit.iter().filter(|&&pixel| pixel).count(), 4);
+
+    assert_eq!(
+        emulator.load_font(&[0; 64]).err(),
+        Some(Chip8Error::InvalidFont { size: 64 })
+    );
+}
+
+#[test]
+fn saved_states_restore_the_machine() {
+    let emulator = run(
+        "
+        CALL sub
+        sub: LD I, 0x300
+        LD V3, 0x42
+        LD F, V3
+        DRW V0, V0, 5
+        halt: JP halt
+        ",
+        1,
+    );
+    let state = emulator.save_state();
+    let mut loaded = Emulator::with_seed(1);
+    loaded.load_state(&state).unwrap();
+    assert_eq!(loaded.save_state(), state);
+    assert_eq!(loaded.display_string(), emulator.display_string());
+    assert_eq!(loaded.registers_string(), emulator.registers_string());
+    assert_eq!(&loaded.stack[..], &emulator.stack[..]);
+
+    assert_eq!(
+        loaded.load_state(&state[1..]),
+        Err(Chip8Error::InvalidState)
+    );
+    let mut corrupt = state;
+    corrupt[0] = b'X';
+    assert_eq!(loaded.load_state(&corrupt), Err(Chip8Error::InvalidState));
+}
+
+#[test]
+fn rpl_flags_survive_reset() {
+    let mut emulator = run(
+        "
+        LD V0, 1
+        LD V1, 2
+        LD V2, 3
+        LD R, V1
+        LD V0, 0
+        LD V1, 0
+        LD V2, 0
+        LD V2, R
+        halt: JP halt
+        ",
+        10,
+    );
+    assert_eq!(&emulator.var_registers[..3], &[1, 2, 0]);
+    assert_eq!(&emulator.flags[..3], &[1, 2, 0]);
+    emulator.replace(Emulator::with_seed(0));
+    assert_eq!(&emulator.flags[..3], &[1, 2, 0]);
+}
+
+#[test]
+fn reset_restores_the_loaded_rom() {
+    let source = "
+        LD I, data
+        LD V0, 0x12
+        LD [I], V0
+        LD V5, 7
+        CALL halt
+        halt: JP halt
+        data: DB 0xAB
+        ";
+    let rom = asm::assemble(source).unwrap();
+    let mut emulator = run(source, 10);
+    let data = PROGRAM_ADDRESS + rom.len() - 1;
+    assert_eq!(emulator.memory[data], 0x12);
+    emulator.flags[0] = 1;
+    emulator.set_speed(100);
+
+    emulator.reset();
+    assert_eq!(&emulator.memory[PROGRAM_ADDRESS..data + 1], &rom[..]);
+    assert_eq!(emulator.pc, PROGRAM_ADDRESS);
+    assert_eq!(emulator.var_registers, [0; 16]);
+    assert!(emulator.stack.is_empty());
+    assert_eq!((emulator.flags[0], emulator.speed()), (1, 100));
+    for _ in 0..10 {
+        emulator.step().unwrap();
+    }
+    assert_eq!(emulator.memory[data], 0x12);
+
+    emulator.hard_reset();
+    assert_eq!(emulator.memory[PROGRAM_ADDRESS], 0);
+    assert_eq!(emulator.memory[data], 0);
+    assert_eq!((emulator.flags[0], emulator.speed()), (0, 100));
+}
+
+#[test]
+fn run_frame_takes_keys_and_reports_sound() {
+    let rom = asm::assemble(
+        "
+        LD V0, 2
+        LD ST, V0
+        LD V1, 3
+        SKNP V1
+        LD V2, 1
+        halt: JP halt
+        ",
+    )
+    .unwrap();
+    let mut emulator = Emulator::with_seed(0);
+    emulator.load_rom(&rom).unwrap();
+    let mut keys = [false; 16];
+    keys[3] = true;
+    let frame = emulator.run_frame(&keys).unwrap();
+    assert_eq!(emulator.var_registers[2], 1);
+    assert!(frame.sound);
+    assert!(!frame.display_changed);
+    assert!(!emulator.run_frame(&keys).unwrap().sound);
+}
+
+#[test]
+fn fx0a_waits_for_release() {
+    let rom = asm::assemble("LD V0, K\nLD V1, 1\nhalt: JP halt").unwrap();
+    let mut pressed = [false; 16];
+    pressed[5] = true;
+    let mut emulator = Emulator::with_seed(0);
+    emulator.load_rom(&rom).unwrap();
+    emulator.run_frame(&[false; 16]).unwrap();
+    emulator.run_frame(&pressed).unwrap();
+    assert_eq!((emulator.pc, emulator.var_registers[1]), (0x200, 0));
+    emulator.run_frame(&[false; 16]).unwrap();
+    assert_eq!(&emulator.var_registers[..2], &[5, 1]);
+
+    let mut emulator = Emulator::with_seed(0);
+    emulator.quirks.key_press = true;
+    emulator.load_rom(&rom).unwrap();
+    emulator.run_frame(&pressed).unwrap();
+    assert_eq!(&emulator.var_registers[..2], &[5, 1]);
+}
+
+#[test]
+fn latched_keys_are_seen_by_the_next_check() {
+    let rom = asm::assemble("LD V0, 7\nSKNP V0\nLD V1, 1\nhalt: JP halt").unwrap();
+    for latch_keys in [false, true] {
+        let mut emulator = Emulator::with_seed(0);
+        emulator.latch_keys = latch_keys;
+        emulator.load_rom(&rom).unwrap();
+        emulator.set_key(7, true);
+        emulator.set_key(7, false);
+        for _ in 0..3 {
+            emulator.step().unwrap();
+        }
+        assert_eq!(emulator.var_registers[1], latch_keys as u8);
+    }
+}
+
+#[test]
+fn sprites_clip_or_wrap_at_the_edges() {
+    let source = "
+        LD V0, 62
+        LD V1, 30
+        LD F, V2
+        DRW V0, V1, 5
+        LD V0, 0
+        LD V1, 0
+        DRW V0, V1, 5
+        halt: JP halt
+    ";
+    let emulator = run(source, 2);
+    assert_eq!(emulator.display[30][62..], [true, true]);
+    assert_eq!(emulator.display[31][62..], [true, false]);
+    assert_eq!(emulator.display[30][..2], [false, false]);
+    assert_eq!(emulator.display[0][62..], [false, false]);
+    assert_eq!(emulator.var_registers[0xF], 0);
+
+    let quirks = Quirks {
+        wrap: true,
+        ..Quirks::default()
+    };
+    let emulator = run_with(quirks, source, 2);
+    assert_eq!(emulator.display[30][..2], [true, true]);
+    assert_eq!(emulator.display[0][62..], [true, false]);
+    assert!(!emulator.display[0][1]);
+    assert_eq!(emulator.var_registers[0xF], 1);
+
+    // Octo wraps, so the XO-CHIP preset does.
+    assert!(Platform::XoChip.quirks().wrap);
+    let emulator = run_with(Platform::XoChip.quirks(), source, 2);
+    assert_eq!(emulator.display[30][..2], [true, true]);
+}
+
+#[test]
+fn hires_roms_use_a_taller_display() {
+    let mut rom = vec![0x12, 0x60];
+    rom.resize(0xC0, 0);
+    rom.extend(asm::assemble("LD V1, 40\nLD F, V0\nDRW V0, V1, 1\nDW 0x0230").unwrap());
+    let mut emulator = Emulator::with_seed(0);
+    emulator.load_rom(&rom).unwrap();
+    assert_eq!((emulator.height(), emulator.pc), (HIRES_HEIGHT, 0x2C0));
+    for _ in 0..3 {
+        emulator.step().unwrap();
+    }
+    assert_eq!(emulator.display[40][..5], [true, true, true, true, false]);
+    emulator.step().unwrap();
+    assert!(emulator.display.iter().flatten().all(|&pixel| !pixel));
+}
+
+#[test]
+fn analyze_follows_reachable_code() {
+    let rom = asm::assemble(
+        "
+        CALL sub
+        JP 0x100
+        sub: SE V0, 1
+        DW 0x00FF
+        RET
+        DW 0xFFFF
+        ",
+    )
+    .unwrap();
+    assert_eq!(
+        analyze(&rom, PROGRAM_ADDRESS),
+        [
+            Diagnostic::JumpBelowProgram {
+                address: 0x202,
+                target: 0x100
+            },
+            Diagnostic::Unsupported {
+                address: 0x206,
+                opcode: 0x00FF,
+                extension: Some("SUPER-CHIP")
+            },
+        ]
+    );
+    assert_eq!(
+        analyze(&[0; 0xE01], PROGRAM_ADDRESS),
+        [Diagnostic::TooLarge {
+            size: 0xE01,
+            max: 0xE00
+        }]
+    );
+}
+
+#[test]
+fn labels_name_subroutines_and_targets() {
+    let rom = asm::assemble("CALL sub\nhalt: JP halt\nsub: LD I, 0x300\nRET").unwrap();
+    let mut labels = Labels::infer(&rom, PROGRAM_ADDRESS);
+    assert_eq!(labels.get(0x204), Some("sub_0x204"));
+    assert_eq!(labels.get(0x202), None);
+    labels.extend(Labels::parse("; sprites\n0x300 player\n0x202 halt").unwrap());
+    assert_eq!(labels.mnemonic(0x2204), "CALL sub_0x204");
+    assert_eq!(labels.mnemonic(0x1202), "JP halt");
+    assert_eq!(labels.mnemonic(0xA300), "LD I, player");
+    assert!(Labels::parse("0x300").is_err());
+}
+
+#[test]
+fn opcode_patterns_match_their_digits() {
+    let draw: Pattern = "dxyn".parse().unwrap();
+    assert!(draw.matches(0xD125) && !draw.matches(0x6125));
+    assert_eq!(draw.to_string(), "DXYN");
+    // All digits, which the debugger would otherwise read as an address.
+    let call: Pattern = "1228".parse().unwrap();
+    assert!(call.matches(0x1228) && !call.matches(0x1229));
+    assert!("12".parse::<Pattern>().is_err() && "12G8".parse::<Pattern>().is_err());
+}
+
+#[test]
+fn guess_quirks_from_idioms() {
+    let rom = asm::assemble(
+        "
+        SHR V1, V2
+        LD I, store
+        store: LD [I], V0
+        LD V3, 2
+        DW 0xB310
+        ",
+    )
+    .unwrap();
+    let (quirks, reasons) = guess_quirks(&rom, PROGRAM_ADDRESS, Quirks::default());
+    assert_eq!(
+        quirks,
+        Quirks {
+            shift_vy: true,
+            memory_increment: true,
+            jump_vx: true,
+            ..Quirks::default()
+        }
+    );
+    assert_eq!(reasons.len(), 3);
+}
+
+#[test]
+fn scan_finds_extensions_and_quirks() {
+    let rom = asm::assemble(
+        "
+        LD F, V0
+        DRW V0, V1, 5
+        DW 0xF130
+        SHR V1, V2
+        DW 0x00FF
+        ",
+    )
+    .unwrap();
+    let scan = scan(&rom, PROGRAM_ADDRESS);
+    assert_eq!(scan.extensions.get("SUPER-CHIP"), Some(&0x204));
+    assert_eq!(scan.unsupported, [(0x208, 0x00FF)]);
+    assert_eq!(scan.platform, Platform::Schip);
+    assert!(scan.quirks.jump_vx && scan.quirks.shift_vy);
+    assert_eq!(scan.ambiguous.get("shift_vy"), Some(&1));
+    assert_eq!(scan.ambiguous.get("wrap"), Some(&1));
+
+    // MEGA-CHIP isn't emulated, so switching to it stops the ROM.
+    let mega = chip8::analyze::scan(&[0x00, 0x11, 0x03, 0x10], PROGRAM_ADDRESS);
+    assert_eq!(mega.extensions.get("MEGA-CHIP"), Some(&0x200));
+    assert_eq!(mega.unsupported, [(0x200, 0x0011)]);
+}
+
+#[test]
+fn labels_cannot_shadow_registers() {
+    for source in ["dt: JP dt", "LD V0, 1\nv1: JP v1", "I: JP 0x200"] {
+        let error = asm::assemble(source).unwrap_err();
+        assert!(error.message.contains("reserved name"), "{}", error);
+    }
+    assert!(asm::assemble("vx: JP vx\ndtx: JP dtx").is_ok());
+}
@@ -0,0 +1,24 @@
+//! Runs the ROMs bundled in `rom/` and compares their screens with the
+//! known-good ones in `tests/expected/`.
+
+mod golden;
+
+fn check(rom: &str, cycles: u64) {
+    let data = std::fs::read(format!("rom/{}", rom)).unwrap();
+    let emulator = golden::run(&data, cycles);
+    assert!(
+        golden::check_screen(rom, &emulator),
+        "no known-good screen for {}, run with CHIP8_BLESS=1 to write it",
+        rom
+    );
+}
+
+#[test]
+fn ibm_logo() {
+    check("IBMLogo.ch8", 100);
+}
+
+#[test]
+fn breakout() {
+    check("br8kout.ch8", 5000);
+}
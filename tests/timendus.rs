@@ -0,0 +1,59 @@
+//! Runs the ROMs of the Timendus CHIP-8 test suite
+//! (<https://github.com/Timendus/chip8-test-suite>) and compares the final
+//! screen with a known-good one.
+//!
+//! The ROMs aren't redistributed with the emulator, so the tests are ignored
+//! by default: download them into `tests/roms/`, or point `CHIP8_TEST_ROMS`
+//! at a directory containing them, and run `cargo test -- --ignored`. A
+//! missing ROM or known-good screen then fails the test.
+
+mod golden;
+
+use std::path::PathBuf;
+
+/// Test ROMs and the number of instructions they need to finish.
+const SUITE: &[(&str, u64)] = &[
+    ("1-chip8-logo.ch8", 700),
+    ("2-ibm-logo.ch8", 700),
+    ("3-corax+.ch8", 1400),
+    ("4-flags.ch8", 1400),
+];
+
+fn check(rom: &str) {
+    let cycles = SUITE.iter().find(|(name, _)| *name == rom).unwrap().1;
+    let dir = std::env::var_os("CHIP8_TEST_ROMS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("tests/roms"));
+    let data = std::fs::read(dir.join(rom))
+        .unwrap_or_else(|e| panic!("failed to read {} in {}: {}", rom, dir.display(), e));
+    let emulator = golden::run(&data, cycles);
+    assert!(
+        golden::check_screen(rom, &emulator),
+        "no known-good screen for {}, run with CHIP8_BLESS=1 to write it",
+        rom
+    );
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite's ROMs"]
+fn chip8_logo() {
+    check("1-chip8-logo.ch8");
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite's ROMs"]
+fn ibm_logo() {
+    check("2-ibm-logo.ch8");
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite's ROMs"]
+fn corax_plus() {
+    check("3-corax+.ch8");
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite's ROMs"]
+fn flags() {
+    check("4-flags.ch8");
+}